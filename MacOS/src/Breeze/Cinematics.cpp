@@ -21,9 +21,38 @@ const CinematicCharacter* CinematicPlayer::Speaker() const {
   return enum_indexed_sparse_array_get_ptr(characters, *speaker);
 }
 
+CinematicFlagValue StoryFlags::Get(const CinematicFlag flag) const {
+  const auto it = values.find(flag);
+  return it == values.end() ? 0 : it->second;
+}
+
+void StoryFlags::Set(const CinematicFlag flag, const CinematicFlagValue value) {
+  values[flag] = value;
+}
+
+bool StoryFlags::Test(const CinematicCondition& condition) const {
+  const CinematicFlagValue value = Get(condition.flag);
+  switch (condition.comparison) {
+    case CinematicComparison::Eq:
+      return value == condition.value;
+    case CinematicComparison::Ne:
+      return value != condition.value;
+    case CinematicComparison::Lt:
+      return value < condition.value;
+    case CinematicComparison::Le:
+      return value <= condition.value;
+    case CinematicComparison::Gt:
+      return value > condition.value;
+    case CinematicComparison::Ge:
+      return value >= condition.value;
+  }
+  throw std::invalid_argument("Unknown cinematic comparison");
+}
+
 bool CinematicPlayer::Apply(const CinematicCommand& command) {
   // ReSharper disable CppRedundantElseKeywordInsideCompoundStatement
   // ReSharper disable CppDeclarationHidesLocal
+  jump.reset();
   if (std::holds_alternative<CinematicCommandCommit>(command)) {
     return true;
   } else if (const auto cmd = std::get_if<CinematicCommandSetCharacter>(&command)) {
@@ -60,6 +89,24 @@ bool CinematicPlayer::Apply(const CinematicCommand& command) {
     material = cmd->material;
   } else if (std::holds_alternative<CinematicCommandClearMaterial>(command)) {
     material.reset();
+  } else if (const auto cmd = std::get_if<CinematicCommandSetFlag>(&command)) {
+    flags.Set(cmd->flag, cmd->value);
+  } else if (const auto cmd = std::get_if<CinematicCommandAddFlag>(&command)) {
+    flags.Set(
+        cmd->flag, static_cast<CinematicFlagValue>(flags.Get(cmd->flag) + cmd->amount)
+    );
+  } else if (const auto cmd = std::get_if<CinematicCommandJump>(&command)) {
+    jump = cmd->target;
+  } else if (const auto cmd = std::get_if<CinematicCommandJumpUnless>(&command)) {
+    if (!flags.Test(cmd->condition)) {
+      jump = cmd->target;
+    }
+  } else if (const auto cmd = std::get_if<CinematicCommandChoice>(&command)) {
+    choices = cmd->options;
+    return true;
+  } else if (std::holds_alternative<CinematicCommandPanCamera>(command) ||
+             std::holds_alternative<CinematicCommandShakeCamera>(command)) {
+    // There's no map behind the stage to move.
   } else {
     throw std::invalid_argument("Unknown cinematic command type");
   }
@@ -76,6 +123,8 @@ void CinematicPlayer::Reset() {
   speaker.reset();
   text.reset();
   material.reset();
+  choices.clear();
+  jump.reset();
 
   std::vector<CinematicCommand> v{
       CinematicCommandClearBackground{},
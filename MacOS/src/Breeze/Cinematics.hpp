@@ -2,8 +2,10 @@
 
 #include <better-enums/enum.h>
 
+#include <map>
 #include <string>
 #include <variant>
+#include <vector>
 
 #include "Alchemy.hpp"
 #include "EnumSet.hpp"
@@ -22,10 +24,25 @@ using CinematicText = std::string;
 
 using CinematicBackground = size_t;
 
+/// Materials are shown by their icon, so this is the icon's sprite index,
+/// looked up from `items.json` when the cinematic is compiled.
+using CinematicMaterial = size_t;
+
+/// Story flag ID: position in `cinematics/story_flags.txt`.
+using CinematicFlag = size_t;
+
+/// Int flags are 16-bit. Bool flags are 0 for false and 1 for true.
+using CinematicFlagValue = int16_t;
+
+/// Index of a command in a cinematic.
+using CinematicPosition = size_t;
+
 // NOLINTBEGIN(*-explicit-constructor, *-no-recursion)
 
 BETTER_ENUM(CinematicCharacterSlot, uint8_t, Left, Right)
 
+BETTER_ENUM(CinematicComparison, uint8_t, Eq, Ne, Lt, Le, Gt, Ge)
+
 // NOLINTEND(*-explicit-constructor, *-no-recursion)
 
 template <typename Enum, typename Element>
@@ -80,11 +97,76 @@ struct CinematicCommandSetBackground {
 struct CinematicCommandClearBackground {};
 
 struct CinematicCommandSetMaterial {
-  Material::ID material;
+  CinematicMaterial material;
 };
 
 struct CinematicCommandClearMaterial {};
 
+struct CinematicCommandSetFlag {
+  CinematicFlag flag;
+  CinematicFlagValue value;
+};
+
+/// Int flags only.
+struct CinematicCommandAddFlag {
+  CinematicFlag flag;
+  CinematicFlagValue amount;
+};
+
+struct CinematicCommandJump {
+  CinematicPosition target;
+};
+
+struct CinematicCondition {
+  CinematicFlag flag;
+  CinematicComparison comparison;
+  CinematicFlagValue value;
+};
+
+struct CinematicCommandJumpUnless {
+  CinematicCondition condition;
+  CinematicPosition target;
+};
+
+struct CinematicChoice {
+  CinematicText text;
+  /// Where to go on from if this is picked.
+  CinematicPosition target;
+};
+
+/// The page is ready to display, with options to pick one of.
+struct CinematicCommandChoice {
+  std::vector<CinematicChoice> options;
+};
+
+/// Moves the view of the map behind a WASM-4 cinematic. The Mac edition doesn't
+/// show the map behind cinematics, so this does nothing here.
+struct CinematicCommandPanCamera {
+  int16_t x;
+  int16_t y;
+  uint16_t frames;
+};
+
+/// Shakes the view of the map behind a WASM-4 cinematic. Does nothing here,
+/// like `CinematicCommandPanCamera`.
+struct CinematicCommandShakeCamera {
+  uint16_t magnitude;
+  uint16_t frames;
+};
+
+/// Story flags set and tested by cinematics. These belong to the game,
+/// so that a cinematic can test flags set by an earlier one.
+class StoryFlags {
+ public:
+  /// Flags that haven't been set are 0.
+  [[nodiscard]] CinematicFlagValue Get(CinematicFlag flag) const;
+  void Set(CinematicFlag flag, CinematicFlagValue value);
+  [[nodiscard]] bool Test(const CinematicCondition& condition) const;
+
+ private:
+  std::map<CinematicFlag, CinematicFlagValue> values;
+};
+
 /// State delta.
 using CinematicCommand = std::variant<
     CinematicCommandCommit,
@@ -98,24 +180,40 @@ using CinematicCommand = std::variant<
     CinematicCommandSetBackground,
     CinematicCommandClearBackground,
     CinematicCommandSetMaterial,
-    CinematicCommandClearMaterial>;
+    CinematicCommandClearMaterial,
+    CinematicCommandSetFlag,
+    CinematicCommandAddFlag,
+    CinematicCommandJump,
+    CinematicCommandJumpUnless,
+    CinematicCommandChoice,
+    CinematicCommandPanCamera,
+    CinematicCommandShakeCamera>;
 
 /// Accumulates state.
 struct CinematicPlayer {
+  explicit CinematicPlayer(StoryFlags& flags) : flags(flags) {}
+
   std::optional<CinematicBackground> background;
   EnumIndexedSparseArray<CinematicCharacterSlot, CinematicCharacter> characters;
   std::optional<CinematicCharacterSlot> speaker;
   std::optional<CinematicText> text;
-  std::optional<Material::ID> material;
+  std::optional<CinematicMaterial> material;
+  /// Options to pick from, if the page is a choice.
+  std::vector<CinematicChoice> choices;
+  /// Where to go next, if the last command was a jump that was taken.
+  std::optional<CinematicPosition> jump;
+  /// Shared with every other cinematic, and kept when this one is reset.
+  StoryFlags& flags;
 
   [[nodiscard]] const CinematicCharacter* Left() const;
   [[nodiscard]] const CinematicCharacter* Right() const;
   [[nodiscard]] const CinematicCharacter* Speaker() const;
 
-  /// Returns true when a commit is received and the page should be shown.
+  /// Returns true when a commit or choice is received and the page should be
+  /// shown. After a jump, `jump` says where to go next.
   [[nodiscard]] bool Apply(const CinematicCommand& command);
 
-  /// Discard all state.
+  /// Discard all state except story flags.
   void Reset();
 };
 
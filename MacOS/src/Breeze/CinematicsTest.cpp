@@ -0,0 +1,75 @@
+#include <catch2/catch_test_macros.hpp>
+
+#include "Cinematics.hpp"
+
+using namespace Breeze;
+
+TEST_CASE("cinematic flags") {
+  StoryFlags flags;
+  CinematicPlayer player(flags);
+  REQUIRE(flags.Get(1) == 0);
+
+  REQUIRE(!player.Apply(CinematicCommandSetFlag{.flag = 1, .value = 2}));
+  REQUIRE(!player.Apply(CinematicCommandAddFlag{.flag = 1, .amount = -3}));
+  REQUIRE(flags.Get(1) == -1);
+
+  REQUIRE(flags.Test(CinematicCondition{
+      .flag = 1, .comparison = CinematicComparison::Lt, .value = 0
+  }));
+  REQUIRE(!flags.Test(CinematicCondition{
+      .flag = 0, .comparison = CinematicComparison::Ne, .value = 0
+  }));
+}
+
+TEST_CASE("cinematic flags outlast the cinematic that set them") {
+  StoryFlags flags;
+  {
+    CinematicPlayer player(flags);
+    REQUIRE(!player.Apply(CinematicCommandSetFlag{.flag = 0, .value = 1}));
+    player.Reset();
+  }
+  REQUIRE(flags.Get(0) == 1);
+
+  CinematicPlayer player(flags);
+  REQUIRE(!player.Apply(CinematicCommandJumpUnless{
+      .condition =
+          {.flag = 0, .comparison = CinematicComparison::Ne, .value = 0},
+      .target = 7,
+  }));
+  REQUIRE(!player.jump.has_value());
+}
+
+TEST_CASE("cinematic jumps") {
+  StoryFlags flags;
+  CinematicPlayer player(flags);
+
+  REQUIRE(!player.Apply(CinematicCommandJump{.target = 5}));
+  REQUIRE(player.jump == 5);
+
+  const CinematicCommandJumpUnless jumpUnlessSet{
+      .condition =
+          {.flag = 0, .comparison = CinematicComparison::Ne, .value = 0},
+      .target = 7,
+  };
+  REQUIRE(!player.Apply(jumpUnlessSet));
+  REQUIRE(player.jump == 7);
+
+  REQUIRE(!player.Apply(CinematicCommandSetFlag{.flag = 0, .value = 1}));
+  REQUIRE(!player.jump.has_value());
+  REQUIRE(!player.Apply(jumpUnlessSet));
+  REQUIRE(!player.jump.has_value());
+}
+
+TEST_CASE("cinematic choices") {
+  StoryFlags flags;
+  CinematicPlayer player(flags);
+
+  REQUIRE(player.Apply(CinematicCommandChoice{
+      .options = {{.text = "Yes", .target = 3}, {.text = "No", .target = 9}}
+  }));
+  REQUIRE(player.choices.size() == 2);
+  REQUIRE(player.choices[1].target == 9);
+
+  player.Reset();
+  REQUIRE(player.choices.empty());
+}
@@ -1,8 +1,5 @@
 #include "CinematicGameMode.hpp"
 
-#include <sstream>
-#include <stdexcept>
-
 #include "AppResources.h"
 
 namespace AtelierEsri {
@@ -17,8 +14,9 @@ CinematicGameMode::CinematicGameMode(
       window(atelierInteriorWINDResourceID),
       forwardButton(cinematicForwardButtonCNTLResourceID, window),
       backButton(cinematicBackButtonCNTLResourceID, window),
+      player(game.Flags()),
       cinematic(cinematic),
-      position(cinematic.cbegin()) {
+      position(0) {
   window.onUpdate = [&]([[maybe_unused]] const Window& window) { Draw(); };
   window.Title(name);
 
@@ -27,6 +25,9 @@ CinematicGameMode::CinematicGameMode(
   };
 
   backButton.onClick = [&]([[maybe_unused]] const Button& button) { Back(); };
+
+  window.onContentMouseDown = [&]([[maybe_unused]] const Window& window,
+                                  const Point point) { Click(point); };
 }
 
 // Used in lambda above.
@@ -37,6 +38,26 @@ void CinematicGameMode::Forward() { Advance(); }
 // ReSharper disable once CppDFAUnreachableFunctionCall
 void CinematicGameMode::Back() { Reset(); }
 
+// ReSharper disable once CppDFAUnreachableFunctionCall
+void CinematicGameMode::Click(V2I point) {
+  if (player.choices.empty() || !TextLinesRect.Contains(point)) {
+    return;
+  }
+  point -= TextLinesRect.origin;
+  const size_t index = point.y / TextLineHeight;
+  if (index < player.choices.size()) {
+    Choose(index);
+  }
+}
+
+// ReSharper disable once CppDFAUnreachableFunctionCall
+void CinematicGameMode::Choose(const size_t index) {
+  position = player.choices[index].target;
+  player.choices.clear();
+  forwardButton.Enabled(true);
+  Advance();
+}
+
 // ReSharper disable once CppDFAUnreachableFunctionCall
 void CinematicGameMode::Draw() const {
   const GWorldActiveGuard activeGuard = window.MakeActivePort();
@@ -64,7 +85,23 @@ void CinematicGameMode::Draw() const {
     game.MainSpriteSheet().Draw(*rightCharacter, RightSlotCharacterRect);
   }
 
-  if (text) {
+  if (!player.choices.empty()) {
+    // Choices take over the text box, one per line. Click one to pick it.
+    game.MainSpriteSheet().Draw9Patch(Border, TextDecorationRect);
+    const Rect rect = TextLinesRect;
+    FillRect(&rect, &pattern);
+
+    const ChangeClip changeClip{TextLinesRect};
+    ForeColor(whiteColor);
+    for (size_t i = 0; i < player.choices.size(); i++) {
+      QD::MoveTo(
+          TextLinesRect.origin +
+          V2I{0, static_cast<int>(i + 1) * TextLineHeight}
+      );
+      QD::DrawText("\xa5 " + player.choices[i].text);
+    }
+    ForeColor(blackColor);
+  } else if (text) {
     // TODO: speaker indicator
 
     game.MainSpriteSheet().Draw9Patch(Border, TextDecorationRect);
@@ -73,7 +110,7 @@ void CinematicGameMode::Draw() const {
 
     // TODO: use TextEdit to draw multiple lines correctly
     const ChangeClip changeClip{TextLinesRect};
-    QD::MoveTo(TextLinesRect.origin + V2I{0, 12});
+    QD::MoveTo(TextLinesRect.origin + V2I{0, TextLineHeight});
     ForeColor(whiteColor);
     QD::DrawText(*text);
     ForeColor(blackColor);
@@ -95,8 +132,15 @@ void CinematicGameMode::Invalidate() const {
 
 // ReSharper disable once CppDFAUnreachableFunctionCall
 void CinematicGameMode::Advance() {
-  for (; position != cinematic.cend(); ++position) {
-    if (player.Apply(*position)) {
+  while (position < cinematic.size()) {
+    const bool show = player.Apply(cinematic[position]);
+    if (player.jump) {
+      position = *player.jump;
+    } else {
+      ++position;
+    }
+
+    if (show) {
       // Convert player IDs to resources and sprites.
 
       // These are not even remotely the same branch.
@@ -126,25 +170,15 @@ void CinematicGameMode::Advance() {
 
       text = player.text;
 
-      if (player.material) {
-        const auto& catalog = game.Catalog();
-        const size_t id = *player.material;
-        if (id >= catalog.size()) {
-          std::stringstream message;
-          message << "Invalid cinematic: material ID " << id
-                  << " is missing from the game's material catalog";
-          throw std::invalid_argument(message.str());
-        }
-        material = catalog[id].spriteIndex;
-      } else {
-        material.reset();
+      material = player.material;
+
+      // Wait for a choice to be picked instead.
+      if (!player.choices.empty()) {
+        forwardButton.Enabled(false);
       }
 
       Invalidate();
 
-      // Advance past the commit command.
-      ++position;
-
       return;
     }
   }
@@ -157,7 +191,7 @@ void CinematicGameMode::Advance() {
 
 // ReSharper disable once CppDFAUnreachableFunctionCall
 void CinematicGameMode::Reset() {
-  position = cinematic.cbegin();
+  position = 0;
   player.Reset();
 
   background.reset();
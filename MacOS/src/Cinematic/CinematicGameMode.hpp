@@ -26,6 +26,9 @@ class CinematicGameMode final : public GameMode {
   void Draw() const;
   void Invalidate() const;
 
+  void Click(V2I point);
+  void Choose(size_t index);
+
   void Advance();
   void Reset();
 
@@ -35,7 +38,7 @@ class CinematicGameMode final : public GameMode {
 
   Breeze::CinematicPlayer player;
   const std::vector<Breeze::CinematicCommand>& cinematic;
-  std::vector<Breeze::CinematicCommand>::const_iterator position;
+  Breeze::CinematicPosition position;
 
   std::optional<Picture> background;
   std::optional<SpriteSheet::SpriteIndex> leftCharacter;
@@ -57,6 +60,7 @@ class CinematicGameMode final : public GameMode {
 
   static constexpr R2I TextDecorationRect{{100, 200}, {200, 80}};
   static constexpr R2I TextLinesRect{{110, 210}, {180, 60}};
+  static constexpr int TextLineHeight = 12;
 };
 
 }  // namespace AtelierEsri
@@ -86,6 +86,8 @@ const std::vector<Material>& Game::Catalog() const { return catalog; }
 
 Breeze::PlayerInventory& Game::Inventory() { return inventory; }
 
+Breeze::StoryFlags& Game::Flags() { return flags; }
+
 Breeze::Quality Game::PlayerMaxQuality() { return 120; }
 
 int Game::PlayerMaxPlacements() { return 5; }
@@ -1,6 +1,7 @@
 #pragma once
 
 #include "Breeze/Alchemy.hpp"
+#include "Breeze/Cinematics.hpp"
 #include "MaskedImage.hpp"
 #include "Material.hpp"
 #include "SpriteSheet.hpp"
@@ -69,6 +70,10 @@ class Game {
   /// Intentionally mutable: some game modes will modify this.
   [[nodiscard]] Breeze::PlayerInventory& Inventory();
 
+  /// Story flags, set and tested by cinematics.
+  /// Intentionally mutable: cinematics set them.
+  [[nodiscard]] Breeze::StoryFlags& Flags();
+
   // TODO: extract these to a Breeze alchemy level/skills class
   [[nodiscard]] Breeze::Quality PlayerMaxQuality();
   [[nodiscard]] int PlayerMaxPlacements();
@@ -86,6 +91,7 @@ class Game {
   // TODO: save/load games
 
   Breeze::PlayerInventory inventory;
+  Breeze::StoryFlags flags;
 };
 
 }  // namespace AtelierEsri
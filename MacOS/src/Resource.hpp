@@ -88,6 +88,7 @@ using RGNResource = Resource<'RGN#'>;
 using NinePatchResource = Resource<'9PC#'>;
 using TSXResource = Resource<'TSX '>;
 using TMXResource = Resource<'TMX '>;
+using OBJResource = Resource<'OBJ#'>;
 // ReSharper restore CppMultiCharacterLiteral
 
 }  // namespace AtelierEsri
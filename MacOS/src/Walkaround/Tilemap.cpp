@@ -1,19 +1,40 @@
 #include "Tilemap.hpp"
 
+#include <algorithm>
+#include <cstdlib>
 #include <utility>
 
 #include "Assets.h"
 #include "Drawing.hpp"
 #include "SpriteSheet.hpp"
+#include "Strings.hpp"
 
 namespace AtelierEsri {
 
+const TiledProperty* FindProperty(
+    const std::vector<TiledProperty>& properties, const std::string& name
+) {
+  const auto found = std::lower_bound(
+      properties.begin(),
+      properties.end(),
+      name,
+      [](const TiledProperty& property, const std::string& name) {
+        return property.name < name;
+      }
+  );
+  if (found == properties.end() || found->name != name) {
+    return nullptr;
+  }
+  return &*found;
+}
+
 // TODO: can't handle unmasked images yet
 Tileset::Tileset(const TSXAsset& asset)
     : image(MaskedImage::Get(
           asset.image_pict_resource_id, asset.mask_pict_resource_id.value()
       )),
-      tile_size{asset.tile_width, asset.tile_height} {
+      tile_size{asset.tile_width, asset.tile_height},
+      tile_properties(asset.tile_properties) {
   const V2I image_size{asset.image_width, asset.image_height};
   const int tiles_per_row = image_size.x / tile_size.x;
   const int tiles_per_col = image_size.y / tile_size.y;
@@ -41,6 +62,23 @@ void Tileset::draw_tile(const TilemapTile& tile, const R2I& dst) const {
   image.Draw(R2I{tile_origins[tile.tile_id], tile_size}, dst);
 }
 
+const TiledProperty* Tileset::tile_property(
+    const uint16_t tile_id, const std::string& name
+) const {
+  const auto found = std::lower_bound(
+      tile_properties.begin(),
+      tile_properties.end(),
+      tile_id,
+      [](const TSXTileProperties& tile, const uint16_t tile_id) {
+        return tile.tile_id < tile_id;
+      }
+  );
+  if (found == tile_properties.end() || found->tile_id != tile_id) {
+    return nullptr;
+  }
+  return FindProperty(found->properties, name);
+}
+
 std::shared_ptr<Tileset> TSXResourceIDResolver::get(const ResourceID resource_id
 ) {
   if (const auto existing = cache.find(resource_id); existing != cache.end()) {
@@ -76,6 +114,9 @@ TilemapTileLayer::TilemapTileLayer(
     const Tilemap& tilemap, const TMXTileLayer& tmx_tile_layer
 )
     : name(tmx_tile_layer.name),
+      offset(tmx_tile_layer.offset_x, tmx_tile_layer.offset_y),
+      opacity(tmx_tile_layer.opacity),
+      properties(tmx_tile_layer.properties),
       tilemap(tilemap),
       size(tmx_tile_layer.width, tmx_tile_layer.height) {
   tiles.reserve(tmx_tile_layer.tiles.size());
@@ -102,13 +143,156 @@ const TilemapTile* TilemapTileLayer::tile_at(const V2I& pos) const {
   return maybe_tile ? maybe_tile.operator->() : nullptr;
 }
 
+// TODO: can't handle unmasked images yet
+TilemapImageLayer::TilemapImageLayer(const TMXImageLayer& tmx_image_layer)
+    : name(tmx_image_layer.name),
+      properties(tmx_image_layer.properties),
+      image(MaskedImage::Get(
+          tmx_image_layer.image_pict_resource_id,
+          tmx_image_layer.mask_pict_resource_id.value()
+      )),
+      offset(tmx_image_layer.offset_x, tmx_image_layer.offset_y),
+      parallax(tmx_image_layer.parallax_x, tmx_image_layer.parallax_y),
+      opacity(tmx_image_layer.opacity) {}
+
+void TilemapImageLayer::draw(const R2I& src, const R2I& dst) const {
+  // TODO: blend partially transparent layers
+  if (opacity < 128) {
+    return;
+  }
+
+  const ChangeClip change_clip(dst);
+
+  const R2I bounds = image.Bounds();
+  const V2I origin = dst.origin + offset - src.origin * parallax / 256;
+  image.Draw(bounds, R2I{origin, bounds.size});
+}
+
+std::vector<TilemapObject> TilemapObject::ReadOBJ(const ResourceID resource_id
+) {
+  const OBJResource resource = OBJResource::Get(resource_id);
+  const size_t len = RES_CHECKED(
+      GetMaxResourceSize(resource.Unmanaged()),
+      "Couldn't get OBJ# resource size"
+  );
+  return ReadOBJ(len, reinterpret_cast<uint8_t*>(*resource.Unmanaged()));
+}
+
+std::vector<TilemapObject> TilemapObject::ReadOBJ(
+    const size_t len, const uint8_t* ptr
+) {
+  const uint8_t* end = ptr + len;
+
+  // Check there's room for `size` more bytes.
+  const auto need = [&](const size_t size) {
+    if (ptr + size > end) {
+      BAIL("Read past end of OBJ#");
+    }
+  };
+  const auto align_word = [&] {
+    if (reinterpret_cast<int32_t>(ptr) & 1) {
+      ptr++;
+    }
+  };
+  const auto read_u16 = [&] {
+    need(sizeof(uint16_t));
+    const uint16_t x = *reinterpret_cast<const uint16_t*>(ptr);
+    ptr += sizeof(uint16_t);
+    return x;
+  };
+  const auto read_pstring = [&] {
+    need(1);
+    need(1 + *ptr);
+    std::string str;
+    ptr += Strings::ReadPascal(ptr, str);
+    return str;
+  };
+  // Values are stored as text, whatever their type.
+  const auto read_property = [&] {
+    TiledProperty property{};
+    property.name = read_pstring();
+    align_word();
+    property.type = static_cast<TiledProperty::Type>(read_u16());
+    const std::string text = read_pstring();
+    align_word();
+    switch (property.type) {
+      case TiledProperty::Type::Bool:
+        property.value = text == "true";
+        break;
+      case TiledProperty::Type::Int:
+      case TiledProperty::Type::Object:
+        property.value =
+            static_cast<int32_t>(std::strtol(text.c_str(), nullptr, 10));
+        break;
+      case TiledProperty::Type::Color:
+        property.value =
+            static_cast<int32_t>(std::strtoul(text.c_str(), nullptr, 10));
+        break;
+      case TiledProperty::Type::Float:
+        property.value = std::strtof(text.c_str(), nullptr);
+        break;
+      default:
+        property.value = text;
+        break;
+    }
+    return property;
+  };
+
+  uint16_t count = read_u16();
+  std::vector<TilemapObject> objects{};
+  objects.reserve(count);
+  while (count > 0) {
+    TilemapObject object{};
+
+    object.name = read_pstring();
+    align_word();
+
+    object.class_name = read_pstring();
+    align_word();
+
+    object.shape = static_cast<Shape>(read_u16());
+
+    need(sizeof(Rect));
+    object.bounds = *reinterpret_cast<const Rect*>(ptr);
+    ptr += sizeof(Rect);
+
+    uint16_t num_points = read_u16();
+    object.points.reserve(num_points);
+    while (num_points > 0) {
+      need(sizeof(Point));
+      object.points.push_back(*reinterpret_cast<const Point*>(ptr));
+      ptr += sizeof(Point);
+      num_points--;
+    }
+
+    uint16_t num_properties = read_u16();
+    object.properties.reserve(num_properties);
+    while (num_properties > 0) {
+      object.properties.push_back(read_property());
+      num_properties--;
+    }
+
+    objects.push_back(std::move(object));
+    count--;
+  }
+
+  return objects;
+}
+
 TilemapRegionGroup::TilemapRegionGroup(
-    std::string name, const std::vector<Rect>& regions
+    std::string name,
+    const std::vector<Rect>& regions,
+    std::vector<TilemapObject> objects,
+    std::vector<TiledProperty> properties
 )
-    : name(std::move(name)), regions(regions) {}
+    : name(std::move(name)),
+      regions(regions),
+      objects(std::move(objects)),
+      properties(std::move(properties)) {}
 
 Tilemap::Tilemap(const TMXAsset& asset)
-    : size(asset.width, asset.height),
+    : properties(asset.properties),
+      size(asset.width, asset.height),
       tile_size(asset.tile_width, asset.tile_height) {
   tilesets.reserve(asset.tileset_resource_ids.size());
   for (const auto resource_id : asset.tileset_resource_ids) {
@@ -120,9 +304,20 @@ Tilemap::Tilemap(const TMXAsset& asset)
     tile_layers.emplace_back(*this, tmx_tile_layer);
   }
 
+  image_layers.reserve(asset.image_layers.size());
+  for (const auto& tmx_image_layer : asset.image_layers) {
+    image_layers.emplace_back(tmx_image_layer);
+  }
+
   tilesets.reserve(asset.region_groups.size());
-  for (const auto& [name, rgn_resource_id] : asset.region_groups) {
-    region_groups.emplace_back(name, SpriteSheet::ReadRGN(rgn_resource_id));
+  for (const auto& [name, rgn_resource_id, obj_resource_id, properties] :
+       asset.region_groups) {
+    region_groups.emplace_back(
+        name,
+        SpriteSheet::ReadRGN(rgn_resource_id),
+        TilemapObject::ReadOBJ(obj_resource_id),
+        properties
+    );
   }
 }
 
@@ -133,15 +328,23 @@ void Tilemap::draw_layer(
     BAIL("layer index too large");
   }
   const TilemapTileLayer& tile_layer = tile_layers[layer_index];
+  // TODO: blend partially transparent layers
+  if (tile_layer.opacity < 128) {
+    return;
+  }
 
   // Clip our drawing so we don't need to worry about partial tiles.
   const ChangeClip change_clip(dst);
 
-  // Find NW and SE corners of tile position range that fully contains `src`.
-  const V2I tile_nw = src.NW() / tile_size;
-  const V2I tile_se = src.SE() / tile_size;
+  // Find NW and SE corners of tile position range that fully contains `src`,
+  // in layer space.
+  const R2I layer_src{src.origin - tile_layer.offset, src.size};
+  const V2I tile_nw = layer_src.NW() / tile_size;
+  const V2I tile_se = layer_src.SE() / tile_size;
 
-  R2I tile_dst = {dst.origin, tile_size};
+  const V2I row_start =
+      dst.origin + tile_nw * tile_size + tile_layer.offset - src.origin;
+  R2I tile_dst = {row_start, tile_size};
   for (int tile_y = tile_nw.y; tile_y <= tile_se.y; ++tile_y) {
     for (int tile_x = tile_nw.x; tile_x <= tile_se.x; ++tile_x) {
       if (const TilemapTile* tile = tile_layer.tile_at({tile_x, tile_y})) {
@@ -149,11 +352,20 @@ void Tilemap::draw_layer(
       }
       tile_dst.origin.x += tile_size.x;
     }
-    tile_dst.origin.x = dst.origin.x;
+    tile_dst.origin.x = row_start.x;
     tile_dst.origin.y += tile_size.y;
   }
 }
 
+void Tilemap::draw_image_layer(
+    const R2I& src, const R2I& dst, const size_t layer_index
+) const {
+  if (layer_index >= image_layers.size()) {
+    BAIL("image layer index too large");
+  }
+  image_layers[layer_index].draw(src, dst);
+}
+
 Tilemap TMXResourceIDResolver::get(const ResourceID resource_id) {
   if (resource_id == assetVillageTmxResourceId) {
     return Tilemap(assetVillageTmx);
@@ -1,6 +1,8 @@
 #pragma once
 
+#include <string>
 #include <unordered_map>
+#include <vector>
 
 #include "MaskedImage.hpp"
 #include "TMXData.hpp"
@@ -8,6 +10,12 @@
 
 namespace AtelierEsri {
 
+/// Find a custom property by name in a list sorted by name.
+/// Returns `nullptr` if it's not set.
+const TiledProperty* FindProperty(
+    const std::vector<TiledProperty>& properties, const std::string& name
+);
+
 class TilemapTile;
 
 class Tileset {
@@ -17,10 +25,16 @@ class Tileset {
   /// `dst` is in window space.
   void draw_tile(const TilemapTile& tile, const R2I& dst) const;
 
+  /// Returns `nullptr` if the tile doesn't have that property.
+  [[nodiscard]] const TiledProperty* tile_property(
+      uint16_t tile_id, const std::string& name
+  ) const;
+
  private:
   MaskedImage image;
   const V2I tile_size;
   std::vector<V2I> tile_origins;
+  std::vector<TSXTileProperties> tile_properties;
 };
 
 // TODO: Hack for loading TSX assets from code instead of resources,
@@ -61,6 +75,11 @@ class TilemapTileLayer {
   [[nodiscard]] const TilemapTile* tile_at(const V2I& pos) const;
 
   const std::string name;
+  /// In pixels.
+  const V2I offset;
+  /// 0 is transparent, 255 is opaque.
+  const uint8_t opacity;
+  const std::vector<TiledProperty> properties;
 
  private:
   const Tilemap& tilemap;
@@ -69,12 +88,70 @@ class TilemapTileLayer {
   std::vector<std::optional<TilemapTile>> tiles;
 };
 
+/// Background image, drawn behind the tile layers.
+class TilemapImageLayer {
+ public:
+  explicit TilemapImageLayer(const TMXImageLayer& tmx_image_layer);
+
+  /// `src` is in map space.
+  /// `dst` is in window space.
+  void draw(const R2I& src, const R2I& dst) const;
+
+  const std::string name;
+  const std::vector<TiledProperty> properties;
+
+ private:
+  MaskedImage image;
+  /// In pixels.
+  const V2I offset;
+  /// In 256ths: 256 scrolls with the map, and 0 doesn't scroll.
+  const V2I parallax;
+  /// 0 is transparent, 255 is opaque.
+  const uint8_t opacity;
+};
+
+/// Object from a map object layer. Coordinates are in map pixels.
+struct TilemapObject {
+  enum class Shape : int16_t {
+    Rectangle = 0,
+    Ellipse = 1,
+    Point = 2,
+    Polyline = 3,
+    Polygon = 4,
+  };
+
+  std::string name;
+  /// Tiled class. May be empty.
+  std::string class_name;
+  Shape shape;
+  /// Empty for points.
+  Rect bounds;
+  /// Vertices of polylines and polygons. Empty for other shapes.
+  std::vector<Point> points;
+  /// Custom properties, sorted by name.
+  std::vector<TiledProperty> properties;
+
+  /// Read an `OBJ#` object list resource.
+  static std::vector<TilemapObject> ReadOBJ(ResourceID resource_id);
+  static std::vector<TilemapObject> ReadOBJ(size_t len, const uint8_t* ptr);
+};
+
 class TilemapRegionGroup {
  public:
-  TilemapRegionGroup(std::string name, const std::vector<Rect>& regions);
+  TilemapRegionGroup(
+      std::string name,
+      const std::vector<Rect>& regions,
+      std::vector<TilemapObject> objects,
+      std::vector<TiledProperty> properties
+  );
 
   const std::string name;
+  /// Rectangular objects only, for looking up by index.
   const std::vector<Rect> regions;
+  /// Every object in the layer, in layer order.
+  const std::vector<TilemapObject> objects;
+  /// Object layer's properties.
+  const std::vector<TiledProperty> properties;
 };
 
 class Tilemap {
@@ -87,6 +164,13 @@ class Tilemap {
   /// `dst` is in window space.
   void draw_layer(const R2I& src, const R2I& dst, size_t layer_index) const;
 
+  /// `src` is in map space.
+  /// `dst` is in window space.
+  void draw_image_layer(const R2I& src, const R2I& dst, size_t layer_index)
+      const;
+
+  const std::vector<TiledProperty> properties;
+
  private:
   /// In tiles.
   const V2I size;
@@ -94,6 +178,7 @@ class Tilemap {
   const V2I tile_size;
   std::vector<std::shared_ptr<Tileset>> tilesets;
   std::vector<TilemapTileLayer> tile_layers;
+  std::vector<TilemapImageLayer> image_layers;
   std::vector<TilemapRegionGroup> region_groups;
 };
 
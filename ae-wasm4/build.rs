@@ -14,6 +14,9 @@ fn main() {
     aseprite_assets_lo5();
     aseprite_assets();
     classic_assets();
+    items_code();
+    maps_code();
+    cinematics_code();
 }
 
 fn classic_assets() {
@@ -141,6 +144,36 @@ fn aetools_unisprite_encode(input: &Path, output: &Path) {
     assert!(status.success());
 }
 
+fn aetools_items_code(input: &Path, output: &Path) {
+    let status = Command::new("aetools")
+        .arg("items-code")
+        .arg(input)
+        .arg(output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn aetools_maps_code(input: &Path, output: &Path) {
+    let status = Command::new("aetools")
+        .arg("maps-code")
+        .arg(input)
+        .arg(output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn aetools_cinematics_code(input: &Path, output: &Path) {
+    let status = Command::new("aetools")
+        .arg("cinematics-code")
+        .arg(input)
+        .arg(output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
 /// In-place `rustfmt`.
 fn rustfmt(path: &Path) {
     let status = Command::new("rustfmt").arg(path).status().unwrap();
@@ -292,3 +325,58 @@ fn aseprite_assets() {
         fs::copy(group_rs, out_group_rs).unwrap();
     }
 }
+
+/// Generate alchemy material and recipe constants from the items JSON file.
+fn items_code() {
+    let items_json = Path::new("asset_originals").join("items.json");
+    println!("cargo:rerun-if-changed={}", items_json.to_string_lossy());
+
+    // Write straight to the Cargo generated-source folder:
+    // the other asset steps clear out the build directory when they run.
+    let out_material_data_rs = Path::new(&env::var_os("OUT_DIR").unwrap()).join("material_data.rs");
+    aetools_items_code(&items_json, &out_material_data_rs);
+
+    // Make generated output readable.
+    rustfmt(&out_material_data_rs);
+}
+
+/// Generate tileset, layer, and region constants from the Tiled maps and tilesets.
+fn maps_code() {
+    let asset_base_dir = Path::new("asset_originals");
+    for subdir in ["maps", "tilesets"] {
+        println!(
+            "cargo:rerun-if-changed={}",
+            asset_base_dir.join(subdir).to_string_lossy()
+        );
+    }
+
+    let out_map_data_rs = Path::new(&env::var_os("OUT_DIR").unwrap()).join("map_data.rs");
+    aetools_maps_code(asset_base_dir, &out_map_data_rs);
+
+    // Make generated output readable.
+    rustfmt(&out_map_data_rs);
+}
+
+/// Generate cinematic scripts and the portraits they use from the `.aecinematic` files.
+fn cinematics_code() {
+    let asset_base_dir = Path::new("asset_originals");
+    for subdir in ["cinematics", "avatars"] {
+        println!(
+            "cargo:rerun-if-changed={}",
+            asset_base_dir.join(subdir).to_string_lossy()
+        );
+    }
+
+    // Materials are looked up in the items JSON file.
+    println!(
+        "cargo:rerun-if-changed={}",
+        asset_base_dir.join("items.json").to_string_lossy()
+    );
+
+    let out_cinematic_data_rs =
+        Path::new(&env::var_os("OUT_DIR").unwrap()).join("cinematic_data.rs");
+    aetools_cinematics_code(asset_base_dir, &out_cinematic_data_rs);
+
+    // Make generated output readable.
+    rustfmt(&out_cinematic_data_rs);
+}
@@ -0,0 +1,386 @@
+//! Inventory list for the alchemy screen: scrolls, sorts, and filters down to what a recipe node takes.
+
+use crate::alchemy::{Element, Item, RecipeNode, INVENTORY};
+use crate::font::TINY;
+use crate::gfx::thick_line;
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_UP, MOUSE_LEFT, MOUSE_RIGHT};
+use crate::{asset_data, input, scene, wasm4};
+
+const TOP: i32 = 100;
+const WIDTH: i32 = 100;
+const HEIGHT: i32 = 60;
+const HEADER_HEIGHT: i32 = 7;
+const ROW_TOP: i32 = TOP + HEADER_HEIGHT;
+const ROW_HEIGHT: i32 = 17;
+const VISIBLE_ROWS: usize = ((HEIGHT - HEADER_HEIGHT) / ROW_HEIGHT) as usize;
+const SCROLLBAR_WIDTH: i32 = 4;
+const ROW_WIDTH: i32 = WIDTH - SCROLLBAR_WIDTH;
+
+/// Header controls: x, width.
+const SORT_BUTTON: (i32, i32) = (1, 44);
+const FILTER_BUTTON: (i32, i32) = (48, 44);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Order items were added to the inventory.
+    Inventory,
+    /// Highest quality first.
+    Quality,
+    /// Highest element value first.
+    ElementValue,
+}
+
+impl SortOrder {
+    fn next(self) -> SortOrder {
+        match self {
+            SortOrder::Inventory => SortOrder::Quality,
+            SortOrder::Quality => SortOrder::ElementValue,
+            SortOrder::ElementValue => SortOrder::Inventory,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            SortOrder::Inventory => "Sort: Bag",
+            SortOrder::Quality => "Sort: Qual",
+            SortOrder::ElementValue => "Sort: Elem",
+        }
+    }
+}
+
+/// Part of the panel, for mouse hits and gamepad focus.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PanelTarget {
+    Sort,
+    Filter,
+    ScrollUp,
+    ScrollDown,
+    /// Inventory index of the item in a row.
+    Row(usize),
+}
+
+pub struct InventoryPanel {
+    sort: SortOrder,
+    /// Show only items that the target recipe node takes and that have its element.
+    filtering: bool,
+    /// Inventory indexes of the items shown, in display order.
+    rows: Vec<usize>,
+    /// Index into `rows` of the first visible row.
+    scroll: usize,
+    /// Index into `rows` of the row the gamepad is pointing at.
+    cursor: usize,
+}
+
+impl InventoryPanel {
+    pub const fn new() -> InventoryPanel {
+        InventoryPanel {
+            sort: SortOrder::Inventory,
+            filtering: false,
+            rows: Vec::new(),
+            scroll: 0,
+            cursor: 0,
+        }
+    }
+
+    /// Rebuild the rows from the inventory.
+    /// `target` is the recipe node to filter for, if filtering is on.
+    pub fn refresh(&mut self, inventory: &[Item], target: Option<&RecipeNode>) {
+        self.rows = (0..inventory.len())
+            .filter(|i| match (self.filtering, target) {
+                (true, Some(recipe_node)) => {
+                    let item = &inventory[*i];
+                    recipe_node.accepts(item) && item.elements.contains(recipe_node.element)
+                }
+                _ => true,
+            })
+            .collect();
+        match self.sort {
+            SortOrder::Inventory => {}
+            SortOrder::Quality => self.rows.sort_by_key(|i| u16::MAX - inventory[*i].quality),
+            SortOrder::ElementValue => self
+                .rows
+                .sort_by_key(|i| u8::MAX - inventory[*i].element_value),
+        }
+        self.move_cursor(0);
+    }
+
+    pub fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+    }
+
+    pub fn toggle_filter(&mut self) {
+        self.filtering = !self.filtering;
+    }
+
+    /// Inventory index of the item the gamepad is pointing at.
+    pub fn cursor_item(&self) -> Option<usize> {
+        self.rows.get(self.cursor).copied()
+    }
+
+    /// Is the gamepad cursor on the first row, so that moving up leaves the list?
+    pub fn cursor_at_top(&self) -> bool {
+        self.cursor == 0
+    }
+
+    /// Move the gamepad cursor by some number of rows, scrolling to keep it visible.
+    pub fn move_cursor(&mut self, delta: i32) {
+        let last = self.rows.len().saturating_sub(1) as i32;
+        self.cursor = (self.cursor as i32 + delta).clamp(0, last) as usize;
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        }
+        if self.cursor >= self.scroll + VISIBLE_ROWS {
+            self.scroll = self.cursor + 1 - VISIBLE_ROWS;
+        }
+        self.scroll_by(0);
+    }
+
+    /// Point the gamepad cursor at an item's row, if it's shown.
+    pub fn move_cursor_to(&mut self, inventory_index: usize) {
+        if let Some(row) = self.rows.iter().position(|i| *i == inventory_index) {
+            self.cursor = row;
+            self.move_cursor(0);
+        }
+    }
+
+    /// Scroll by some number of rows without moving past either end of the list.
+    pub fn scroll_by(&mut self, delta: i32) {
+        let max_scroll = self.rows.len().saturating_sub(VISIBLE_ROWS) as i32;
+        self.scroll = (self.scroll as i32 + delta).clamp(0, max_scroll) as usize;
+    }
+
+    /// What's at this point, if it's on the panel?
+    pub fn hit(&self, point: (i32, i32)) -> Option<PanelTarget> {
+        let (x, y) = point;
+        if !(0..WIDTH).contains(&x) || !(TOP..TOP + HEIGHT).contains(&y) {
+            return None;
+        }
+        if y < ROW_TOP {
+            return if (SORT_BUTTON.0..SORT_BUTTON.0 + SORT_BUTTON.1).contains(&x) {
+                Some(PanelTarget::Sort)
+            } else if (FILTER_BUTTON.0..FILTER_BUTTON.0 + FILTER_BUTTON.1).contains(&x) {
+                Some(PanelTarget::Filter)
+            } else {
+                None
+            };
+        }
+        if x >= ROW_WIDTH {
+            return if y < ROW_TOP + (HEIGHT - HEADER_HEIGHT) / 2 {
+                Some(PanelTarget::ScrollUp)
+            } else {
+                Some(PanelTarget::ScrollDown)
+            };
+        }
+        let row = self.scroll + ((y - ROW_TOP) / ROW_HEIGHT) as usize;
+        if row >= self.scroll + VISIBLE_ROWS {
+            return None;
+        }
+        self.rows.get(row).map(|i| PanelTarget::Row(*i))
+    }
+
+    /// Draw the panel. `selection` and `used` are indexed like the inventory.
+    /// `focus` is the part of the panel the gamepad is pointing at, if any.
+    pub fn draw(
+        &self,
+        inventory: &[Item],
+        selection: &[bool],
+        used: &[bool],
+        focus: Option<PanelTarget>,
+    ) {
+        unsafe { *wasm4::DRAW_COLORS = 0x22 };
+        wasm4::rect(0, TOP, WIDTH as u32, HEIGHT as u32);
+
+        // Header controls.
+        let filter_name = if self.filtering {
+            "Filter: On"
+        } else {
+            "Filter: Off"
+        };
+        for (text, (x, w), target) in [
+            (self.sort.name(), SORT_BUTTON, PanelTarget::Sort),
+            (filter_name, FILTER_BUTTON, PanelTarget::Filter),
+        ] {
+            unsafe { *wasm4::DRAW_COLORS = 0x340 };
+            TINY.text(text, x, TOP + 1);
+            if focus == Some(target) {
+                unsafe { *wasm4::DRAW_COLORS = 0x40 };
+                wasm4::rect(x - 1, TOP, w as u32, HEADER_HEIGHT as u32);
+            }
+        }
+
+        // Rows.
+        debug_assert!(
+            self.rows.iter().all(|i| *i < inventory.len()),
+            "Inventory panel wasn't refreshed after the inventory changed"
+        );
+        for (row, i) in self
+            .rows
+            .iter()
+            .copied()
+            .enumerate()
+            .skip(self.scroll)
+            .take(VISIBLE_ROWS)
+        {
+            let y = ROW_TOP + (row - self.scroll) as i32 * ROW_HEIGHT;
+            let item = &inventory[i];
+
+            // Items already used in this synthesis blend into the background.
+            unsafe { *wasm4::DRAW_COLORS = if used[i] { 0x22 } else { 0x23 } };
+            wasm4::rect(0, y, ROW_WIDTH as u32, ROW_HEIGHT as u32 + 1);
+            item.material.icon.blit(1, y + 1, 0);
+            let x_col2 = 16 + 2;
+            unsafe { *wasm4::DRAW_COLORS = 0x210 };
+            TINY.text(item.material.name, x_col2, y + 2);
+            let y_element = y + 9;
+            let mut x_element = x_col2;
+            for element in Element::ALL {
+                let icon = if item.elements.contains(element) {
+                    element.icon()
+                } else {
+                    asset_data::element::EMPTY7
+                };
+                icon.blit(x_element, y_element, 0);
+                x_element += 8;
+            }
+            unsafe { *wasm4::DRAW_COLORS = 0x210 };
+            let y_row2_text = y_element + 1;
+            x_element += 1;
+            TINY.text(&item.element_value.to_string(), x_element, y_row2_text);
+            let mut quality = "Quality: ".to_string();
+            quality.push_str(&item.quality.to_string());
+            let quality_metrics = TINY.metrics(&quality);
+            TINY.text(
+                &quality,
+                ROW_WIDTH - 2 - quality_metrics.0 as i32,
+                y_row2_text,
+            );
+
+            if selection[i] {
+                unsafe { *wasm4::DRAW_COLORS = 0x4 };
+                thick_line(1, y + 4, 3, y + 6, 3, 3);
+                thick_line(3, y + 6, 7, y + 1, 3, 3);
+                unsafe { *wasm4::DRAW_COLORS = 0x1 };
+                wasm4::line(1 + 1, y + 4 + 1, 3 + 1, y + 6 + 1);
+                wasm4::line(3 + 1, y + 6 + 1, 7 + 1, y + 1 + 1);
+            }
+
+            if focus == Some(PanelTarget::Row(i)) {
+                unsafe { *wasm4::DRAW_COLORS = 0x40 };
+                wasm4::rect(0, y, ROW_WIDTH as u32, ROW_HEIGHT as u32 + 1);
+            }
+        }
+
+        if self.rows.is_empty() {
+            unsafe { *wasm4::DRAW_COLORS = 0x340 };
+            TINY.text("(Nothing)", 1, ROW_TOP + 1);
+        }
+
+        // Scrollbar, with a thumb showing which part of the list is visible.
+        let track_height = HEIGHT - HEADER_HEIGHT;
+        unsafe { *wasm4::DRAW_COLORS = 0x33 };
+        wasm4::rect(
+            ROW_WIDTH,
+            ROW_TOP,
+            SCROLLBAR_WIDTH as u32,
+            track_height as u32,
+        );
+        let total_rows = self.rows.len().max(VISIBLE_ROWS) as i32;
+        let thumb_top = ROW_TOP + self.scroll as i32 * track_height / total_rows;
+        let thumb_height = (VISIBLE_ROWS as i32 * track_height / total_rows).max(1);
+        unsafe { *wasm4::DRAW_COLORS = 0x44 };
+        wasm4::rect(
+            ROW_WIDTH + 1,
+            thumb_top,
+            (SCROLLBAR_WIDTH - 2) as u32,
+            thumb_height as u32,
+        );
+    }
+}
+
+// region inventory scene
+
+/// Panel for looking through the inventory outside of synthesis.
+static mut PANEL: InventoryPanel = InventoryPanel::new();
+
+pub fn enter() {
+    let panel = unsafe { &mut PANEL };
+    panel.refresh(unsafe { &INVENTORY }, None);
+}
+
+pub fn update() {
+    let panel = unsafe { &mut PANEL };
+    let inventory = unsafe { &INVENTORY };
+
+    if input::pressed(BUTTON_UP) {
+        panel.move_cursor(-1);
+    }
+    if input::pressed(BUTTON_DOWN) {
+        panel.move_cursor(1);
+    }
+    if input::pressed(BUTTON_1) {
+        panel.cycle_sort();
+    }
+    if input::clicked(MOUSE_LEFT) {
+        match panel.hit(input::mouse().pos) {
+            Some(PanelTarget::Sort) => panel.cycle_sort(),
+            Some(PanelTarget::ScrollUp) => panel.scroll_by(-1),
+            Some(PanelTarget::ScrollDown) => panel.scroll_by(1),
+            Some(PanelTarget::Row(i)) => panel.move_cursor_to(i),
+            // There's no recipe node to filter for.
+            Some(PanelTarget::Filter) | None => {}
+        }
+    }
+    if input::pressed(BUTTON_2) || input::clicked(MOUSE_RIGHT) {
+        scene::pop();
+    }
+    let cursor_item = panel.cursor_item();
+    panel.refresh(inventory, None);
+    if let Some(i) = cursor_item {
+        panel.move_cursor_to(i);
+    }
+
+    if let Some(item) = cursor_item.map(|i| &inventory[i]) {
+        draw_details(item);
+    }
+    let nothing = vec![false; inventory.len()];
+    panel.draw(
+        inventory,
+        &nothing,
+        &nothing,
+        cursor_item.map(PanelTarget::Row),
+    );
+}
+
+/// Everything about an item, above the panel.
+fn draw_details(item: &Item) {
+    item.material.icon.blit2x(2, 2);
+    let x = 2 + 32 + 4;
+    unsafe { *wasm4::DRAW_COLORS = 0x340 };
+    TINY.text(item.material.name, x, 4);
+    let mut quality = "Quality: ".to_string();
+    quality.push_str(&item.quality.to_string());
+    TINY.text(&quality, x, 12);
+    let mut x_element = x;
+    for element in item.elements {
+        element.icon().blit(x_element, 20, 0);
+        x_element += 8;
+    }
+    unsafe { *wasm4::DRAW_COLORS = 0x340 };
+    TINY.text(&item.element_value.to_string(), x_element + 1, 21);
+
+    let mut y = 40;
+    for category in item.categories {
+        TINY.text(category.name(), 2, y);
+        y += 8;
+    }
+    let mut y = 40;
+    for effect in &item.effects {
+        let mut text = effect.id.name().to_string();
+        text.push_str(" Lv. ");
+        text.push_str(&effect.level.to_string());
+        TINY.text(&text, 60, y);
+        y += 8;
+    }
+}
+
+// endregion inventory scene
@@ -1,20 +1,39 @@
+mod inventory;
 mod material_data;
 
 use crate::font::TINY;
 use crate::gfx::{ngon, ngon_points, thick_line, Lo5SplitSprite};
 use crate::gfx_data::CURSOR_POINT;
-use crate::{asset_data, input, wasm4};
+use crate::wasm4::{
+    BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, MOUSE_LEFT, MOUSE_RIGHT,
+};
+use crate::{asset_data, input, save, scene, wasm4};
 use enumset::{enum_set, EnumSet, EnumSetType};
+pub use inventory::{enter as inventory_enter, update as inventory_update};
+use inventory::{InventoryPanel, PanelTarget};
 use std::f32::consts::PI;
 
 const SPACE: f32 = 40.0;
 const H_SPACE_MUL: f32 = 0.8660254037844386;
 
+const GRID_ORIGIN: (i32, i32) = (wasm4::SCREEN_SIZE as i32 / 2, wasm4::SCREEN_SIZE as i32 / 2);
+const NODE_RADIUS: i32 = 13;
+
+/// x, y, width, height.
+const FINISH_BUTTON: (i32, i32, u32, u32) = (104, 146, 52, 12);
+
+/// Quality effects add this much quality per level to a synthesized item.
+const QUALITY_PER_EFFECT_LEVEL: Quality = 10;
+
 pub struct Material<'a> {
+    /// Index in `material_data::MATERIALS`, which save games use to refer to it.
+    index: u8,
     name: &'a str,
     icon: &'a Lo5SplitSprite<'a>,
     categories: EnumSet<Category>,
     recipe: Option<Recipe<'a>>,
+    /// Items of this material also count toward nodes up to this many links away.
+    effect_spread: Option<EffectSpread>,
 }
 
 pub struct Recipe<'a> {
@@ -25,6 +44,9 @@ pub type Quality = u16;
 
 pub type ElementCount = u8;
 
+/// Number of recipe links that Effect Spread reaches across.
+pub type EffectSpread = u8;
+
 pub struct RecipeNode<'a> {
     grid_pos: (i8, i8),
     /// Element used for display and for effect levels.
@@ -34,16 +56,42 @@ pub struct RecipeNode<'a> {
     elemental_requirement: Option<RecipeNodeElementalRequirement>,
     quality_requirement: Option<Quality>,
     parent: Option<usize>,
+    /// Effect Spread doesn't go into, out of, or through nodes with halos around them.
+    halo: bool,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Effect {
     Quality,
     SynthQuantity,
     FireDmg,
 }
 
+impl Effect {
+    /// Every effect, in declaration order. Save games refer to effects by their index in this list.
+    const ALL: [Effect; 3] = [Effect::Quality, Effect::SynthQuantity, Effect::FireDmg];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Effect::Quality => "Quality Up",
+            Effect::SynthQuantity => "Synth Quantity",
+            Effect::FireDmg => "Fire Damage",
+        }
+    }
+}
+
 pub type EffectLevel = u8;
 
+impl RecipeNode<'_> {
+    /// Does this node's input take this item?
+    fn accepts(&self, item: &Item) -> bool {
+        match &self.input {
+            RecipeNodeInput::Material(material) => material.index == item.material.index,
+            RecipeNodeInput::Category(category) => item.categories.contains(*category),
+        }
+    }
+}
+
 pub struct RecipeNodeEffect {
     id: Effect,
     level: EffectLevel,
@@ -243,6 +291,14 @@ struct Item<'a> {
     element_value: ElementCount,
     quality: Quality,
     categories: EnumSet<Category>,
+    effects: Vec<ItemEffect>,
+}
+
+/// Effect that an item got from the recipe node tiers filled while synthesizing it.
+#[derive(Clone)]
+struct ItemEffect {
+    id: Effect,
+    level: EffectLevel,
 }
 
 struct SynthesisNode<'a> {
@@ -272,10 +328,24 @@ impl SynthesisNode<'_> {
         )
     }
 
-    fn draw(&self, grid_origin: (i32, i32)) {
-        let radius = 13;
-        // Draw shape (normally a hexagon)
+    /// Does this point fall on the node?
+    fn contains(&self, grid_origin: (i32, i32), point: (i32, i32)) -> bool {
         let center = self.center(grid_origin);
+        let (dx, dy) = (point.0 - center.0, point.1 - center.1);
+        dx * dx + dy * dy <= NODE_RADIUS * NODE_RADIUS
+    }
+
+    fn draw(&self, grid_origin: (i32, i32), item_value: ElementCount, locked: bool) {
+        let radius = NODE_RADIUS;
+        let center = self.center(grid_origin);
+
+        // Draw halo outside the effect level element icons.
+        if self.recipe_node.halo {
+            unsafe { *wasm4::DRAW_COLORS = 0x40 };
+            wasm4::oval(center.0 - 20, center.1 - 20, 41, 41);
+        }
+
+        // Draw shape (normally a hexagon)
         ngon(center, radius, 6, 0.0, 3, 4);
         unsafe { *wasm4::DRAW_COLORS = 0x22 };
         wasm4::oval(center.0 - 11, center.1 - 11, 21, 21);
@@ -285,27 +355,11 @@ impl SynthesisNode<'_> {
             RecipeNodeInput::Material(material) => {
                 material.icon.blit(center.0 - 8, center.1 - 8, 0)
             }
-            RecipeNodeInput::Category(category) => {
-                let metrics = TINY.metrics(category.fourcc());
-                let shadow_metrics = (metrics.0 + 2, metrics.1 + 2);
-                unsafe { *wasm4::DRAW_COLORS = 0x22 };
-                wasm4::rect(
-                    center.0 - shadow_metrics.0 as i32 / 2 - 1,
-                    center.1 - shadow_metrics.1 as i32 / 2 - 1,
-                    shadow_metrics.0,
-                    shadow_metrics.1,
-                );
-                unsafe { *wasm4::DRAW_COLORS = 0x340 };
-                TINY.text(
-                    category.fourcc(),
-                    center.0 - metrics.0 as i32 / 2 - 1,
-                    center.1 - metrics.1 as i32 / 2 - 1,
-                );
-            }
+            RecipeNodeInput::Category(category) => label(category.fourcc(), center),
         }
 
         // Draw effect level element icons.
-        if let Some((effect, mut value)) = self.active_effect() {
+        if let Some((effect, mut value)) = self.active_effect(item_value) {
             let mut slots = effect.count;
             for slot_center in ngon_points(6, center.into(), radius + 2, PI / -3.0) {
                 if slots == 0 {
@@ -322,54 +376,33 @@ impl SynthesisNode<'_> {
             }
         }
 
-        // Draw lock.
-        // TODO: unlock when parent meets requirements
-        if let Some(req) = &self.recipe_node.elemental_requirement {
+        // Draw lock, with whatever requirements this node has.
+        // Nodes without requirements of their own can still be locked by their parents.
+        if locked {
             asset_data::element::LOCK7.blit(center.0 - 10, center.1 + 4, 0);
 
-            req.element.icon().blit(center.0 - 2, center.1 + 5, 0);
-
-            let unlock_count = req.count.to_string();
-            let metrics = TINY.metrics(&unlock_count);
-            let shadow_metrics = (metrics.0 + 2, metrics.1 + 2);
-            unsafe { *wasm4::DRAW_COLORS = 0x22 };
-            wasm4::rect(
-                center.0 + 8 - shadow_metrics.0 as i32 / 2 - 1,
-                center.1 + 8 - shadow_metrics.1 as i32 / 2 - 1,
-                shadow_metrics.0,
-                shadow_metrics.1,
-            );
-            unsafe { *wasm4::DRAW_COLORS = 0x340 };
-            TINY.text(
-                &unlock_count,
-                center.0 + 8 - metrics.0 as i32 / 2 - 1,
-                center.1 + 8 - metrics.1 as i32 / 2 - 1,
-            );
-        }
-    }
-
-    /// Total element value of items on this node that match the node's element.
-    /// TODO: Effect Spread +N effects on materials can go both forwards and backwards.
-    ///     We'll need to scan the graph in both directions up to max(N) nodes away.
-    ///     Note that Effect Spread doesn't go through nodes with halos around them.
-    fn item_value(&self) -> ElementCount {
-        let mut value = 0;
-        for item in &self.items {
-            if item.elements.contains(self.recipe_node.element.clone()) {
-                value += item.element_value;
+            if let Some(req) = &self.recipe_node.elemental_requirement {
+                req.element.icon().blit(center.0 - 2, center.1 + 5, 0);
+                label(&req.count.to_string(), (center.0 + 8, center.1 + 8));
+            }
+
+            if let Some(quality) = &self.recipe_node.quality_requirement {
+                let mut quality_text = "Q".to_string();
+                quality_text.push_str(&quality.to_string());
+                label(&quality_text, (center.0, center.1 - 8));
             }
         }
-        value
     }
 
     /// Return the effect we're currently trying to fill,
-    /// and how many elements we have filled on the current level.
+    /// and how many elements we have filled on the current level,
+    /// given the node's total element value from `SynthesisState::item_value`.
     /// If we have enough element value to fill up the last effect,
     /// return the last effect, completely filled.
     /// Some nodes may not have any effects so we don't return anything.
     /// TODO: are there real recipes with nodes with no effects?
-    fn active_effect(&self) -> Option<(&RecipeNodeEffect, ElementCount)> {
-        let mut value = self.item_value();
+    fn active_effect(&self, item_value: ElementCount) -> Option<(&RecipeNodeEffect, ElementCount)> {
+        let mut value = item_value;
         for effect in self.recipe_node.effects {
             if value < effect.count {
                 return Some((effect, value));
@@ -382,15 +415,71 @@ impl SynthesisNode<'_> {
             .last()
             .map(|effect| (effect, effect.count));
     }
+
+    /// Return the highest effect tier that's completely filled, if any.
+    fn filled_effect(&self, item_value: ElementCount) -> Option<&RecipeNodeEffect> {
+        let mut value = item_value;
+        let mut filled = None;
+        for effect in self.recipe_node.effects {
+            if value < effect.count {
+                break;
+            }
+            value -= effect.count;
+            filled = Some(effect);
+        }
+        filled
+    }
+}
+
+/// Draw text centered on a point, on a dark background.
+fn label(text: &str, center: (i32, i32)) {
+    let metrics = TINY.metrics(text);
+    let shadow_metrics = (metrics.0 + 2, metrics.1 + 2);
+    unsafe { *wasm4::DRAW_COLORS = 0x22 };
+    wasm4::rect(
+        center.0 - shadow_metrics.0 as i32 / 2 - 1,
+        center.1 - shadow_metrics.1 as i32 / 2 - 1,
+        shadow_metrics.0,
+        shadow_metrics.1,
+    );
+    unsafe { *wasm4::DRAW_COLORS = 0x340 };
+    TINY.text(
+        text,
+        center.0 - metrics.0 as i32 / 2 - 1,
+        center.1 - metrics.1 as i32 / 2 - 1,
+    );
+}
+
+/// Things the player can ask for besides editing the synthesis.
+enum Request {
+    Finish,
+    Leave,
+}
+
+/// What the gamepad is pointing at.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    /// The inventory panel's cursor row.
+    Inventory,
+    InventorySort,
+    InventoryFilter,
+    Node(usize),
+    Finish,
 }
 
 struct SynthesisState<'a> {
     material: &'a Material<'a>,
     nodes: Vec<SynthesisNode<'a>>,
+    /// Node index and inventory index of every item placed so far, in order, for undo.
+    placements: Vec<(usize, usize)>,
+    focus: Focus,
+    /// Node the inventory panel filters for: the last one hovered or pointed at.
+    target: Option<usize>,
+    inventory: InventoryPanel,
 }
 
-impl SynthesisState<'_> {
-    fn new<'a>(material: &'a Material) -> SynthesisState<'a> {
+impl<'a> SynthesisState<'a> {
+    fn new(material: &'a Material) -> SynthesisState<'a> {
         SynthesisState {
             material,
             nodes: material
@@ -401,7 +490,403 @@ impl SynthesisState<'_> {
                 .iter()
                 .map(|recipe_node| SynthesisNode::new(recipe_node))
                 .collect::<Vec<_>>(),
+            placements: Vec::new(),
+            focus: Focus::Inventory,
+            target: None,
+            inventory: InventoryPanel::new(),
+        }
+    }
+
+    /// Indexes of the nodes linked to a node, in either direction.
+    fn neighbors(&self, node_index: usize) -> impl Iterator<Item = usize> + '_ {
+        let parent = self.nodes[node_index].recipe_node.parent;
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(move |(i, node)| {
+                node.recipe_node.parent == Some(node_index) || parent == Some(*i)
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Number of links between a node and every node that Effect Spread can reach it from,
+    /// or `None` for nodes it can't reach it from.
+    fn spread_distances(&self, node_index: usize) -> Vec<Option<EffectSpread>> {
+        let mut distances = vec![None; self.nodes.len()];
+        distances[node_index] = Some(0);
+        if self.nodes[node_index].recipe_node.halo {
+            return distances;
+        }
+        let mut frontier = vec![node_index];
+        let mut distance: EffectSpread = 0;
+        while !frontier.is_empty() {
+            distance = distance.saturating_add(1);
+            let mut next = Vec::new();
+            for i in frontier {
+                for j in self.neighbors(i) {
+                    if distances[j].is_none() && !self.nodes[j].recipe_node.halo {
+                        distances[j] = Some(distance);
+                        next.push(j);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        distances
+    }
+
+    /// Total element value of items with a given element:
+    /// items on the node itself, plus items on other nodes whose Effect Spread reaches it.
+    fn element_value(&self, node_index: usize, element: Element) -> ElementCount {
+        let mut value: ElementCount = 0;
+        for (i, distance) in self.spread_distances(node_index).into_iter().enumerate() {
+            let Some(distance) = distance else {
+                continue;
+            };
+            for item in &self.nodes[i].items {
+                let reaches = distance == 0
+                    || item
+                        .material
+                        .effect_spread
+                        .is_some_and(|spread| spread >= distance);
+                if reaches && item.elements.contains(element) {
+                    value = value.saturating_add(item.element_value);
+                }
+            }
+        }
+        value
+    }
+
+    /// Total element value of items that match a node's element.
+    fn item_value(&self, node_index: usize) -> ElementCount {
+        self.element_value(node_index, self.nodes[node_index].recipe_node.element)
+    }
+
+    /// Mean quality of the items placed so far. Quality requirements are checked against this.
+    fn ingredient_quality(&self) -> Quality {
+        if self.placements.is_empty() {
+            return 0;
         }
+        let total: u32 = self
+            .placements
+            .iter()
+            .map(|(_, i)| unsafe { INVENTORY[*i].quality } as u32)
+            .sum();
+        (total / self.placements.len() as u32) as Quality
+    }
+
+    /// A node is locked if its parent doesn't have enough of the required element,
+    /// if the quality so far is below its quality requirement, or if its parent is locked.
+    fn locked(&self, node_index: usize) -> bool {
+        let quality = self.ingredient_quality();
+        let mut current = Some(node_index);
+        // Bounded, so a node in a link cycle counts as locked instead of hanging.
+        for _ in 0..self.nodes.len() {
+            let Some(i) = current else {
+                return false;
+            };
+            let recipe_node = self.nodes[i].recipe_node;
+            if let Some(req) = &recipe_node.elemental_requirement {
+                let met = recipe_node
+                    .parent
+                    .is_some_and(|parent| self.element_value(parent, req.element) >= req.count);
+                if !met {
+                    return true;
+                }
+            }
+            if recipe_node
+                .quality_requirement
+                .is_some_and(|req| quality < req)
+            {
+                return true;
+            }
+            current = recipe_node.parent;
+        }
+        current.is_some()
+    }
+
+    fn used(&self, inventory_index: usize) -> bool {
+        self.placements.iter().any(|(_, i)| *i == inventory_index)
+    }
+
+    fn can_place(&self, node_index: usize, inventory_index: usize) -> bool {
+        !self.used(inventory_index)
+            && !self.locked(node_index)
+            && self.nodes[node_index]
+                .recipe_node
+                .accepts(unsafe { &INVENTORY[inventory_index] })
+    }
+
+    /// Put an inventory item on a node. Returns whether it went on.
+    fn place(&mut self, node_index: usize, inventory_index: usize) -> bool {
+        if !self.can_place(node_index, inventory_index) {
+            return false;
+        }
+        self.nodes[node_index]
+            .items
+            .push(unsafe { &INVENTORY[inventory_index] });
+        self.placements.push((node_index, inventory_index));
+
+        // An item can lower the quality enough to lock nodes that already have items on them.
+        if (0..self.nodes.len()).any(|i| !self.nodes[i].items.is_empty() && self.locked(i)) {
+            self.unplace();
+            return false;
+        }
+
+        true
+    }
+
+    /// Take the last placed item back off its node.
+    fn unplace(&mut self) {
+        if let Some((node_index, _)) = self.placements.pop() {
+            self.nodes[node_index].items.pop();
+        }
+    }
+
+    /// Inventory indexes of selected items that haven't been used yet.
+    fn selected(&self) -> Vec<usize> {
+        unsafe { &INVENTORY_SELECTION }
+            .iter()
+            .enumerate()
+            .filter(|(i, selected)| **selected && !self.used(*i))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Select or deselect an inventory item.
+    fn toggle(&mut self, inventory_index: usize) {
+        if self.used(inventory_index) {
+            return;
+        }
+        unsafe { INVENTORY_SELECTION[inventory_index] = !INVENTORY_SELECTION[inventory_index] };
+    }
+
+    /// Put every selected item that fits on a node, in inventory order.
+    fn place_selected(&mut self, node_index: usize) {
+        let mut placed_any = false;
+        for inventory_index in self.selected() {
+            if self.place(node_index, inventory_index) {
+                unsafe { INVENTORY_SELECTION[inventory_index] = false };
+                placed_any = true;
+            }
+        }
+        if placed_any {
+            self.focus = Focus::Inventory;
+        }
+    }
+
+    /// Point the gamepad at the first node that a selected item can go on.
+    fn focus_nodes(&mut self) {
+        let selected = self.selected();
+        let node_index = (0..self.nodes.len())
+            .find(|n| selected.iter().any(|i| self.can_place(*n, *i)))
+            .unwrap_or(0);
+        self.focus = Focus::Node(node_index);
+    }
+
+    /// Deselect everything if anything's selected, otherwise undo the last placement.
+    /// Returns false if there was nothing to back out of.
+    fn back(&mut self) -> bool {
+        if !self.selected().is_empty() {
+            unsafe { INVENTORY_SELECTION.iter_mut().for_each(|s| *s = false) };
+        } else if !self.placements.is_empty() {
+            self.unplace();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Handle gamepad and mouse input.
+    /// Returns what the player asked for, if anything: finishing needs at least one item placed,
+    /// and leaving needs nothing left to back out of.
+    fn handle_input(&mut self, grid_origin: (i32, i32)) -> Option<Request> {
+        let node_count = self.nodes.len();
+        let mut finish = false;
+        let mut leave = false;
+
+        match self.focus {
+            Focus::Inventory => {
+                if input::pressed(BUTTON_UP) {
+                    if self.inventory.cursor_at_top() {
+                        self.focus = Focus::InventorySort;
+                    } else {
+                        self.inventory.move_cursor(-1);
+                    }
+                }
+                if input::pressed(BUTTON_DOWN) {
+                    self.inventory.move_cursor(1);
+                }
+                if input::pressed(BUTTON_RIGHT) {
+                    if self.selected().is_empty() {
+                        self.focus = Focus::Finish;
+                    } else {
+                        self.focus_nodes();
+                    }
+                }
+                if input::pressed(BUTTON_1) {
+                    if let Some(i) = self.inventory.cursor_item() {
+                        self.toggle(i);
+                    }
+                }
+                if input::pressed(BUTTON_2) {
+                    leave |= !self.back();
+                }
+            }
+            Focus::InventorySort => {
+                if input::pressed(BUTTON_RIGHT) {
+                    self.focus = Focus::InventoryFilter;
+                }
+                if input::pressed(BUTTON_DOWN) {
+                    self.focus = Focus::Inventory;
+                }
+                if input::pressed(BUTTON_1) {
+                    self.inventory.cycle_sort();
+                }
+                if input::pressed(BUTTON_2) {
+                    leave |= !self.back();
+                }
+            }
+            Focus::InventoryFilter => {
+                if input::pressed(BUTTON_LEFT) {
+                    self.focus = Focus::InventorySort;
+                }
+                if input::pressed(BUTTON_RIGHT) {
+                    self.focus = Focus::Finish;
+                }
+                if input::pressed(BUTTON_DOWN) {
+                    self.focus = Focus::Inventory;
+                }
+                if input::pressed(BUTTON_1) {
+                    self.inventory.toggle_filter();
+                }
+                if input::pressed(BUTTON_2) {
+                    leave |= !self.back();
+                }
+            }
+            Focus::Node(n) => {
+                if input::pressed(BUTTON_LEFT) || input::pressed(BUTTON_UP) {
+                    self.focus = Focus::Node((n + node_count - 1) % node_count);
+                }
+                if input::pressed(BUTTON_RIGHT) || input::pressed(BUTTON_DOWN) {
+                    self.focus = Focus::Node((n + 1) % node_count);
+                }
+                if input::pressed(BUTTON_1) {
+                    self.place_selected(n);
+                }
+                if input::pressed(BUTTON_2) {
+                    self.focus = Focus::Inventory;
+                }
+            }
+            Focus::Finish => {
+                if input::pressed(BUTTON_LEFT) {
+                    self.focus = Focus::Inventory;
+                }
+                if input::pressed(BUTTON_1) {
+                    finish = true;
+                }
+                if input::pressed(BUTTON_2) {
+                    leave |= !self.back();
+                }
+            }
+        }
+
+        let mouse = input::mouse();
+        let hovered_node =
+            (0..node_count).find(|n| self.nodes[*n].contains(grid_origin, mouse.pos));
+        if input::clicked(MOUSE_LEFT) {
+            let (x, y) = mouse.pos;
+            let (button_x, button_y, button_w, button_h) = FINISH_BUTTON;
+            if let Some(target) = self.inventory.hit(mouse.pos) {
+                match target {
+                    PanelTarget::Sort => self.inventory.cycle_sort(),
+                    PanelTarget::Filter => self.inventory.toggle_filter(),
+                    PanelTarget::ScrollUp => self.inventory.scroll_by(-1),
+                    PanelTarget::ScrollDown => self.inventory.scroll_by(1),
+                    PanelTarget::Row(i) => {
+                        self.toggle(i);
+                        self.inventory.move_cursor_to(i);
+                    }
+                }
+            } else if (button_x..button_x + button_w as i32).contains(&x)
+                && (button_y..button_y + button_h as i32).contains(&y)
+            {
+                finish = true;
+            } else if let Some(n) = hovered_node {
+                self.place_selected(n);
+            }
+        }
+        if input::clicked(MOUSE_RIGHT) {
+            leave |= !self.back();
+        }
+
+        if let Focus::Node(n) = self.focus {
+            self.target = Some(n);
+        }
+        if hovered_node.is_some() {
+            self.target = hovered_node;
+        }
+        self.inventory.refresh(
+            unsafe { &INVENTORY },
+            self.target.map(|n| self.nodes[n].recipe_node),
+        );
+
+        if finish && !self.placements.is_empty() {
+            Some(Request::Finish)
+        } else if leave {
+            Some(Request::Leave)
+        } else {
+            None
+        }
+    }
+
+    /// Items produced by this synthesis.
+    /// Each unlocked node contributes its highest filled effect tier; the best level of each effect wins.
+    /// Quality is the mean quality of the ingredients plus a bonus from Quality effects,
+    /// and Synth Quantity effects make extra copies.
+    fn results(&self) -> Vec<Item<'a>> {
+        let mut effects = Vec::<ItemEffect>::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if self.locked(i) {
+                continue;
+            }
+            let Some(effect) = node.filled_effect(self.item_value(i)) else {
+                continue;
+            };
+            match effects.iter_mut().find(|e| e.id == effect.id) {
+                Some(e) => e.level = e.level.max(effect.level),
+                None => effects.push(ItemEffect {
+                    id: effect.id,
+                    level: effect.level,
+                }),
+            }
+        }
+        let effect_level = |id: Effect| effects.iter().find(|e| e.id == id).map_or(0, |e| e.level);
+
+        let mut elements = EnumSet::<Element>::new();
+        let mut total_element_value: u32 = 0;
+        for (_, i) in &self.placements {
+            let item = unsafe { &INVENTORY[*i] };
+            elements |= item.elements;
+            total_element_value += item.element_value as u32;
+        }
+        let element_value =
+            (total_element_value / self.placements.len().max(1) as u32) as ElementCount;
+        let quality = self
+            .ingredient_quality()
+            .saturating_add(effect_level(Effect::Quality) as Quality * QUALITY_PER_EFFECT_LEVEL);
+        let quantity = 1 + effect_level(Effect::SynthQuantity) as usize;
+
+        (0..quantity)
+            .map(|_| Item {
+                material: self.material,
+                elements,
+                element_value,
+                quality,
+                categories: self.material.categories,
+                effects: effects.clone(),
+            })
+            .collect()
     }
 
     fn draw(&self, grid_origin: (i32, i32)) {
@@ -430,52 +915,57 @@ impl SynthesisState<'_> {
         }
 
         // Draw nodes on top of the lines.
-        for node in &self.nodes {
-            node.draw(grid_origin);
+        for (i, node) in self.nodes.iter().enumerate() {
+            node.draw(grid_origin, self.item_value(i), self.locked(i));
         }
 
-        // Draw inventory.
-        unsafe { *wasm4::DRAW_COLORS = 0x22 };
-        wasm4::rect(0, 100, 100, 60);
-        unsafe { *wasm4::DRAW_COLORS = 0x340 };
-        TINY.text("Inventory:", 1, 100 + 1);
-        for (i, item) in unsafe { &INVENTORY }.iter().enumerate() {
-            unsafe { *wasm4::DRAW_COLORS = 0x23 };
-            let y = 100 + 6 + i as i32 * 17;
-            wasm4::rect(0, y, 100, 17 + 1);
-            item.material.icon.blit(1, y + 1, 0);
-            let x_col2 = 16 + 2;
-            unsafe { *wasm4::DRAW_COLORS = 0x210 };
-            TINY.text(item.material.name, x_col2, y + 2);
-            let y_element = y + 9;
-            let mut x_element = x_col2;
-            for element in Element::ALL {
-                let icon = if item.elements.contains(element) {
-                    element.icon()
-                } else {
-                    asset_data::element::EMPTY7
-                };
-                icon.blit(x_element, y_element, 0);
-                x_element += 8;
+        // Outline nodes that selected items can go on, and the node the gamepad is pointing at.
+        let selected = self.selected();
+        for (i, node) in self.nodes.iter().enumerate() {
+            let center = node.center(grid_origin);
+            if selected.iter().any(|held| self.can_place(i, *held)) {
+                ngon(center, NODE_RADIUS + 4, 6, 0.0, 4, 1);
             }
-            unsafe { *wasm4::DRAW_COLORS = 0x210 };
-            let y_row2_text = y_element + 1;
-            x_element += 1;
-            TINY.text(&item.element_value.to_string(), x_element, y_row2_text);
-            let mut quality = "Quality: ".to_string();
-            quality.push_str(&item.quality.to_string());
-            let quality_metrics = TINY.metrics(&quality);
-            TINY.text(&quality, 100 - 2 - quality_metrics.0 as i32, y_row2_text);
-
-            if unsafe { INVENTORY_SELECTION[i] } {
-                unsafe { *wasm4::DRAW_COLORS = 0x4 };
-                thick_line(1, y + 4, 3, y + 6, 3, 3);
-                thick_line(3, y + 6, 7, y + 1, 3, 3);
-                unsafe { *wasm4::DRAW_COLORS = 0x1 };
-                wasm4::line(1 + 1, y + 4 + 1, 3 + 1, y + 6 + 1);
-                wasm4::line(3 + 1, y + 6 + 1, 7 + 1, y + 1 + 1);
+            if self.focus == Focus::Node(i) {
+                ngon(center, NODE_RADIUS + 5, 6, 0.0, 4, 2);
             }
         }
+
+        // Draw inventory.
+        let inventory = unsafe { &INVENTORY };
+        let used = (0..inventory.len())
+            .map(|i| self.used(i))
+            .collect::<Vec<_>>();
+        let panel_focus = match self.focus {
+            Focus::Inventory => self.inventory.cursor_item().map(PanelTarget::Row),
+            Focus::InventorySort => Some(PanelTarget::Sort),
+            Focus::InventoryFilter => Some(PanelTarget::Filter),
+            _ => None,
+        };
+        self.inventory.draw(
+            inventory,
+            unsafe { &INVENTORY_SELECTION },
+            &used,
+            panel_focus,
+        );
+
+        // Draw finish button.
+        let (button_x, button_y, button_w, button_h) = FINISH_BUTTON;
+        unsafe {
+            *wasm4::DRAW_COLORS = if self.focus == Focus::Finish {
+                0x42
+            } else {
+                0x22
+            }
+        };
+        wasm4::rect(button_x, button_y, button_w, button_h);
+        let metrics = TINY.metrics("Finish");
+        unsafe { *wasm4::DRAW_COLORS = 0x340 };
+        TINY.text(
+            "Finish",
+            button_x + (button_w - metrics.0) as i32 / 2,
+            button_y + (button_h - metrics.1) as i32 / 2,
+        );
     }
 }
 
@@ -491,7 +981,8 @@ pub fn init() {
             elements: enum_set!(Element::Fire),
             element_value: 2,
             quality: 93,
-            categories: enum_set!(Category::Water),
+            categories: material_data::GASOLINE.categories,
+            effects: vec![],
         });
         INVENTORY_SELECTION.push(false);
 
@@ -500,19 +991,192 @@ pub fn init() {
             elements: enum_set!(Element::Ice),
             element_value: 2,
             quality: 58,
-            categories: enum_set!(Category::Water),
+            categories: material_data::WATER.categories,
+            effects: vec![],
         });
-        INVENTORY_SELECTION.push(true);
+        INVENTORY_SELECTION.push(false);
+
+        INVENTORY.push(Item {
+            material: &material_data::RED_FLOWER,
+            elements: enum_set!(Element::Fire),
+            element_value: 2,
+            quality: 40,
+            categories: material_data::RED_FLOWER.categories,
+            effects: vec![],
+        });
+        INVENTORY_SELECTION.push(false);
+
+        INVENTORY.push(Item {
+            material: &material_data::ORE_COPPER,
+            elements: enum_set!(Element::Fire | Element::Lightning),
+            element_value: 3,
+            quality: 70,
+            categories: material_data::ORE_COPPER.categories,
+            effects: vec![],
+        });
+        INVENTORY_SELECTION.push(false);
+
+        INVENTORY.push(Item {
+            material: &material_data::SAND,
+            elements: enum_set!(Element::Wind),
+            element_value: 1,
+            quality: 20,
+            categories: material_data::SAND.categories,
+            effects: vec![],
+        });
+        INVENTORY_SELECTION.push(false);
 
-        let mut synthesis_state = SynthesisState::new(material_data::RED_NEUTRALIZER);
-        synthesis_state.nodes[0].items.push(&INVENTORY[1]);
-        SYNTHESIS_STATE = Some(synthesis_state);
+        SYNTHESIS_STATE = Some(SynthesisState::new(material_data::RED_NEUTRALIZER));
     }
 }
 
+/// The player sat down at the cauldron.
+pub fn enter() {
+    save::save();
+}
+
+/// The player got up from the cauldron.
+pub fn exit() {
+    save::save();
+}
+
 pub fn update() {
+    match unsafe { SYNTHESIS_STATE.as_mut().unwrap() }.handle_input(GRID_ORIGIN) {
+        Some(Request::Finish) => finish_synthesis(),
+        Some(Request::Leave) => scene::pop(),
+        None => {}
+    }
+
     let synthesis_state = unsafe { SYNTHESIS_STATE.as_ref().unwrap() };
-    synthesis_state.draw((wasm4::SCREEN_SIZE as i32 / 2, wasm4::SCREEN_SIZE as i32 / 2));
+    synthesis_state.draw(GRID_ORIGIN);
 
     CURSOR_POINT.draw(input::mouse().pos);
 }
+
+/// Replace the items used in the current synthesis with its results,
+/// then start another synthesis of the same material.
+fn finish_synthesis() {
+    unsafe {
+        let synthesis_state = SYNTHESIS_STATE.take().unwrap();
+        let material = synthesis_state.material;
+        let results = synthesis_state.results();
+        let mut used = synthesis_state
+            .placements
+            .iter()
+            .map(|(_, i)| *i)
+            .collect::<Vec<_>>();
+        // Nodes hold references into the inventory, so they have to go before it changes.
+        // Keep the inventory panel so its sort and filter settings carry over.
+        let SynthesisState {
+            nodes,
+            inventory: mut inventory_panel,
+            ..
+        } = synthesis_state;
+        drop(nodes);
+
+        used.sort_unstable();
+        for i in used.into_iter().rev() {
+            INVENTORY.remove(i);
+            INVENTORY_SELECTION.remove(i);
+        }
+        for item in results {
+            INVENTORY.push(item);
+            INVENTORY_SELECTION.push(false);
+        }
+
+        // The panel's rows point into the old inventory, which may have been longer,
+        // and it's drawn this frame, before synthesis would refresh it.
+        inventory_panel.refresh(&INVENTORY, None);
+
+        let mut next_synthesis_state = SynthesisState::new(material);
+        next_synthesis_state.inventory = inventory_panel;
+        SYNTHESIS_STATE = Some(next_synthesis_state);
+    }
+
+    save::save();
+}
+
+// region save games
+
+/// Write the inventory to a save game:
+/// item count, then for each item:
+/// material index in `material_data::MATERIALS`, elements, element value, quality,
+/// effect count, and then each effect's index in `Effect::ALL` and level.
+/// Categories aren't saved since they come from the material.
+pub fn write_inventory(w: &mut save::Writer) {
+    let inventory = unsafe { &INVENTORY };
+    // Anything past 255 items is dropped. The disk would fill up long before that anyway.
+    let count = inventory.len().min(u8::MAX as usize);
+    w.u8(count as u8);
+    for item in &inventory[..count] {
+        w.u8(item.material.index);
+        w.u8(item.elements.as_u8());
+        w.u8(item.element_value);
+        w.u16(item.quality);
+        w.u8(item.effects.len() as u8);
+        for effect in &item.effects {
+            w.u8(effect.id as u8);
+            w.u8(effect.level);
+        }
+    }
+}
+
+/// Inventory read from a save game, not yet swapped in for the current one.
+pub struct SavedInventory(Vec<Item<'static>>);
+
+/// Read an inventory written by `write_inventory`.
+pub fn read_inventory(r: &mut save::Reader) -> Option<SavedInventory> {
+    let count = r.u8()?;
+    let mut items = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let material = *material_data::MATERIALS.get(r.u8()? as usize)?;
+        let elements = EnumSet::try_from_u8(r.u8()?)?;
+        let element_value = r.u8()?;
+        let quality = r.u16()?;
+        let effect_count = r.u8()?;
+        let mut effects = Vec::with_capacity(effect_count as usize);
+        for _ in 0..effect_count {
+            let id = *Effect::ALL.get(r.u8()? as usize)?;
+            let level = r.u8()?;
+            effects.push(ItemEffect { id, level });
+        }
+        items.push(Item {
+            material,
+            elements,
+            element_value,
+            quality,
+            categories: material.categories,
+            effects,
+        });
+    }
+    Some(SavedInventory(items))
+}
+
+/// Replace the inventory with a saved one, and start the current synthesis over.
+pub fn restore_inventory(saved: SavedInventory) {
+    unsafe {
+        // Nodes hold references into the inventory, so they have to go before it changes.
+        let synthesis_state = SYNTHESIS_STATE.take();
+        let kept = synthesis_state.map(|synthesis_state| {
+            let SynthesisState {
+                material,
+                nodes,
+                inventory: inventory_panel,
+                ..
+            } = synthesis_state;
+            drop(nodes);
+            (material, inventory_panel)
+        });
+
+        INVENTORY_SELECTION = vec![false; saved.0.len()];
+        INVENTORY = saved.0;
+
+        if let Some((material, inventory_panel)) = kept {
+            let mut next_synthesis_state = SynthesisState::new(material);
+            next_synthesis_state.inventory = inventory_panel;
+            SYNTHESIS_STATE = Some(next_synthesis_state);
+        }
+    }
+}
+
+// endregion save games
@@ -0,0 +1,207 @@
+//! Camera: which part of a map is on screen.
+//!
+//! Follows a target (usually the player) loosely, so small movements don't scroll the map,
+//! and eases toward it instead of jumping. Cinematics can take over with scripted pans,
+//! and anything can shake it. Maps smaller than the view are centered in it.
+
+use std::cmp::max;
+
+/// Size of the box in the middle of the view that the target can move around in
+/// without the camera following, in pixels.
+const DEAD_ZONE_W: i32 = 32;
+const DEAD_ZONE_H: i32 = 24;
+
+/// The camera covers this fraction of the distance to where it should be every frame.
+/// Always at least a pixel, so it gets there eventually.
+const FOLLOW_DIVISOR: i32 = 4;
+
+pub struct Camera {
+    /// Map position of the view's top left corner, not counting shake.
+    /// Always within the map, or centering it if it's too small.
+    x: i32,
+    y: i32,
+    view_w: i32,
+    view_h: i32,
+    map_w: i32,
+    map_h: i32,
+    pan: Option<Pan>,
+    shake: Option<Shake>,
+}
+
+/// Scripted move from one view position to another.
+struct Pan {
+    from: (i32, i32),
+    to: (i32, i32),
+    frame: u32,
+    frames: u32,
+}
+
+struct Shake {
+    /// Largest offset in pixels, at the start. Dies down linearly.
+    magnitude: i32,
+    frame: u32,
+    frames: u32,
+}
+
+impl Camera {
+    /// `view_w` and `view_h` are the size of the part of the screen the map is drawn in.
+    pub const fn new(view_w: u32, view_h: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            view_w: view_w as i32,
+            view_h: view_h as i32,
+            map_w: view_w as i32,
+            map_h: view_h as i32,
+            pan: None,
+            shake: None,
+        }
+    }
+
+    /// Switch to a map of this size, in pixels, looking at this map point.
+    /// Cancels any pan or shake in progress.
+    pub fn set_map(&mut self, map_w: u32, map_h: u32, x: i32, y: i32) {
+        (self.map_w, self.map_h) = (map_w as i32, map_h as i32);
+        self.shake = None;
+        self.snap(x, y);
+    }
+
+    /// Center the view on a map point right away, as close as the map edges allow.
+    /// Cancels any pan in progress.
+    pub fn snap(&mut self, x: i32, y: i32) {
+        self.pan = None;
+        (self.x, self.y) = self.centered_on(x, y);
+    }
+
+    /// Ease toward keeping a map point (usually the middle of the player) inside the dead zone.
+    /// Ignored while panning.
+    pub fn follow(&mut self, x: i32, y: i32) {
+        if self.pan.is_some() {
+            return;
+        }
+        let (target_x, target_y) = self.clamp(
+            dead_zone_follow(self.x, x, self.view_w, DEAD_ZONE_W),
+            dead_zone_follow(self.y, y, self.view_h, DEAD_ZONE_H),
+        );
+        self.x = approach(self.x, target_x);
+        self.y = approach(self.y, target_y);
+    }
+
+    /// Move over `frames` frames until the view is centered on a map point,
+    /// as close as the map edges allow. Following is ignored until the pan is done.
+    pub fn pan(&mut self, x: i32, y: i32, frames: u32) {
+        if frames == 0 {
+            self.snap(x, y);
+            return;
+        }
+        self.pan = Some(Pan {
+            from: (self.x, self.y),
+            to: self.centered_on(x, y),
+            frame: 0,
+            frames,
+        });
+    }
+
+    pub fn panning(&self) -> bool {
+        self.pan.is_some()
+    }
+
+    /// Shake the view by up to `magnitude` pixels, dying down over `frames` frames.
+    /// Replaces any shake in progress.
+    pub fn shake(&mut self, magnitude: u32, frames: u32) {
+        self.shake = (frames > 0).then_some(Shake {
+            magnitude: magnitude as i32,
+            frame: 0,
+            frames,
+        });
+    }
+
+    /// Call once per frame to move pans and shakes along.
+    pub fn update(&mut self) {
+        if let Some(pan) = &mut self.pan {
+            pan.frame += 1;
+            let (t, frames) = (pan.frame as i32, pan.frames as i32);
+            let (from, to) = (pan.from, pan.to);
+            if pan.frame >= pan.frames {
+                self.pan = None;
+            }
+            self.x = from.0 + (to.0 - from.0) * t / frames;
+            self.y = from.1 + (to.1 - from.1) * t / frames;
+        }
+
+        if let Some(shake) = &mut self.shake {
+            shake.frame += 1;
+            if shake.frame >= shake.frames {
+                self.shake = None;
+            }
+        }
+    }
+
+    /// Map position to draw at the view's top left corner, shake included.
+    /// May be negative if the map is smaller than the view.
+    pub fn position(&self) -> (i32, i32) {
+        let (dx, dy) = self.shake_offset();
+        (self.x + dx, self.y + dy)
+    }
+
+    fn shake_offset(&self) -> (i32, i32) {
+        let Some(shake) = &self.shake else {
+            return (0, 0);
+        };
+        let remaining = (shake.frames - shake.frame) as i32;
+        let magnitude = shake.magnitude * remaining / shake.frames as i32;
+        // Jerk back and forth every frame horizontally, and every other frame vertically.
+        let dx = if shake.frame % 2 == 0 {
+            magnitude
+        } else {
+            -magnitude
+        };
+        let dy = if shake.frame % 4 < 2 {
+            magnitude
+        } else {
+            -magnitude
+        };
+        (dx, dy)
+    }
+
+    /// View position that centers a map point, within the map edges.
+    fn centered_on(&self, x: i32, y: i32) -> (i32, i32) {
+        self.clamp(x - self.view_w / 2, y - self.view_h / 2)
+    }
+
+    fn clamp(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            clamp_axis(x, self.view_w, self.map_w),
+            clamp_axis(y, self.view_h, self.map_h),
+        )
+    }
+}
+
+/// Keep the view within the map, or center the map if it's smaller than the view.
+fn clamp_axis(pos: i32, view: i32, map: i32) -> i32 {
+    if map <= view {
+        (map - view) / 2
+    } else {
+        pos.clamp(0, map - view)
+    }
+}
+
+/// Smallest move along one axis from view position `pos` that puts `target`
+/// inside a dead zone of size `dead_zone` centered in a view of size `view`.
+fn dead_zone_follow(pos: i32, target: i32, view: i32, dead_zone: i32) -> i32 {
+    let zone_min = pos + (view - dead_zone) / 2;
+    let zone_max = zone_min + dead_zone;
+    if target < zone_min {
+        pos - (zone_min - target)
+    } else if target > zone_max {
+        pos + (target - zone_max)
+    } else {
+        pos
+    }
+}
+
+/// Step from `pos` toward `target`.
+fn approach(pos: i32, target: i32) -> i32 {
+    let distance = target - pos;
+    pos + distance.signum() * max(1, distance.abs() / FOLLOW_DIVISOR)
+}
@@ -0,0 +1,453 @@
+//! Cinematic scene: plays a script generated from an `.aecinematic` file by `aetools cinematics-code`.
+//!
+//! A script is a list of commands that change what's on stage: background, characters, speaker,
+//! text, and material. Each commit shows the stage as it is and waits. Text types itself out;
+//! X shows the rest of it right away, or if it's all there, goes on to the next commit.
+//! A choice shows its options in place of the text: up and down pick one, and X goes with it.
+//! Scripts can also set and test story flags, jump around, and pan and shake the walkaround camera,
+//! which X waits for the end of a pan on. After the last commit,
+//! or a jump past the end, X goes back to the scene underneath.
+
+use crate::cinematic_data::{BACKGROUNDS, CHARACTER_NAMES, MATERIAL_NAMES, PORTRAITS, SCRIPTS};
+use crate::font::{TypewriterText, TINY};
+use crate::gfx::draw_unisprite;
+use crate::scene::{self, Scene};
+use crate::story::{self, StoryFlag};
+use crate::wasm4::{BUTTON_1, BUTTON_DOWN, BUTTON_UP};
+use crate::{input, walkaround, wasm4};
+use std::ptr::addr_of_mut;
+
+/// Script for the opening of a new game.
+pub const INTRO: &str = "intro";
+
+static mut SCRIPT: &str = INTRO;
+static mut PLAYER: Option<Player> = None;
+
+/// Everything above the dialog box.
+const STAGE_H: u32 = 120;
+const PORTRAIT_SIZE: i32 = 64;
+/// Portraits stand on the dialog box, this far in from the sides of the screen.
+const PORTRAIT_INSET: i32 = 8;
+const TEXT_INSET: i32 = 4;
+/// Height of the tab on top of the dialog box with the speaker's name in it.
+const NAME_TAB_H: u32 = 9;
+const TEXT_COLORS: u16 = 0x340;
+/// Room for the cursor in front of the options of a choice.
+const CHOICE_INDENT: i32 = 6;
+const CHOICE_SPACING: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    Left,
+    Right,
+}
+
+impl Slot {
+    const ALL: [Slot; 2] = [Slot::Left, Slot::Right];
+}
+
+/// Stand-in for a background's art, from `cinematics/wasm4_backgrounds.json`:
+/// a wall down to the floor line, and then floor.
+pub struct CinematicBackground {
+    pub floor_y: u8,
+    /// Palette colors, from 1 to 4.
+    pub wall_color: u8,
+    pub floor_color: u8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CinematicCharacter {
+    /// Index into `cinematic_data::CHARACTER_NAMES`.
+    pub id: usize,
+    /// Index into `cinematic_data::PORTRAITS`.
+    pub mood: usize,
+}
+
+#[derive(Clone, Copy)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Copy)]
+pub enum Condition {
+    /// Bool story flag has this value.
+    Flag(StoryFlag, bool),
+    /// Int story flag, by index, compares to this number.
+    IntFlag(usize, Comparison, i16),
+}
+
+impl Condition {
+    fn test(self) -> bool {
+        match self {
+            Condition::Flag(flag, value) => story::flag(flag) == value,
+            Condition::IntFlag(flag, comparison, value) => {
+                let flag_value = story::int_flag(flag);
+                match comparison {
+                    Comparison::Eq => flag_value == value,
+                    Comparison::Ne => flag_value != value,
+                    Comparison::Lt => flag_value < value,
+                    Comparison::Le => flag_value <= value,
+                    Comparison::Gt => flag_value > value,
+                    Comparison::Ge => flag_value >= value,
+                }
+            }
+        }
+    }
+}
+
+/// Same commands as the Mac edition's `CinematicCommand`,
+/// except that bool and int story flags are numbered separately.
+/// Jump targets are indexes into the script.
+pub enum CinematicCommand {
+    /// The stage is ready to show.
+    Commit,
+    SetCharacter {
+        slot: Slot,
+        character: CinematicCharacter,
+    },
+    /// As above but set only the mood.
+    SetMood {
+        slot: Slot,
+        mood: usize,
+    },
+    ClearCharacter {
+        slot: Slot,
+    },
+    SetSpeaker {
+        slot: Slot,
+    },
+    ClearSpeaker,
+    SetText(&'static str),
+    ClearText,
+    /// Index into `cinematic_data::BACKGROUNDS`.
+    SetBackground(usize),
+    ClearBackground,
+    /// Index into `cinematic_data::MATERIAL_NAMES`.
+    SetMaterial(usize),
+    ClearMaterial,
+    SetFlag(StoryFlag, bool),
+    SetIntFlag(usize, i16),
+    AddIntFlag(usize, i16),
+    Jump(usize),
+    JumpUnless(Condition, usize),
+    /// Like a commit, but waits for one of the options to be picked,
+    /// then goes on from the target that goes with it.
+    Choice(&'static [(&'static str, usize)]),
+    /// Center the walkaround camera on a map point over this many frames.
+    PanCamera {
+        x: i16,
+        y: i16,
+        frames: u16,
+    },
+    ShakeCamera {
+        magnitude: u16,
+        frames: u16,
+    },
+}
+
+/// Runs a script and keeps track of what's on stage.
+struct Player {
+    script: &'static [CinematicCommand],
+    /// Index of the next command to run.
+    next: usize,
+    background: Option<usize>,
+    /// Indexed by `Slot`.
+    characters: [Option<CinematicCharacter>; 2],
+    speaker: Option<Slot>,
+    text: Option<TypewriterText<'static>>,
+    /// How many characters of the text have been typed out so far.
+    typed: usize,
+    material: Option<usize>,
+    /// Options of the choice waiting to be picked, if there is one.
+    choices: &'static [(&'static str, usize)],
+    /// Text of each option, wrapped to fit beside the cursor.
+    choice_texts: Vec<TypewriterText<'static>>,
+    chosen: usize,
+}
+
+/// Pick the script the cinematic scene plays the next time it's entered.
+pub fn queue(script: &'static str) {
+    unsafe { SCRIPT = script };
+}
+
+/// Play a script on top of the current scene.
+pub fn play(script: &'static str) {
+    queue(script);
+    scene::push(Scene::Cinematic);
+}
+
+fn player() -> &'static mut Option<Player> {
+    unsafe { &mut *addr_of_mut!(PLAYER) }
+}
+
+/// Start the queued script. A script that doesn't exist plays as an empty one.
+pub fn enter() {
+    let name = unsafe { SCRIPT };
+    let script = SCRIPTS
+        .iter()
+        .find(|(script_name, _)| *script_name == name)
+        .map_or(&[][..], |(_, script)| *script);
+    let mut player = Player::new(script);
+    player.run();
+    *self::player() = Some(player);
+}
+
+pub fn exit() {
+    *player() = None;
+}
+
+pub fn update() {
+    let Some(player) = player() else {
+        return;
+    };
+
+    let camera = walkaround::camera();
+    camera.update();
+    let ready = !camera.panning();
+
+    player.draw();
+
+    if !player.choices.is_empty() {
+        let count = player.choices.len();
+        if input::pressed(BUTTON_UP) {
+            player.chosen = (player.chosen + count - 1) % count;
+        }
+        if input::pressed(BUTTON_DOWN) {
+            player.chosen = (player.chosen + 1) % count;
+        }
+        if ready && input::pressed(BUTTON_1) {
+            player.choose();
+        }
+        return;
+    }
+
+    let Some(text) = &player.text else {
+        if ready && input::pressed(BUTTON_1) {
+            player.advance();
+        }
+        return;
+    };
+    let length = text.char_count();
+    if player.typed < length {
+        player.typed += 1;
+        if input::pressed(BUTTON_1) {
+            player.typed = length;
+        }
+    } else if ready && input::pressed(BUTTON_1) {
+        player.advance();
+    }
+}
+
+impl Player {
+    fn new(script: &'static [CinematicCommand]) -> Self {
+        Self {
+            script,
+            next: 0,
+            background: None,
+            characters: [None; 2],
+            speaker: None,
+            text: None,
+            typed: 0,
+            material: None,
+            choices: &[],
+            choice_texts: vec![],
+            chosen: 0,
+        }
+    }
+
+    /// Go on to the next commit, or back to the scene underneath if there isn't one.
+    fn advance(&mut self) {
+        if !self.run() {
+            scene::pop();
+        }
+    }
+
+    /// Go on from the picked option.
+    fn choose(&mut self) {
+        self.next = self.choices[self.chosen].1;
+        self.choices = &[];
+        self.choice_texts.clear();
+        self.advance();
+    }
+
+    /// Run commands up to and including the next commit or choice.
+    /// Returns false if the script ran out first.
+    fn run(&mut self) -> bool {
+        while let Some(command) = self.script.get(self.next) {
+            self.next += 1;
+            match command {
+                CinematicCommand::Commit => return true,
+                CinematicCommand::SetCharacter { slot, character } => {
+                    self.characters[*slot as usize] = Some(*character);
+                }
+                CinematicCommand::SetMood { slot, mood } => {
+                    if let Some(character) = &mut self.characters[*slot as usize] {
+                        character.mood = *mood;
+                    }
+                }
+                CinematicCommand::ClearCharacter { slot } => {
+                    self.characters[*slot as usize] = None;
+                }
+                CinematicCommand::SetSpeaker { slot } => self.speaker = Some(*slot),
+                CinematicCommand::ClearSpeaker => self.speaker = None,
+                CinematicCommand::SetText(text) => {
+                    self.text = Some(TypewriterText::new(
+                        TINY,
+                        TEXT_COLORS,
+                        text,
+                        wasm4::SCREEN_SIZE - 2 * TEXT_INSET as u32,
+                    ));
+                    self.typed = 0;
+                }
+                CinematicCommand::ClearText => self.text = None,
+                CinematicCommand::SetBackground(background) => self.background = Some(*background),
+                CinematicCommand::ClearBackground => self.background = None,
+                CinematicCommand::SetMaterial(material) => self.material = Some(*material),
+                CinematicCommand::ClearMaterial => self.material = None,
+                CinematicCommand::SetFlag(flag, value) => story::set_flag(*flag, *value),
+                CinematicCommand::SetIntFlag(flag, value) => story::set_int_flag(*flag, *value),
+                CinematicCommand::AddIntFlag(flag, amount) => {
+                    story::set_int_flag(*flag, story::int_flag(*flag).wrapping_add(*amount));
+                }
+                CinematicCommand::Jump(target) => self.next = *target,
+                CinematicCommand::JumpUnless(condition, target) => {
+                    if !condition.test() {
+                        self.next = *target;
+                    }
+                }
+                CinematicCommand::Choice(options) => {
+                    self.choices = options;
+                    self.choice_texts = options
+                        .iter()
+                        .map(|(option, _)| {
+                            TypewriterText::new(
+                                TINY,
+                                TEXT_COLORS,
+                                option,
+                                wasm4::SCREEN_SIZE - 2 * TEXT_INSET as u32 - CHOICE_INDENT as u32,
+                            )
+                        })
+                        .collect();
+                    self.chosen = 0;
+                    return true;
+                }
+                CinematicCommand::PanCamera { x, y, frames } => {
+                    walkaround::camera().pan(*x as i32, *y as i32, *frames as u32);
+                }
+                CinematicCommand::ShakeCamera { magnitude, frames } => {
+                    walkaround::camera().shake(*magnitude as u32, *frames as u32);
+                }
+            }
+        }
+        false
+    }
+
+    fn draw(&self) {
+        self.draw_background();
+
+        for slot in Slot::ALL {
+            let Some(character) = self.characters[slot as usize] else {
+                continue;
+            };
+            let dimmed = self.speaker.is_some_and(|speaker| speaker != slot);
+            draw_unisprite(
+                PORTRAITS[character.mood],
+                portrait_x(slot),
+                STAGE_H as i32 - PORTRAIT_SIZE,
+                dimmed,
+            );
+        }
+
+        if let Some(material) = self.material {
+            let name = MATERIAL_NAMES[material];
+            let (name_w, name_h) = TINY.metrics(name);
+            let x = (wasm4::SCREEN_SIZE - name_w) as i32 / 2;
+            unsafe { *wasm4::DRAW_COLORS = 0x22 };
+            wasm4::rect(
+                x - TEXT_INSET,
+                TEXT_INSET,
+                name_w + 2 * TEXT_INSET as u32,
+                name_h + 4,
+            );
+            unsafe { *wasm4::DRAW_COLORS = TEXT_COLORS };
+            TINY.text(name, x, TEXT_INSET + 2);
+        }
+
+        unsafe { *wasm4::DRAW_COLORS = 0x22 };
+        wasm4::rect(
+            0,
+            STAGE_H as i32,
+            wasm4::SCREEN_SIZE,
+            wasm4::SCREEN_SIZE - STAGE_H,
+        );
+
+        if let Some((slot, character)) = self
+            .speaker
+            .and_then(|slot| Some((slot, self.characters[slot as usize]?)))
+        {
+            let name = CHARACTER_NAMES[character.id];
+            let (name_w, _) = TINY.metrics(name);
+            let tab_w = name_w + 2 * TEXT_INSET as u32;
+            let tab_x = match slot {
+                Slot::Left => portrait_x(slot),
+                Slot::Right => portrait_x(slot) + PORTRAIT_SIZE - tab_w as i32,
+            };
+            let tab_y = STAGE_H as i32 - NAME_TAB_H as i32;
+            unsafe { *wasm4::DRAW_COLORS = 0x22 };
+            wasm4::rect(tab_x, tab_y, tab_w, NAME_TAB_H);
+            unsafe { *wasm4::DRAW_COLORS = TEXT_COLORS };
+            TINY.text(name, tab_x + TEXT_INSET, tab_y + 2);
+        }
+
+        if !self.choices.is_empty() {
+            self.draw_choices();
+        } else if let Some(text) = &self.text {
+            text.draw(self.typed, TEXT_INSET, STAGE_H as i32 + TEXT_INSET);
+            if self.typed == text.char_count() {
+                unsafe { *wasm4::DRAW_COLORS = TEXT_COLORS };
+                TINY.text("X", 150, 150);
+            }
+        }
+    }
+
+    /// Options go in the dialog box, wrapped like dialogue, with a cursor by the picked one.
+    fn draw_choices(&self) {
+        unsafe { *wasm4::DRAW_COLORS = TEXT_COLORS };
+        let mut y = STAGE_H as i32 + TEXT_INSET;
+        for (i, option) in self.choice_texts.iter().enumerate() {
+            if i == self.chosen {
+                TINY.text(">", TEXT_INSET, y);
+            }
+            option.draw(option.char_count(), TEXT_INSET + CHOICE_INDENT, y);
+            let (_, option_h) = option.metrics();
+            y += option_h as i32 + CHOICE_SPACING;
+        }
+    }
+
+    /// With no background, the map shows through if the script was started from it.
+    fn draw_background(&self) {
+        if let Some(background) = self.background {
+            let background = &BACKGROUNDS[background];
+            let floor_y = background.floor_y as u32;
+            // Fill and outline in the same color.
+            unsafe { *wasm4::DRAW_COLORS = background.wall_color as u16 * 0x11 };
+            wasm4::rect(0, 0, wasm4::SCREEN_SIZE, floor_y);
+            unsafe { *wasm4::DRAW_COLORS = background.floor_color as u16 * 0x11 };
+            wasm4::rect(0, floor_y as i32, wasm4::SCREEN_SIZE, STAGE_H - floor_y);
+        } else if scene::stack().iter().rev().nth(1) == Some(&Scene::Walkaround) {
+            walkaround::draw();
+        }
+    }
+}
+
+fn portrait_x(slot: Slot) -> i32 {
+    match slot {
+        Slot::Left => PORTRAIT_INSET,
+        Slot::Right => wasm4::SCREEN_SIZE as i32 - PORTRAIT_INSET - PORTRAIT_SIZE,
+    }
+}
@@ -0,0 +1,3 @@
+//! Include the file generated from cinematic scripts by `aetools cinematics-code`.
+
+include!(concat!(env!("OUT_DIR"), "/cinematic_data.rs"));
@@ -67,6 +67,11 @@ impl TypewriterText<'_> {
         }
     }
 
+    /// Size of all the text, once it's typed out.
+    pub fn metrics(&self) -> (u32, u32) {
+        self.font.metrics(&self.text)
+    }
+
     pub fn char_count(&self) -> usize {
         self.text.chars().count()
     }
@@ -106,51 +106,7 @@ impl Sprite for Lo5SplitSprite<'_> {
 impl Sprite for Unisprite<&[u8]> {
     /// This version of Unisprite does not attempt to optimize blits in any way.
     fn draw(&self, x: i32, y: i32) {
-        let screen_x0 = x;
-        let screen_y0 = y;
-        let framebuffer = unsafe { &mut *wasm4::FRAMEBUFFER };
-        for sprite_y in 0..self.h {
-            let screen_y = screen_y0 + sprite_y;
-            if screen_y < 0 || screen_y >= wasm4::SCREEN_SIZE as i32 {
-                continue;
-            }
-            for sprite_x in 0..self.w {
-                let screen_x = screen_x0 + sprite_x;
-                if screen_x < 0 || screen_x >= wasm4::SCREEN_SIZE as i32 {
-                    continue;
-                }
-                let alpha = get_pixel(
-                    self.alpha,
-                    self.w as u32,
-                    1,
-                    false,
-                    sprite_x as u32,
-                    sprite_y as u32,
-                );
-                if alpha < 1 {
-                    continue;
-                }
-                let luma = get_pixel(
-                    self.luma,
-                    self.w as u32,
-                    2,
-                    false,
-                    sprite_x as u32,
-                    sprite_y as u32,
-                );
-                set_pixel(
-                    framebuffer,
-                    wasm4::SCREEN_SIZE,
-                    2,
-                    true,
-                    screen_x as u32,
-                    screen_y as u32,
-                    // Invert color to match WASM-4's backwards default palette
-                    // TODO: set things up so we don't have to do this
-                    (4 - 1) - luma,
-                );
-            }
-        }
+        draw_unisprite(self, x, y, false);
     }
 
     fn draw2x(&self, x: i32, y: i32) {
@@ -181,6 +137,61 @@ impl Sprite for Unisprite<&[u8]> {
     }
 }
 
+/// Draw a unisprite, optionally dimmed, as for characters in a cinematic who aren't speaking.
+pub fn draw_unisprite(sprite: &Unisprite<&[u8]>, x: i32, y: i32, dimmed: bool) {
+    let screen_x0 = x;
+    let screen_y0 = y;
+    let framebuffer = unsafe { &mut *wasm4::FRAMEBUFFER };
+    for sprite_y in 0..sprite.h {
+        let screen_y = screen_y0 + sprite_y;
+        if screen_y < 0 || screen_y >= wasm4::SCREEN_SIZE as i32 {
+            continue;
+        }
+        for sprite_x in 0..sprite.w {
+            let screen_x = screen_x0 + sprite_x;
+            if screen_x < 0 || screen_x >= wasm4::SCREEN_SIZE as i32 {
+                continue;
+            }
+            let alpha = get_pixel(
+                sprite.alpha,
+                sprite.w as u32,
+                1,
+                false,
+                sprite_x as u32,
+                sprite_y as u32,
+            );
+            if alpha < 1 {
+                continue;
+            }
+            let luma = get_pixel(
+                sprite.luma,
+                sprite.w as u32,
+                2,
+                false,
+                sprite_x as u32,
+                sprite_y as u32,
+            );
+            // Dimmed sprites are shaded with a checkerboard of the darkest color.
+            let luma = if dimmed && (sprite_x + sprite_y) % 2 == 1 {
+                0
+            } else {
+                luma
+            };
+            set_pixel(
+                framebuffer,
+                wasm4::SCREEN_SIZE,
+                2,
+                true,
+                screen_x as u32,
+                screen_y as u32,
+                // Invert color to match WASM-4's backwards default palette
+                // TODO: set things up so we don't have to do this
+                (4 - 1) - luma,
+            );
+        }
+    }
+}
+
 // endregion split sprites
 
 // region sprite scaling
@@ -326,6 +337,31 @@ impl From<(i32, i32)> for Orientation {
     }
 }
 
+impl Orientation {
+    pub const ALL: [Orientation; 8] = [
+        Orientation::E,
+        Orientation::NE,
+        Orientation::N,
+        Orientation::NW,
+        Orientation::W,
+        Orientation::SW,
+        Orientation::S,
+        Orientation::SE,
+    ];
+
+    /// Nearest of the 8 directions to face something this far away.
+    /// Only diagonal if it's about as far along one axis as the other.
+    pub fn toward(dx: i32, dy: i32) -> Self {
+        if dx.abs() > 2 * dy.abs() {
+            Orientation::from((dx, 0))
+        } else if dy.abs() > 2 * dx.abs() {
+            Orientation::from((0, dy))
+        } else {
+            Orientation::from((dx, dy))
+        }
+    }
+}
+
 /// Assumed to use a sprite strip.
 pub struct CharacterSprite<'a> {
     pub image_w: u32,
@@ -335,6 +371,9 @@ pub struct CharacterSprite<'a> {
     pub sprite_w: u32,
     pub walk_cycle_length: usize,
     pub orientation_starts_flags: [(usize, u32); 8],
+    /// Part of a sprite that collides with things and touches regions: x, y, width, height.
+    /// Usually just the feet, so a character's head can overlap things behind them.
+    pub hitbox: (i32, i32, u32, u32),
 }
 
 impl CharacterSprite<'_> {
@@ -362,15 +401,130 @@ impl CharacterSprite<'_> {
 
 type TileId = u8;
 
+/// Per-tile bit flags, from Tiled tile properties.
+pub type TileFlags = u8;
+
+/// Tile has the `solid` property: actors can't walk through it.
+pub const TILE_SOLID: TileFlags = 1 << 0;
+
+/// Per-tile flip bits, from Tiled. Diagonal flips combine with the others to make rotations.
+pub type TileFlip = u8;
+
+pub const TILE_FLIP_H: TileFlip = 1 << 0;
+pub const TILE_FLIP_V: TileFlip = 1 << 1;
+pub const TILE_FLIP_D: TileFlip = 1 << 2;
+
+/// Frames of a Tiled tile animation, played in order and then repeated:
+/// the tile to show, and for how many frames (60 Hz ticks).
+pub type TileAnimation<'a> = &'a [(TileId, u16)];
+
+static mut FRAME_COUNT: u32 = 0;
+
+/// Call once at the start of every frame, to advance tile animations.
+pub fn tick() {
+    unsafe {
+        FRAME_COUNT = FRAME_COUNT.wrapping_add(1);
+    }
+}
+
+/// Frames since the game started.
+pub fn frame_count() -> u32 {
+    unsafe { FRAME_COUNT }
+}
+
+/// Typed custom property value from Tiled.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PropertyValue<'a> {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    /// ARGB.
+    Color(u32),
+    Str(&'a str),
+    /// Relative to the file the property is in.
+    File(&'a str),
+    /// Object ID, or 0 if unset.
+    Object(u32),
+    /// Value name, or comma-separated names for flag enums.
+    Enum(&'a str),
+}
+
+impl<'a> PropertyValue<'a> {
+    /// Text of strings, files, and enums.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            PropertyValue::Str(x) | PropertyValue::File(x) | PropertyValue::Enum(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+/// Custom properties from Tiled, sorted by name.
+/// Class properties are flattened into one property per member, named `property.member`.
+pub type Properties<'a> = &'a [(&'a str, PropertyValue<'a>)];
+
+/// Value of a custom property, if it's set.
+pub fn property<'a>(properties: Properties<'a>, name: &str) -> Option<PropertyValue<'a>> {
+    properties
+        .binary_search_by_key(&name, |(k, _)| k)
+        .ok()
+        .map(|i| properties[i].1)
+}
+
 /// Map tileset. Wrapper around an image.
 pub struct Tileset<'a> {
     pub tile_width: u32,
     pub tile_height: u32,
     pub image: &'a Lo5SplitSprite<'a>,
+    /// Flags for each tile, starting with tile 1.
+    /// May be shorter than the number of tiles: the rest have no flags.
+    pub flags: &'a [TileFlags],
+    /// Custom properties of tiles that have any, sorted by tile.
+    /// Includes properties that are also stored as flags.
+    pub tile_properties: &'a [(TileId, Properties<'a>)],
+    /// Animations of tiles that have any, sorted by tile.
+    pub animations: &'a [(TileId, TileAnimation<'a>)],
 }
 
-impl Tileset<'_> {
-    pub fn blit(&self, tile: TileId, x: i32, y: i32) {
+impl<'a> Tileset<'a> {
+    pub fn flags(&self, tile: TileId) -> TileFlags {
+        if tile == 0 {
+            return 0;
+        }
+        self.flags.get(tile as usize - 1).copied().unwrap_or(0)
+    }
+
+    /// Value of a tile's custom property, if it's set.
+    pub fn tile_property(&self, tile: TileId, name: &str) -> Option<PropertyValue<'a>> {
+        let i = self
+            .tile_properties
+            .binary_search_by_key(&tile, |(t, _)| *t)
+            .ok()?;
+        property(self.tile_properties[i].1, name)
+    }
+
+    /// Tile to show in place of this one at a given frame count.
+    /// The tile itself if it isn't animated.
+    pub fn animated(&self, tile: TileId, frame: u32) -> TileId {
+        let Ok(i) = self.animations.binary_search_by_key(&tile, |(t, _)| *t) else {
+            return tile;
+        };
+        let frames = self.animations[i].1;
+        let length = frames.iter().map(|(_, ticks)| *ticks as u32).sum::<u32>();
+        if length == 0 {
+            return tile;
+        }
+        let mut t = frame % length;
+        for (frame_tile, ticks) in frames {
+            if t < *ticks as u32 {
+                return *frame_tile;
+            }
+            t -= *ticks as u32;
+        }
+        tile
+    }
+
+    pub fn blit(&self, tile: TileId, flip: TileFlip, x: i32, y: i32) {
         if tile == 0 {
             return;
         }
@@ -385,17 +539,54 @@ impl Tileset<'_> {
             self.tile_height,
             col * self.tile_width,
             row * self.tile_height,
-            0,
+            blit_flags(flip),
         );
     }
 }
 
+/// Convert Tiled flip bits to WASM-4 blit flags.
+/// Tiled flips diagonally first, then horizontally, then vertically,
+/// whereas WASM-4 rotates counterclockwise and flips X the other way when rotating,
+/// so with a diagonal flip, the horizontal and vertical flips trade places.
+/// Only works for square tiles.
+fn blit_flags(flip: TileFlip) -> u32 {
+    let h = flip & TILE_FLIP_H != 0;
+    let v = flip & TILE_FLIP_V != 0;
+    let (flip_x, flip_y, rotate) = if flip & TILE_FLIP_D != 0 {
+        (!v, h, true)
+    } else {
+        (h, v, false)
+    };
+    let mut flags = 0;
+    if flip_x {
+        flags |= wasm4::BLIT_FLIP_X;
+    }
+    if flip_y {
+        flags |= wasm4::BLIT_FLIP_Y;
+    }
+    if rotate {
+        flags |= wasm4::BLIT_ROTATE;
+    }
+    flags
+}
+
+/// WASM-4 can't blend, so layers less opaque than this aren't drawn at all.
+const MIN_OPACITY: u8 = 128;
+
 /// Map layer. Someday we'll support more than one.
 pub struct Layer<'a> {
     pub width_tiles: u32,
     pub height_tiles: u32,
     pub tileset: &'a Tileset<'a>,
-    pub tiles: &'a [TileId],
+    /// Where the layer's top left corner is, in map pixels.
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// 0 is transparent, 255 is opaque.
+    pub opacity: u8,
+    pub tiles: RleRows<'a>,
+    /// Empty if no tiles are flipped.
+    pub flips: RleRows<'a>,
+    pub properties: Properties<'a>,
 }
 
 impl Layer<'_> {
@@ -407,9 +598,65 @@ impl Layer<'_> {
         )
     }
 
+    /// Tile at a map pixel position, or 0 (no tile) if it's off the map.
+    pub fn tile_at(&self, map_x: i32, map_y: i32) -> TileId {
+        let col = (map_x - self.offset_x).div_euclid(self.tileset.tile_width as i32);
+        let row = (map_y - self.offset_y).div_euclid(self.tileset.tile_height as i32);
+        self.tile(col, row)
+    }
+
+    /// Tile at a layer row and column, or 0 (no tile) if it's off the layer.
+    fn tile(&self, col: i32, row: i32) -> TileId {
+        if !(0..self.width_tiles as i32).contains(&col)
+            || !(0..self.height_tiles as i32).contains(&row)
+        {
+            return 0;
+        }
+        self.tiles.get(row as usize, col as usize)
+    }
+
+    /// Does any tile touched by this rectangle (map pixels) have a solid tile?
+    pub fn solid(&self, map_x: i32, map_y: i32, w: u32, h: u32) -> bool {
+        let (map_x, map_y) = (map_x - self.offset_x, map_y - self.offset_y);
+        let tile_w = self.tileset.tile_width as i32;
+        let tile_h = self.tileset.tile_height as i32;
+        let (right, bottom) = (map_x + w as i32 - 1, map_y + h as i32 - 1);
+        let col_range = map_x.div_euclid(tile_w)..=right.div_euclid(tile_w);
+        let row_range = map_y.div_euclid(tile_h)..=bottom.div_euclid(tile_h);
+        row_range.into_iter().any(|row| {
+            col_range
+                .clone()
+                .any(|col| self.tileset.flags(self.tile(col, row)) & TILE_SOLID != 0)
+        })
+    }
+
     pub fn draw(&self, x: i32, y: i32, map_x: i32, map_y: i32, w: u32, h: u32) {
+        self.draw_with_actors(x, y, map_x, map_y, w, h, &mut []);
+    }
+
+    /// Draw the layer a row of tiles at a time, with actors in between,
+    /// so an actor is covered by rows that reach lower on the map than its feet
+    /// and drawn over the rest.
+    /// Actors get sorted by where their feet are.
+    /// Actors are still drawn if the layer is too transparent to draw.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_with_actors(
+        &self,
+        x: i32,
+        y: i32,
+        map_x: i32,
+        map_y: i32,
+        w: u32,
+        h: u32,
+        actors: &mut [Actor],
+    ) {
         let map_x_to_x = x - map_x;
         let map_y_to_y = y - map_y;
+        let visible = self.opacity >= MIN_OPACITY;
+
+        // Work in layer pixels from here on.
+        let (map_x, map_y) = (map_x - self.offset_x, map_y - self.offset_y);
+        let (layer_x_to_x, layer_y_to_y) = (map_x_to_x + self.offset_x, map_y_to_y + self.offset_y);
 
         let map_x_min = max(0, map_x - (map_x % self.tileset.tile_width as i32));
         let map_x_max = min(
@@ -423,17 +670,250 @@ impl Layer<'_> {
             map_y + h as i32 + ((map_y + h as i32) % self.tileset.tile_height as i32),
         );
 
+        actors.sort_by_key(|actor| actor.feet());
+        let mut actors = actors.iter().peekable();
+        let frame = frame_count();
+
         for map_y_tile in (map_y_min..map_y_max).step_by(self.tileset.tile_height as usize) {
+            let row_bottom = map_y_tile + self.tileset.tile_height as i32 + self.offset_y;
+            while let Some(actor) = actors.next_if(|actor| actor.feet() < row_bottom) {
+                actor.draw(map_x_to_x, map_y_to_y);
+            }
+            if !visible {
+                continue;
+            }
+
+            let row = (map_y_tile / self.tileset.tile_height as i32) as usize;
+            let col_min = (map_x_min / self.tileset.tile_width as i32) as usize;
+            let mut tiles = self.tiles.row(row).skip(col_min);
+            let mut flips = self.flips.row(row).skip(col_min);
             for map_x_tile in (map_x_min..map_x_max).step_by(self.tileset.tile_width as usize) {
-                let row = map_y_tile / self.tileset.tile_height as i32;
-                let col = map_x_tile / self.tileset.tile_width as i32;
-                let tile_index = row * self.width_tiles as i32 + col;
-                let tile = self.tiles[tile_index as usize];
-                self.tileset
-                    .blit(tile, map_x_tile + map_x_to_x, map_y_tile + map_y_to_y);
+                let tile = tiles.next().unwrap_or(0);
+                let flip = flips.next().unwrap_or(0);
+                self.tileset.blit(
+                    self.tileset.animated(tile, frame),
+                    flip,
+                    map_x_tile + layer_x_to_x,
+                    map_y_tile + layer_y_to_y,
+                );
+            }
+        }
+
+        // Anything below the last visible row is in front of everything.
+        for actor in actors {
+            actor.draw(map_x_to_x, map_y_to_y);
+        }
+    }
+}
+
+/// Map image, usually a parallax background, from a Tiled image layer.
+pub struct ImageLayer<'a> {
+    pub image: &'a Unisprite<&'a [u8]>,
+    /// Where the image's top left corner is, in map pixels.
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// How fast the image scrolls compared to the map, in 256ths:
+    /// 256 scrolls with the map, and 0 doesn't scroll.
+    pub parallax_x: i32,
+    pub parallax_y: i32,
+    /// 0 is transparent, 255 is opaque.
+    pub opacity: u8,
+    pub properties: Properties<'a>,
+}
+
+impl ImageLayer<'_> {
+    /// Draw the part of the image seen from the map position `map_x`, `map_y`
+    /// at screen position `x`, `y`.
+    pub fn draw(&self, x: i32, y: i32, map_x: i32, map_y: i32) {
+        if self.opacity < MIN_OPACITY {
+            return;
+        }
+        self.image.draw(
+            x + self.offset_x - map_x * self.parallax_x / 256,
+            y + self.offset_y - map_y * self.parallax_y / 256,
+        );
+    }
+}
+
+/// Tile or image layer, for drawing all of a map's layers in order.
+pub enum MapLayer<'a> {
+    Tiles(&'a Layer<'a>),
+    Image(&'a ImageLayer<'a>),
+}
+
+/// Draw a map's layers, bottom to top, like [`Layer::draw_with_actors`].
+/// Actors are drawn among the rows of the top tile layer.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_map(
+    layers: &[MapLayer],
+    x: i32,
+    y: i32,
+    map_x: i32,
+    map_y: i32,
+    w: u32,
+    h: u32,
+    actors: &mut [Actor],
+) {
+    let top_tiles = layers
+        .iter()
+        .rposition(|layer| matches!(layer, MapLayer::Tiles(_)));
+    for (i, layer) in layers.iter().enumerate() {
+        match layer {
+            MapLayer::Tiles(layer) if Some(i) == top_tiles => {
+                layer.draw_with_actors(x, y, map_x, map_y, w, h, actors)
+            }
+            MapLayer::Tiles(layer) => layer.draw(x, y, map_x, map_y, w, h),
+            MapLayer::Image(layer) => layer.draw(x, y, map_x, map_y),
+        }
+    }
+}
+
+/// Grid of bytes, run-length encoded one row at a time with PackBits,
+/// so any row can be decoded without decoding the ones above it.
+/// Each packet in a row starts with a control byte `c`:
+/// if `c < 128`, the next `c + 1` bytes are copied as they are;
+/// otherwise the next byte is repeated `c - 126` times.
+pub struct RleRows<'a> {
+    /// Where each row's packets start in `packets`.
+    /// Empty if every value is 0.
+    pub rows: &'a [u16],
+    pub packets: &'a [u8],
+}
+
+impl RleRows<'_> {
+    /// Values in a row, left to right.
+    pub fn row(&self, row: usize) -> RleRow<'_> {
+        let packets = match self.rows.get(row) {
+            Some(&start) => {
+                let end = self
+                    .rows
+                    .get(row + 1)
+                    .map_or(self.packets.len(), |&end| end as usize);
+                &self.packets[start as usize..end]
             }
+            None => &[],
+        };
+        RleRow {
+            packets,
+            literal: false,
+            remaining: 0,
+            value: 0,
         }
     }
+
+    /// Value at a row and column, or 0 if it's out of bounds.
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.row(row).nth(col).unwrap_or(0)
+    }
+}
+
+/// Decoder for one row of `RleRows`.
+pub struct RleRow<'a> {
+    packets: &'a [u8],
+    /// Is the current packet a literal or a repeat?
+    literal: bool,
+    /// Values left in the current packet.
+    remaining: u8,
+    /// Value for a repeat packet.
+    value: u8,
+}
+
+impl Iterator for RleRow<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.remaining == 0 {
+            let (&control, rest) = self.packets.split_first()?;
+            self.packets = rest;
+            if control < 128 {
+                self.literal = true;
+                self.remaining = control + 1;
+            } else {
+                self.literal = false;
+                self.remaining = control - 126;
+                let (&value, rest) = self.packets.split_first()?;
+                self.packets = rest;
+                self.value = value;
+            }
+        }
+        self.remaining -= 1;
+        if self.literal {
+            let (&value, rest) = self.packets.split_first()?;
+            self.packets = rest;
+            Some(value)
+        } else {
+            Some(self.value)
+        }
+    }
+}
+
+/// Named shape on a map, from a Tiled object layer: doors, NPC spawn points, paths, and so on.
+pub struct Region<'a> {
+    pub name: &'a str,
+    /// Tiled class. May be empty.
+    pub class: &'a str,
+    pub shape: Shape<'a>,
+    /// Bounding box, in map pixels. Points have no width or height.
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+    pub properties: Properties<'a>,
+}
+
+/// Tiled object shapes. Points are in map pixels.
+pub enum Shape<'a> {
+    Rectangle,
+    Ellipse,
+    Point,
+    Polyline(&'a [(i32, i32)]),
+    Polygon(&'a [(i32, i32)]),
+}
+
+impl<'a> Region<'a> {
+    /// Does this region's bounding box overlap a rectangle (map pixels)?
+    /// Points and lines with no width or height still cover the pixels they're on.
+    pub fn overlaps(&self, x: i32, y: i32, w: u32, h: u32) -> bool {
+        let (self_w, self_h) = (max(self.w, 1), max(self.h, 1));
+        x < self.x + self_w as i32
+            && self.x < x + w as i32
+            && y < self.y + self_h as i32
+            && self.y < y + h as i32
+    }
+
+    /// Value of a custom property, if it's set.
+    pub fn property(&self, name: &str) -> Option<PropertyValue<'a>> {
+        property(self.properties, name)
+    }
+}
+
+/// Character drawn between rows of a map layer: the player, NPCs, gathering spots.
+pub struct Actor<'a> {
+    pub sprite: &'a CharacterSprite<'a>,
+    /// Map coordinates of the sprite's top left corner.
+    pub x: i32,
+    pub y: i32,
+    /// Walk cycle frame.
+    pub w: usize,
+    pub o: Orientation,
+}
+
+impl Actor<'_> {
+    /// Sprite hitbox in map pixels: x, y, width, height.
+    pub fn hitbox(&self) -> (i32, i32, u32, u32) {
+        let (x, y, w, h) = self.sprite.hitbox;
+        (self.x + x, self.y + y, w, h)
+    }
+
+    /// Map Y coordinate of the bottom of the sprite.
+    fn feet(&self) -> i32 {
+        self.y + self.sprite.image_h as i32
+    }
+
+    fn draw(&self, map_x_to_x: i32, map_y_to_y: i32) {
+        self.sprite
+            .draw(self.x + map_x_to_x, self.y + map_y_to_y, self.w, self.o);
+    }
 }
 
 // endregion map
@@ -1,35 +1,7 @@
 use crate::asset_data;
-use crate::gfx::{CharacterSprite, Cursor, Lo5SplitSprite};
+use crate::gfx::{CharacterSprite, Cursor};
 use crate::wasm4;
 
-pub const ESRI: Lo5SplitSprite = Lo5SplitSprite {
-    w: asset_data::ESRI_LO4_WIDTH,
-    h: asset_data::ESRI_LO4_HEIGHT,
-    lo4: &asset_data::ESRI_LO4,
-    hi2: &asset_data::ESRI_HI2,
-};
-
-pub const ALLIE: Lo5SplitSprite = Lo5SplitSprite {
-    w: asset_data::ALLIE_LO4_WIDTH,
-    h: asset_data::ALLIE_LO4_HEIGHT,
-    lo4: &asset_data::ALLIE_LO4,
-    hi2: &asset_data::ALLIE_HI2,
-};
-
-pub const SAE: Lo5SplitSprite = Lo5SplitSprite {
-    w: asset_data::SAE_LO4_WIDTH,
-    h: asset_data::SAE_LO4_HEIGHT,
-    lo4: &asset_data::SAE_LO4,
-    hi2: &asset_data::SAE_HI2,
-};
-
-pub const KMRPG: Lo5SplitSprite = Lo5SplitSprite {
-    w: asset_data::KENNEY_MONOCHROMERPG_EXTENDED_LO4_WIDTH,
-    h: asset_data::KENNEY_MONOCHROMERPG_EXTENDED_LO4_HEIGHT,
-    lo4: &asset_data::KENNEY_MONOCHROMERPG_EXTENDED_LO4,
-    hi2: &asset_data::KENNEY_MONOCHROMERPG_EXTENDED_HI2,
-};
-
 pub const GUNGIRL: CharacterSprite = CharacterSprite {
     image_w: asset_data::GUNGIRL_WIDTH,
     image_h: asset_data::GUNGIRL_HEIGHT,
@@ -47,6 +19,13 @@ pub const GUNGIRL: CharacterSprite = CharacterSprite {
         (12, asset_data::GUNGIRL_FLAGS),
         (8, asset_data::GUNGIRL_FLAGS),
     ],
+    hitbox: (4, 10, 8, 6),
+};
+
+/// Stand-in for townies until they have their own sprites: the player sprite in other colors.
+pub const TOWNIE: CharacterSprite = CharacterSprite {
+    draw_colors: 0x4230,
+    ..GUNGIRL
 };
 
 pub const CURSOR_POINT: &Cursor = &Cursor {
@@ -16,3 +16,39 @@ pub fn mouse() -> MouseState {
         right: buttons & wasm4::MOUSE_RIGHT != 0,
     }
 }
+
+static mut PREV_GAMEPAD: u8 = 0;
+static mut GAMEPAD_PRESSED: u8 = 0;
+static mut PREV_MOUSE_BUTTONS: u8 = 0;
+static mut MOUSE_PRESSED: u8 = 0;
+
+/// Call once at the start of every frame, before checking for presses.
+pub fn update() {
+    unsafe {
+        let gamepad = *wasm4::GAMEPAD1;
+        GAMEPAD_PRESSED = gamepad & !PREV_GAMEPAD;
+        PREV_GAMEPAD = gamepad;
+
+        let mouse_buttons = *wasm4::MOUSE_BUTTONS;
+        MOUSE_PRESSED = mouse_buttons & !PREV_MOUSE_BUTTONS;
+        PREV_MOUSE_BUTTONS = mouse_buttons;
+    }
+}
+
+/// Forget this frame's presses and clicks, so nothing else acts on them.
+pub fn clear() {
+    unsafe {
+        GAMEPAD_PRESSED = 0;
+        MOUSE_PRESSED = 0;
+    }
+}
+
+/// Was this gamepad button (`wasm4::BUTTON_*`) pressed this frame, as opposed to being held down?
+pub fn pressed(button: u8) -> bool {
+    unsafe { GAMEPAD_PRESSED & button != 0 }
+}
+
+/// Was this mouse button (`wasm4::MOUSE_*`) clicked this frame, as opposed to being held down?
+pub fn clicked(button: u8) -> bool {
+    unsafe { MOUSE_PRESSED & button != 0 }
+}
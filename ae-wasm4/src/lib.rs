@@ -4,43 +4,34 @@ mod alloc;
 mod asset_data;
 mod audio;
 mod audio_data;
+mod camera;
+mod cinematic;
+mod cinematic_data;
 mod font;
 mod gfx;
 mod gfx_data;
 mod input;
-mod intro;
 mod map_data;
+mod npc;
+mod save;
+mod scene;
+mod story;
+mod title;
 mod walkaround;
 mod wasm4;
 
-enum Mode {
-    Intro,
-    Alchemy,
-}
-
-static mut MODE: Mode = Mode::Intro;
-
 #[no_mangle]
 fn start() {
     // audio::init();
     // audio::music(0);
-    intro::init();
-    // alchemy::init();
+    alchemy::init();
+    scene::init();
 }
 
 #[no_mangle]
 fn update() {
     // audio::music_update();
-
-    unsafe {
-        match MODE {
-            Mode::Intro => {
-                let continue_intro = intro::update();
-                if !continue_intro {
-                    MODE = Mode::Alchemy;
-                }
-            }
-            Mode::Alchemy => alchemy::update(),
-        }
-    }
+    input::update();
+    gfx::tick();
+    scene::update();
 }
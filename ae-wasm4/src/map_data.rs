@@ -1,59 +1,3 @@
-use crate::gfx::{Layer, Tileset};
-use crate::gfx_data;
+//! Include the file generated from Tiled maps by `aetools maps-code`.
 
-/// TODO: add Tiled .tsx tileset files to build script
-pub const KMRPG: Tileset = Tileset {
-    tile_width: 16,
-    tile_height: 16,
-    image: &gfx_data::KMRPG,
-};
-
-/// TODO: add Tiled .tmx map files to build script
-pub const VILLAGE_GROUND: Layer = Layer {
-    width_tiles: 27,
-    height_tiles: 15,
-    tileset: &KMRPG,
-    tiles: &[
-        15, 1, 1, 12, 12, 11, 31, 32, 30, 31, 31, 13, 1, 1, 58, 1, 1, 1, 49, 31, 31, 32, 1, 1, 50,
-        50, 1, 1, 1, 11, 31, 31, 31, 31, 32, 30, 31, 31, 31, 13, 20, 39, 20, 1, 1, 131, 50, 50, 1,
-        1, 12, 12, 1, 1, 1, 30, 31, 31, 31, 31, 51, 1, 1, 50, 49, 31, 31, 58, 20, 1, 1, 1, 1, 1,
-        71, 1, 30, 31, 31, 13, 1, 1, 30, 31, 31, 31, 51, 1, 1, 14, 1, 1, 50, 50, 145, 88, 147, 1,
-        1, 1, 70, 1, 70, 30, 31, 31, 31, 13, 97, 1, 50, 50, 50, 1, 58, 1, 39, 1, 73, 72, 117, 103,
-        123, 105, 102, 20, 20, 1, 1, 1, 20, 50, 50, 49, 31, 117, 1, 77, 1, 14, 1, 46, 47, 48, 7,
-        72, 73, 99, 98, 119, 98, 98, 98, 98, 98, 97, 20, 20, 58, 1, 30, 31, 117, 1, 1, 14, 70, 70,
-        103, 122, 105, 45, 1, 39, 117, 1, 70, 70, 14, 145, 147, 48, 118, 90, 127, 91, 1, 11, 31,
-        119, 98, 98, 98, 98, 98, 98, 100, 98, 98, 98, 100, 116, 1, 58, 58, 1, 120, 121, 105, 117,
-        110, 130, 111, 30, 31, 31, 12, 12, 12, 20, 39, 20, 74, 117, 70, 39, 77, 117, 39, 58, 1, 58,
-        77, 1, 58, 1, 115, 98, 119, 98, 97, 50, 50, 31, 31, 31, 46, 47, 48, 1, 117, 1, 33, 1, 117,
-        46, 103, 124, 105, 1, 1, 1, 58, 1, 58, 1, 1, 117, 39, 1, 31, 31, 51, 120, 123, 121, 1, 117,
-        14, 1, 1, 117, 103, 124, 123, 105, 70, 58, 1, 20, 77, 1, 39, 20, 117, 20, 32, 51, 50, 1,
-        70, 115, 98, 98, 116, 1, 1, 14, 115, 98, 98, 118, 39, 77, 145, 88, 147, 102, 87, 46, 148,
-        48, 87, 12, 32, 1, 14, 1, 1, 33, 77, 1, 1, 1, 102, 102, 102, 77, 117, 1, 14, 103, 122, 105,
-        102, 106, 124, 123, 124, 106, 31, 14, 1, 1, 1, 1, 1, 1, 1, 1, 1, 5, 6, 6, 7, 117, 1, 1, 39,
-        117, 1, 1, 12, 16, 117, 16, 77, 50, 1, 1, 1, 1, 1, 5, 6, 6, 6, 6, 38, 25, 25, 26, 115, 98,
-        98, 98, 116, 1, 11, 31, 32, 117, 1, 12, 1,
-    ],
-};
-
-pub const VILLAGE_BUILDINGS: Layer = Layer {
-    width_tiles: 27,
-    height_tiles: 15,
-    tileset: &KMRPG,
-    tiles: &[
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 89, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 107, 108, 109, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 126, 127, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 9,
-        10, 0, 0, 0, 0, 0, 0, 0, 0, 107, 109, 0, 0, 0, 89, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 126, 128, 10, 0, 107, 108, 109, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 8, 9, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 9, 10, 0, 0, 0, 0, 0, 0, 8, 46,
-        47, 48, 0, 0, 89, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 107, 108, 109, 0, 89, 0, 89, 0, 89, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 126, 127, 128, 0, 68, 8, 87, 10, 68, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0,
-    ],
-};
+include!(concat!(env!("OUT_DIR"), "/map_data.rs"));
@@ -0,0 +1,168 @@
+//! Townies on the walkaround map.
+//!
+//! NPCs come from map object layers: every object with the `npc` class is one.
+//! Its position is where the sprite's top left corner goes.
+//! Townies don't have their own sprites yet, so they all look like a recolored player.
+//! Points and rectangles stand where they're put. Polylines walk from one end to the other
+//! and back, and polygons walk around and around. Either way, they stop at every vertex
+//! for the `pause` property's number of frames.
+//! Talking to one plays the cinematic script named by its `cinematic` property.
+
+use crate::gfx::{Actor, CharacterSprite, Orientation, PropertyValue, Region, Shape};
+use crate::gfx_data;
+
+const NPC_CLASS: &str = "npc";
+
+/// NPCs take a step every this many frames, so they amble along at half the player's speed.
+const STEP_FRAMES: u32 = 2;
+
+pub struct Npc {
+    /// Cinematic script to play when the player talks to them. May be empty.
+    pub cinematic: &'static str,
+    sprite: &'static CharacterSprite<'static>,
+    x: i32,
+    y: i32,
+    o: Orientation,
+    /// Walk cycle frame.
+    w: usize,
+    /// Empty for NPCs that stand still.
+    path: &'static [(i32, i32)],
+    /// Polygons loop around. Polylines turn around at the ends.
+    closed: bool,
+    /// Index of the path vertex they're walking to.
+    target: usize,
+    /// Walking toward the end of the path rather than back to the start.
+    forward: bool,
+    /// Frames to stand at each vertex.
+    pause: u32,
+    /// Frames left before walking on.
+    wait: u32,
+    /// Frames since the last step.
+    frame: u32,
+    /// Standing still for a conversation.
+    talking: bool,
+}
+
+/// NPCs from a map object layer, in layer order.
+pub fn spawn(regions: &'static [Region<'static>]) -> Vec<Npc> {
+    regions
+        .iter()
+        .filter(|region| region.class == NPC_CLASS)
+        .map(Npc::new)
+        .collect()
+}
+
+impl Npc {
+    fn new(region: &'static Region<'static>) -> Self {
+        let (path, closed) = match region.shape {
+            Shape::Polyline(points) => (points, false),
+            Shape::Polygon(points) => (points, true),
+            _ => (&[][..], false),
+        };
+        let (x, y) = path.first().copied().unwrap_or((region.x, region.y));
+        let pause = match region.property("pause") {
+            Some(PropertyValue::Int(pause)) => pause.max(0) as u32,
+            _ => 0,
+        };
+        Self {
+            cinematic: region
+                .property("cinematic")
+                .and_then(|value| value.as_str())
+                .unwrap_or(""),
+            sprite: &gfx_data::TOWNIE,
+            x,
+            y,
+            o: Orientation::default(),
+            w: 0,
+            path,
+            closed,
+            target: if path.len() > 1 { 1 } else { 0 },
+            forward: true,
+            pause,
+            wait: pause,
+            frame: 0,
+            talking: false,
+        }
+    }
+
+    /// Call once per frame to walk along the path.
+    /// `blocked` says whether the NPC would bump into something (like the player)
+    /// with its sprite at a map position; if so, it waits for the way to clear.
+    pub fn update(&mut self, blocked: impl Fn(&Actor) -> bool) {
+        if self.talking || self.path.len() < 2 {
+            return;
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            self.w = 0;
+            return;
+        }
+        self.frame += 1;
+        if self.frame < STEP_FRAMES {
+            return;
+        }
+        self.frame = 0;
+
+        let (target_x, target_y) = self.path[self.target];
+        let (dx, dy) = ((target_x - self.x).signum(), (target_y - self.y).signum());
+        let mut next = self.actor();
+        (next.x, next.y) = (self.x + dx, self.y + dy);
+        if blocked(&next) {
+            self.w = 0;
+            return;
+        }
+        (self.x, self.y) = (next.x, next.y);
+        self.o = Orientation::from((dx, dy));
+        self.w = (self.w + 1) % self.sprite.walk_cycle_length;
+
+        if (self.x, self.y) == (target_x, target_y) {
+            self.w = 0;
+            self.wait = self.pause;
+            self.next_target();
+        }
+    }
+
+    fn next_target(&mut self) {
+        if self.closed {
+            self.target = (self.target + 1) % self.path.len();
+            return;
+        }
+        if self.target == self.path.len() - 1 {
+            self.forward = false;
+        } else if self.target == 0 {
+            self.forward = true;
+        }
+        if self.forward {
+            self.target += 1;
+        } else {
+            self.target -= 1;
+        }
+    }
+
+    /// Stop and turn to look at whoever's talking to them, at a map point like the middle
+    /// of the player. They stay that way until [`Npc::walk_on`].
+    pub fn talk(&mut self, x: i32, y: i32) {
+        let (center_x, center_y) = (
+            self.x + self.sprite.sprite_w as i32 / 2,
+            self.y + self.sprite.image_h as i32 / 2,
+        );
+        self.o = Orientation::toward(x - center_x, y - center_y);
+        self.w = 0;
+        self.talking = true;
+    }
+
+    /// Go back to walking after a conversation.
+    pub fn walk_on(&mut self) {
+        self.talking = false;
+    }
+
+    pub fn actor(&self) -> Actor<'static> {
+        Actor {
+            sprite: self.sprite,
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            o: self.o,
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Save games, stored in WASM-4's 1 KiB of persistent storage.
+//!
+//! Layout, all numbers little-endian:
+//! - header:
+//!   - magic: `b"AE"`
+//!   - format version: `u8`
+//!   - body length: `u16`
+//!   - Fletcher-16 checksum of the body: `u16`
+//! - body, version 3:
+//!   - where the player is: `u8`, 0 for the map, 1 for the cauldron
+//!   - walkaround player x, y: `u16` each
+//!   - walkaround player orientation: `u8`
+//!   - story flags: `story::FLAG_BYTES` bytes
+//!   - int story flags: `story::INT_FLAG_COUNT` of them, `u16` each, two's complement
+//!   - inventory item count: `u8`, then that many items (see `alchemy::write_inventory`)
+
+use crate::gfx::Orientation;
+use crate::scene::{self, Scene};
+use crate::wasm4::trace;
+use crate::{alchemy, story, walkaround, wasm4};
+
+const MAGIC: &[u8; 2] = b"AE";
+/// Bump this when the body layout changes, and add a step to `migrate`.
+const VERSION: u8 = 3;
+const HEADER_LEN: usize = 7;
+const DISK_SIZE: usize = 1024;
+
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn new() -> Writer {
+        Writer { bytes: Vec::new() }
+    }
+
+    pub fn u8(&mut self, x: u8) {
+        self.bytes.push(x);
+    }
+
+    pub fn u16(&mut self, x: u16) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, x: &[u8]) {
+        self.bytes.extend_from_slice(x);
+    }
+}
+
+/// Reads return `None` if there aren't enough bytes left.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes }
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        let bytes = self.bytes(2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Some(head)
+    }
+}
+
+fn fletcher16(bytes: &[u8]) -> u16 {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for byte in bytes {
+        sum1 = (sum1 + *byte as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+/// Save the game. Returns whether it fit on the disk.
+pub fn save() -> bool {
+    let mut body = Writer::new();
+
+    body.u8(scene::stack().contains(&Scene::Alchemy) as u8);
+
+    let (x, y, o) = walkaround::position();
+    body.u16(x as u16);
+    body.u16(y as u16);
+    body.u8(o as u8);
+
+    body.bytes(&story::flags());
+    for int_flag in story::int_flags() {
+        body.u16(int_flag as u16);
+    }
+
+    alchemy::write_inventory(&mut body);
+
+    if HEADER_LEN + body.bytes.len() > DISK_SIZE {
+        trace("Save game doesn't fit on disk");
+        return false;
+    }
+
+    let mut disk = Writer::new();
+    disk.bytes(MAGIC);
+    disk.u8(VERSION);
+    disk.u16(body.bytes.len() as u16);
+    disk.u16(fletcher16(&body.bytes));
+    disk.bytes(&body.bytes);
+    unsafe { wasm4::diskw(disk.bytes.as_ptr(), disk.bytes.len() as u32) };
+    true
+}
+
+/// Is there a saved game that isn't obviously broken?
+pub fn exists() -> bool {
+    read_body().is_some()
+}
+
+/// Read the disk and return the save body, upgraded to the current format version,
+/// if there's a save and its checksum matches.
+fn read_body() -> Option<Vec<u8>> {
+    let mut disk = [0u8; DISK_SIZE];
+    let disk_len = unsafe { wasm4::diskr(disk.as_mut_ptr(), disk.len() as u32) } as usize;
+    let mut header = Reader::new(&disk[..disk_len]);
+
+    if header.bytes(MAGIC.len())? != MAGIC {
+        return None;
+    }
+    let version = header.u8()?;
+    let body_len = header.u16()? as usize;
+    let checksum = header.u16()?;
+    let body = header.bytes(body_len)?;
+    if fletcher16(body) != checksum {
+        trace("Save game checksum doesn't match");
+        return None;
+    }
+    migrate(version, body)
+}
+
+/// Load the saved game, if there is one.
+/// Returns the scenes to resume with if it loaded; if it didn't, the game is left as it was.
+pub fn load() -> Option<Vec<Scene>> {
+    let body = read_body()?;
+
+    // Read everything before changing anything, so a bad save can't leave the game half-loaded.
+    let mut r = Reader::new(&body);
+    let Some((scenes, position, flags, int_flags, inventory)) = (|| {
+        let scenes = match r.u8()? {
+            0 => vec![Scene::Walkaround],
+            1 => vec![Scene::Walkaround, Scene::Alchemy],
+            _ => return None,
+        };
+        let position = (
+            r.u16()? as i32,
+            r.u16()? as i32,
+            *Orientation::ALL.get(r.u8()? as usize)?,
+        );
+        let flags = r.bytes(story::FLAG_BYTES)?.try_into().ok()?;
+        let mut int_flags = [0; story::INT_FLAG_COUNT];
+        for int_flag in &mut int_flags {
+            *int_flag = r.u16()? as i16;
+        }
+        let inventory = alchemy::read_inventory(&mut r)?;
+        Some((scenes, position, flags, int_flags, inventory))
+    })() else {
+        trace("Save game is corrupt");
+        return None;
+    };
+
+    let (x, y, o) = position;
+    walkaround::set_position(x, y, o);
+    story::set_flags(flags);
+    story::set_int_flags(int_flags);
+    alchemy::restore_inventory(inventory);
+    Some(scenes)
+}
+
+/// Upgrade a save body from an older format version to the current one, one version at a time.
+/// When the format changes, add a step here that converts the previous version's body
+/// and passes it on to the next step, like `1 => migrate(2, &v1_to_v2(body)?)`.
+fn migrate(version: u8, body: &[u8]) -> Option<Vec<u8>> {
+    match version {
+        VERSION => Some(body.to_vec()),
+        1 => migrate(2, &v1_to_v2(body)?),
+        2 => migrate(3, &v2_to_v3(body)?),
+        _ => {
+            trace("Save game is from an unknown version");
+            None
+        }
+    }
+}
+
+/// Version 1 started with the game mode instead of where the player is:
+/// 0 for the intro, which was only a test screen, or 1 for alchemy.
+/// Those are the numbers for the map and the cauldron, which is where they resume.
+fn v1_to_v2(body: &[u8]) -> Option<Vec<u8>> {
+    match body.first()? {
+        0 | 1 => Some(body.to_vec()),
+        _ => None,
+    }
+}
+
+/// Version 3 added int story flags after the bool ones. They start at 0.
+fn v2_to_v3(body: &[u8]) -> Option<Vec<u8>> {
+    // Where the player is, position, orientation, then story flags.
+    let flags_end = 1 + 2 + 2 + 1 + story::FLAG_BYTES;
+    if body.len() < flags_end {
+        return None;
+    }
+    let mut v3 = body[..flags_end].to_vec();
+    v3.extend_from_slice(&[0; 2 * story::INT_FLAG_COUNT]);
+    v3.extend_from_slice(&body[flags_end..]);
+    Some(v3)
+}
@@ -0,0 +1,177 @@
+//! Scene stack: which part of the game is running, and fades between them.
+//!
+//! Only the top scene gets updated. Pushing a scene enters it on top of the current one,
+//! which stays where it was underneath; popping exits the top scene and goes back to that one.
+//! Every change fades out to black, swaps scenes, and fades back in.
+
+use crate::wasm4::PALETTE;
+use crate::{alchemy, cinematic, input, title, walkaround};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    Title,
+    Walkaround,
+    Cinematic,
+    Alchemy,
+    Inventory,
+}
+
+impl Scene {
+    fn enter(self) {
+        match self {
+            Scene::Title => title::enter(),
+            Scene::Walkaround => walkaround::enter(),
+            Scene::Cinematic => cinematic::enter(),
+            Scene::Alchemy => alchemy::enter(),
+            Scene::Inventory => alchemy::inventory_enter(),
+        }
+    }
+
+    fn exit(self) {
+        match self {
+            Scene::Title => {}
+            Scene::Walkaround => {}
+            Scene::Cinematic => cinematic::exit(),
+            Scene::Alchemy => alchemy::exit(),
+            Scene::Inventory => {}
+        }
+    }
+
+    fn update(self) {
+        match self {
+            Scene::Title => title::update(),
+            Scene::Walkaround => walkaround::update(),
+            Scene::Cinematic => cinematic::update(),
+            Scene::Alchemy => alchemy::update(),
+            Scene::Inventory => alchemy::inventory_update(),
+        }
+    }
+}
+
+enum Change {
+    Push(Scene),
+    Pop,
+    /// Throw out the whole stack and start over with these scenes, bottom first.
+    Reset(Vec<Scene>),
+}
+
+struct Transition {
+    change: Option<Change>,
+    /// Counts up from 0 to `2 * FADE_FRAMES`. The change happens halfway through.
+    frame: u32,
+}
+
+/// Length of a fade out or a fade in.
+const FADE_FRAMES: u32 = 12;
+
+static mut STACK: Vec<Scene> = vec![];
+static mut TRANSITION: Option<Transition> = None;
+/// Palette to fade from and back to.
+static mut BASE_PALETTE: [u32; 4] = [0; 4];
+
+/// Start at the title screen, with the current palette as the one to fade back to.
+pub fn init() {
+    unsafe {
+        BASE_PALETTE = *PALETTE;
+        STACK = vec![Scene::Title];
+    }
+    Scene::Title.enter();
+}
+
+/// Scenes from bottom to top.
+pub fn stack() -> &'static [Scene] {
+    unsafe { &STACK }
+}
+
+/// Go to a scene on top of this one. Ignored if a change is already in progress.
+pub fn push(scene: Scene) {
+    request(Change::Push(scene));
+}
+
+/// Go back to the scene under this one. Ignored if a change is already in progress.
+pub fn pop() {
+    request(Change::Pop);
+}
+
+/// Replace every scene with these, bottom first. Ignored if a change is already in progress.
+pub fn reset(scenes: Vec<Scene>) {
+    request(Change::Reset(scenes));
+}
+
+/// Is the screen fading between scenes?
+pub fn changing() -> bool {
+    unsafe { TRANSITION.is_some() }
+}
+
+fn request(change: Change) {
+    unsafe {
+        if TRANSITION.is_none() {
+            TRANSITION = Some(Transition {
+                change: Some(change),
+                frame: 0,
+            });
+        }
+    }
+}
+
+pub fn update() {
+    let transition = unsafe { TRANSITION.as_mut() };
+    if let Some(transition) = transition {
+        // Nobody gets to act on presses while the screen is fading.
+        input::clear();
+
+        if transition.frame == FADE_FRAMES {
+            if let Some(change) = transition.change.take() {
+                apply(change);
+            }
+        }
+        let brightness = transition.frame.abs_diff(FADE_FRAMES);
+        set_brightness(brightness, FADE_FRAMES);
+
+        transition.frame += 1;
+        if transition.frame > 2 * FADE_FRAMES {
+            unsafe { TRANSITION = None };
+        }
+    }
+
+    if let Some(scene) = stack().last() {
+        scene.update();
+    }
+}
+
+/// Scenes are exited after they come off the stack and entered after they go on,
+/// so hooks that look at the stack (like saving) see where the game is going.
+fn apply(change: Change) {
+    let stack = unsafe { &mut STACK };
+    match change {
+        Change::Push(scene) => {
+            stack.push(scene);
+            scene.enter();
+        }
+        Change::Pop => {
+            // There has to be something to go back to.
+            if stack.len() > 1 {
+                stack.pop().unwrap().exit();
+            }
+        }
+        Change::Reset(scenes) => {
+            while let Some(scene) = stack.pop() {
+                scene.exit();
+            }
+            for scene in scenes {
+                stack.push(scene);
+                scene.enter();
+            }
+        }
+    }
+}
+
+/// Scale every palette color toward black. `numerator / denominator` is the fraction left.
+fn set_brightness(numerator: u32, denominator: u32) {
+    let base = unsafe { BASE_PALETTE };
+    let palette = base.map(|color| {
+        let channel = |shift: u32| (((color >> shift) & 0xff) * numerator / denominator) << shift;
+        channel(16) | channel(8) | channel(0)
+    });
+    unsafe { *PALETTE = palette };
+}
@@ -0,0 +1,57 @@
+//! Story flags: one bit each for things that have happened in the game,
+//! and a few numbers for things worth counting.
+//! Cinematics name them in `cinematics/story_flags.txt`; these are the IDs they get here.
+
+pub type StoryFlag = u8;
+
+/// How many int flags there's room for. `aetools cinematics-code` checks that scripts fit.
+pub const INT_FLAG_COUNT: usize = 16;
+
+/// Enough bytes for every possible `StoryFlag`.
+pub const FLAG_BYTES: usize = (StoryFlag::MAX as usize + 1) / 8;
+
+static mut FLAGS: [u8; FLAG_BYTES] = [0; FLAG_BYTES];
+static mut INT_FLAGS: [i16; INT_FLAG_COUNT] = [0; INT_FLAG_COUNT];
+
+pub fn flag(flag: StoryFlag) -> bool {
+    unsafe { FLAGS[flag as usize / 8] & (1 << (flag % 8)) != 0 }
+}
+
+pub fn set_flag(flag: StoryFlag, value: bool) {
+    let (byte, bit) = (flag as usize / 8, 1 << (flag % 8));
+    unsafe {
+        if value {
+            FLAGS[byte] |= bit;
+        } else {
+            FLAGS[byte] &= !bit;
+        }
+    }
+}
+
+/// All flags, packed, for saving.
+pub fn flags() -> [u8; FLAG_BYTES] {
+    unsafe { FLAGS }
+}
+
+/// Replace all flags, for loading.
+pub fn set_flags(flags: [u8; FLAG_BYTES]) {
+    unsafe { FLAGS = flags };
+}
+
+pub fn int_flag(flag: usize) -> i16 {
+    unsafe { INT_FLAGS[flag] }
+}
+
+pub fn set_int_flag(flag: usize, value: i16) {
+    unsafe { INT_FLAGS[flag] = value };
+}
+
+/// All int flags, for saving.
+pub fn int_flags() -> [i16; INT_FLAG_COUNT] {
+    unsafe { INT_FLAGS }
+}
+
+/// Replace all int flags, for loading.
+pub fn set_int_flags(flags: [i16; INT_FLAG_COUNT]) {
+    unsafe { INT_FLAGS = flags };
+}
@@ -0,0 +1,39 @@
+use crate::asset_data::item_unisprite::AXE;
+use crate::font::TINY;
+use crate::gfx::Sprite;
+use crate::scene::{self, Scene};
+use crate::wasm4::{BUTTON_1, BUTTON_2};
+use crate::{cinematic, input, save, wasm4};
+
+static mut HAS_SAVE: bool = false;
+
+pub fn enter() {
+    unsafe { HAS_SAVE = save::exists() };
+}
+
+pub fn update() {
+    AXE.draw2x(64, 40);
+
+    unsafe { *wasm4::DRAW_COLORS = 0x340 };
+    let title = "Atelier Esri";
+    let (title_w, _) = TINY.metrics(title);
+    TINY.text(title, (wasm4::SCREEN_SIZE - title_w) as i32 / 2, 90);
+
+    let has_save = unsafe { HAS_SAVE };
+    if has_save {
+        TINY.text("X: Continue", 56, 120);
+    }
+    TINY.text("Z: New game", 56, 130);
+
+    if has_save && input::pressed(BUTTON_1) {
+        match save::load() {
+            Some(scenes) => scene::reset(scenes),
+            None => unsafe { HAS_SAVE = false },
+        }
+    }
+    if input::pressed(BUTTON_2) {
+        // The opening plays over the map, and the player ends up there when it's done.
+        cinematic::queue(cinematic::INTRO);
+        scene::reset(vec![Scene::Walkaround, Scene::Cinematic]);
+    }
+}
@@ -1,29 +1,71 @@
-use crate::gfx::Orientation;
-use crate::gfx_data;
-use crate::map_data;
+use crate::camera::Camera;
+use crate::gfx::{self, Actor, Orientation};
+use crate::npc::{self, Npc};
+use crate::scene::{self, Scene};
 use crate::wasm4::{
-    BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, DRAW_COLORS, GAMEPAD1, SCREEN_SIZE,
+    BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, DRAW_COLORS, GAMEPAD1,
+    SCREEN_SIZE,
 };
+use crate::{cinematic, gfx_data, input, map_data};
 use std::cmp::{max, min};
+use std::ptr::addr_of_mut;
 
-static mut PLAYER_X: i32 = 0;
-static mut PLAYER_Y: i32 = 0;
+// New games start outside Esri's house.
+static mut PLAYER_X: i32 = 224;
+static mut PLAYER_Y: i32 = 96;
 // Can't do `::default()` in a const context. Tragic.
 static mut PLAYER_O: Orientation = Orientation::S;
 static mut PLAYER_W: usize = 0;
 
+static mut CAMERA: Camera = Camera::new(SCREEN_SIZE, SCREEN_SIZE);
+static mut TOWNIES: Vec<Npc> = vec![];
+
+/// How far past the player's hitbox they can reach to talk to someone, in pixels.
+const TALK_REACH: i32 = 4;
+
+/// Where the player is on the map, for saving.
+pub fn position() -> (i32, i32, Orientation) {
+    unsafe { (PLAYER_X, PLAYER_Y, PLAYER_O) }
+}
+
+/// Put the player somewhere on the map, for loading.
+pub fn set_position(x: i32, y: i32, o: Orientation) {
+    unsafe { (PLAYER_X, PLAYER_Y, PLAYER_O, PLAYER_W) = (x, y, o, 0) }
+    let (x, y) = player_center(x, y);
+    camera().snap(x, y);
+}
+
+/// Camera for the walkaround map. Cinematics pan and shake it.
+pub fn camera() -> &'static mut Camera {
+    unsafe { &mut *addr_of_mut!(CAMERA) }
+}
+
+fn townies() -> &'static mut Vec<Npc> {
+    unsafe { &mut *addr_of_mut!(TOWNIES) }
+}
+
+/// Arriving on the map: look at the player without scrolling there,
+/// and put everyone else back where they start.
+pub fn enter() {
+    let (map_w, map_h) = map_data::VILLAGE_GROUND.dimensions();
+    let (player_x, player_y) = unsafe { (PLAYER_X, PLAYER_Y) };
+    let (x, y) = player_center(player_x, player_y);
+    camera().set_map(map_w, map_h, x, y);
+    *townies() = npc::spawn(map_data::VILLAGE_TOWNIES);
+}
+
 pub fn update() {
     let (mut player_x, mut player_y, mut player_o, mut player_w) =
         unsafe { (PLAYER_X, PLAYER_Y, PLAYER_O, PLAYER_W) };
 
     let (map_w, map_h) = map_data::VILLAGE_GROUND.dimensions();
-    let map_x = max(0, min((map_w - SCREEN_SIZE) as i32, player_x));
-    let map_y = max(0, min((map_h - SCREEN_SIZE) as i32, player_y));
 
-    unsafe { *DRAW_COLORS = 0x1234 }
-    map_data::VILLAGE_GROUND.draw(0, 0, map_x, map_y, SCREEN_SIZE, SCREEN_SIZE);
-
-    let gamepad = unsafe { *GAMEPAD1 };
+    // The player stands still while the screen fades, like townies in a conversation.
+    let gamepad = if scene::changing() {
+        0
+    } else {
+        unsafe { *GAMEPAD1 }
+    };
     let mut heading_x = 0;
     let mut heading_y = 0;
     if gamepad & BUTTON_LEFT != 0 {
@@ -45,28 +87,167 @@ pub fn update() {
     } else {
         player_w = 0;
     }
-    player_x = max(
+    // Move along each axis separately, so that walking diagonally into a wall slides along it.
+    let new_x = max(
         0,
         min(
             (map_w - gfx_data::GUNGIRL.sprite_w) as i32,
             player_x + heading_x,
         ),
     );
-    player_y = max(
+    if !blocked(new_x, player_y) {
+        player_x = new_x;
+    }
+    let new_y = max(
         0,
         min(
             (map_h - gfx_data::GUNGIRL.image_h) as i32,
             player_y + heading_y,
         ),
     );
+    if !blocked(player_x, new_y) {
+        player_y = new_y;
+    }
 
-    let player_screen_x = player_x - map_x;
-    let player_screen_y = player_y - map_y;
+    // Conversations last until the fades into and back out of their cinematics are over.
+    let player = player_actor(player_x, player_y, player_o, player_w).hitbox();
+    let townies = townies();
+    for i in 0..townies.len() {
+        let (before, rest) = townies.split_at_mut(i);
+        let (townie, after) = rest.split_first_mut().unwrap();
+        if !scene::changing() {
+            townie.walk_on();
+        }
+        townie.update(|next| {
+            let hitbox = next.hitbox();
+            overlaps(hitbox, player)
+                || solid(hitbox)
+                || before
+                    .iter()
+                    .chain(after.iter())
+                    .any(|other| overlaps(other.actor().hitbox(), hitbox))
+        });
+    }
 
-    gfx_data::GUNGIRL.draw(player_screen_x, player_screen_y, player_w, player_o);
+    unsafe { (PLAYER_X, PLAYER_Y, PLAYER_O, PLAYER_W) = (player_x, player_y, player_o, player_w) }
 
-    // TODO: this won't actually work, we need to draw actors between map rows
-    map_data::VILLAGE_BUILDINGS.draw(0, 0, map_x, map_y, SCREEN_SIZE, SCREEN_SIZE);
+    // Move the camera before drawing, so the view doesn't lag a frame behind the player.
+    let camera = camera();
+    let (x, y) = player_center(player_x, player_y);
+    camera.follow(x, y);
+    camera.update();
 
-    unsafe { (PLAYER_X, PLAYER_Y, PLAYER_O, PLAYER_W) = (player_x, player_y, player_o, player_w) }
+    draw();
+
+    if input::pressed(BUTTON_1) {
+        interact(player_x, player_y);
+    }
+    if input::pressed(BUTTON_2) {
+        scene::push(Scene::Inventory);
+    }
+}
+
+/// Draw the map and everyone on it where the camera is looking, without moving anyone.
+/// Cinematics played from the map draw it behind them.
+pub fn draw() {
+    let (map_x, map_y) = camera().position();
+
+    let (player_x, player_y, player_o, player_w) =
+        unsafe { (PLAYER_X, PLAYER_Y, PLAYER_O, PLAYER_W) };
+    let mut actors = vec![player_actor(player_x, player_y, player_o, player_w)];
+    actors.extend(townies().iter().map(Npc::actor));
+
+    unsafe { *DRAW_COLORS = 0x1234 }
+    gfx::draw_map(
+        map_data::VILLAGE_LAYERS,
+        0,
+        0,
+        map_x,
+        map_y,
+        SCREEN_SIZE,
+        SCREEN_SIZE,
+        &mut actors,
+    );
+}
+
+/// Middle of the player sprite in map pixels, for the camera to look at.
+fn player_center(player_x: i32, player_y: i32) -> (i32, i32) {
+    (
+        player_x + gfx_data::GUNGIRL.sprite_w as i32 / 2,
+        player_y + gfx_data::GUNGIRL.image_h as i32 / 2,
+    )
+}
+
+fn player_actor(
+    player_x: i32,
+    player_y: i32,
+    player_o: Orientation,
+    player_w: usize,
+) -> Actor<'static> {
+    Actor {
+        sprite: &gfx_data::GUNGIRL,
+        x: player_x,
+        y: player_y,
+        w: player_w,
+        o: player_o,
+    }
+}
+
+/// Player hitbox in map pixels, if the player sprite were at this position.
+fn hitbox(player_x: i32, player_y: i32) -> (i32, i32, u32, u32) {
+    player_actor(player_x, player_y, Orientation::default(), 0).hitbox()
+}
+
+/// Do these rectangles (x, y, width, height) overlap?
+fn overlaps(a: (i32, i32, u32, u32), b: (i32, i32, u32, u32)) -> bool {
+    let ((ax, ay, aw, ah), (bx, by, bw, bh)) = (a, b);
+    ax < bx + bw as i32 && bx < ax + aw as i32 && ay < by + bh as i32 && by < ay + ah as i32
+}
+
+/// Does this rectangle (x, y, width, height) touch any solid tiles?
+fn solid(rect: (i32, i32, u32, u32)) -> bool {
+    let (x, y, w, h) = rect;
+    map_data::VILLAGE_GROUND.solid(x, y, w, h) || map_data::VILLAGE_BUILDINGS.solid(x, y, w, h)
+}
+
+/// Would the player run into a solid tile or a townie at this position?
+fn blocked(player_x: i32, player_y: i32) -> bool {
+    let hitbox = hitbox(player_x, player_y);
+    solid(hitbox)
+        || townies()
+            .iter()
+            .any(|townie| overlaps(townie.actor().hitbox(), hitbox))
+}
+
+/// Talk to whoever's in reach, or else use whatever the player is standing on.
+fn interact(player_x: i32, player_y: i32) {
+    let (x, y, w, h) = hitbox(player_x, player_y);
+    let reach = (
+        x - TALK_REACH,
+        y - TALK_REACH,
+        w + 2 * TALK_REACH as u32,
+        h + 2 * TALK_REACH as u32,
+    );
+    if let Some(townie) = townies()
+        .iter_mut()
+        .find(|townie| overlaps(townie.actor().hitbox(), reach))
+    {
+        let (center_x, center_y) = player_center(player_x, player_y);
+        townie.talk(center_x, center_y);
+        if !townie.cinematic.is_empty() {
+            cinematic::play(townie.cinematic);
+        }
+        return;
+    }
+
+    let Some(region) = map_data::VILLAGE_ZONES
+        .iter()
+        .find(|region| region.overlaps(x, y, w, h))
+    else {
+        return;
+    };
+    // TODO: other buildings and fights don't have scenes yet.
+    if region.name == "atelier_door" {
+        scene::push(Scene::Alchemy);
+    }
 }
@@ -0,0 +1,146 @@
+//! Syntax tree for `.aecinematic` scripts.
+//!
+//! Scripts are read a line at a time. Blank lines and lines starting with `#` are skipped.
+//! Every other line is one statement, or starts or ends a block of them:
+//!
+//! - `!set background <name>` and `!set material <name>` show a background or a material.
+//!   Materials can be given by their ID or name in `items.json`.
+//! - `!set left …` and `!set right …` bring a character on stage in that slot:
+//!   the speaker line and mood line after it say who and how they look.
+//! - `!unset background`, `material`, `speaker`, `text`, `left`, or `right` takes that away.
+//! - `NAME:` makes the character with that ID in `cinematics/characters.json` the speaker.
+//! - `[mood]` changes the speaker's mood.
+//! - Anything else is a line of text, and gets a page of its own.
+//!
+//! Story flags, declared in `cinematics/story_flags.txt`, change what happens next:
+//!
+//! - `!flag <name> = <value>` sets a flag to `true`, `false`, or a number.
+//!   `!flag <name> += <number>` and `-= <number>` add to or take away from an int flag.
+//! - `!if <name>`, `!if not <name>`, or `!if <name> <op> <number>`, with `op` one of
+//!   `==`, `!=`, `<`, `<=`, `>`, or `>=`, starts a block that only plays if that's so.
+//!   `!else` starts a block that plays if it isn't, and `!end` ends them.
+//! - `!choice` starts a block of 2 to 4 options, each one `!option <text>`
+//!   followed by what plays if it's picked, and `!end` ends it.
+//! - `!label <name>` marks a place in the script, and `!jump <name>` goes there.
+//!
+//! Cinematics played from a map show it behind the stage, and can move the view of it:
+//!
+//! - `!pan <x> <y> <frames>` moves the view over that many frames until it's centered on
+//!   that point of the map, in pixels. Text can't be skipped past until the pan is done.
+//! - `!shake <pixels> <frames>` shakes the view, dying down over that many frames.
+//!
+//! The Mac edition doesn't show the map behind cinematics, so these do nothing there.
+
+use crate::cinematic_script::{CinematicCharacterSlot, CinematicComparison};
+
+/// Byte range in a script's source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl Span {
+    pub(crate) fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Spanned<T> {
+    pub(crate) node: T,
+    pub(crate) span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub(crate) fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Script {
+    pub(crate) statements: Vec<Spanned<Statement>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Statement {
+    SetBackground(Spanned<String>),
+    SetMaterial(Spanned<String>),
+    /// `!set left …` or `!set right …`.
+    Enter(Spanned<CinematicCharacterSlot>),
+    Unset(Spanned<UnsetTarget>),
+    Speaker(Spanned<String>),
+    Mood(Spanned<String>),
+    Text(String),
+    Label(Spanned<String>),
+    Jump(Spanned<String>),
+    Pan {
+        x: i16,
+        y: i16,
+        frames: u16,
+    },
+    Shake {
+        magnitude: u16,
+        frames: u16,
+    },
+    SetFlag {
+        flag: Spanned<String>,
+        operator: FlagOperator,
+        value: Spanned<FlagValue>,
+    },
+    /// Span is the `!if` line.
+    If {
+        condition: Condition,
+        then: Vec<Spanned<Statement>>,
+        otherwise: Vec<Spanned<Statement>>,
+    },
+    /// Span is the `!choice` line.
+    Choice(Vec<ChoiceOption>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnsetTarget {
+    Background,
+    Material,
+    Speaker,
+    Text,
+    Character(CinematicCharacterSlot),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FlagOperator {
+    /// `=`
+    Set,
+    /// `+=`
+    Add,
+    /// `-=`
+    Subtract,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FlagValue {
+    Bool(bool),
+    Int(i16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Condition {
+    pub(crate) flag: Spanned<String>,
+    pub(crate) test: ConditionTest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ConditionTest {
+    /// `!if <name>`: true, or not 0.
+    True,
+    /// `!if not <name>`: false, or 0.
+    False,
+    Compare(CinematicComparison, Spanned<i16>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChoiceOption {
+    pub(crate) text: Spanned<String>,
+    pub(crate) body: Vec<Spanned<Statement>>,
+}
@@ -0,0 +1,745 @@
+//! Check a script's syntax tree for mistakes that parsing can't catch,
+//! like talking to someone who isn't on stage, and turn it into cinematic commands.
+//!
+//! Stage checks read the script from top to bottom, so they don't follow jumps.
+//! Where the branches of an `!if` or `!choice` meet again, only what they agree on
+//! is known to be on stage.
+
+use crate::cinematic_script::ast::{
+    Condition, ConditionTest, FlagOperator, FlagValue, Script, Span, Spanned, Statement,
+    UnsetTarget,
+};
+use crate::cinematic_script::diagnostic::{Diagnostic, Diagnostics};
+use crate::cinematic_script::flags::STORY_FLAGS_PATH;
+use crate::cinematic_script::parse::parse;
+use crate::cinematic_script::roster::{CHARACTERS_PATH, ITEMS_PATH};
+use crate::cinematic_script::{
+    CinematicCharacter, CinematicCharacterSlot, CinematicChoice, CinematicCommand,
+    CinematicComparison, CinematicCondition, CinematicTables, StoryFlagKind,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Read a script into cinematic commands.
+/// Moods and backgrounds are looked up by name, so each edition can number them its own way.
+/// If the script has mistakes, the error is a [`Diagnostics`] listing all of them.
+pub(crate) fn compile_script(
+    input: &Path,
+    tables: &CinematicTables,
+    lookup_mood: impl FnMut(usize, &str) -> anyhow::Result<usize>,
+    lookup_background: impl FnMut(&str) -> anyhow::Result<usize>,
+) -> anyhow::Result<Vec<CinematicCommand>> {
+    let source = fs::read_to_string(input)?;
+    let (script, mut diagnostics) = parse(&source);
+    let (script, compile_diagnostics) = compile(&script, tables, lookup_mood, lookup_background);
+    diagnostics.extend(compile_diagnostics);
+    if diagnostics.is_empty() {
+        return Ok(script);
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    Err(Diagnostics {
+        path: input.to_path_buf(),
+        source,
+        diagnostics,
+    }
+    .into())
+}
+
+fn compile(
+    script: &Script,
+    tables: &CinematicTables,
+    lookup_mood: impl FnMut(usize, &str) -> anyhow::Result<usize>,
+    lookup_background: impl FnMut(&str) -> anyhow::Result<usize>,
+) -> (Vec<CinematicCommand>, Vec<Diagnostic>) {
+    let mut compiler = Compiler {
+        tables,
+        lookup_mood,
+        lookup_background,
+        set_character_slot: None,
+        set_character_id: None,
+        stage: Stage::default(),
+        labels: BTreeMap::new(),
+        jumps: Vec::new(),
+        commands: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    compiler.block(&script.statements);
+    compiler.resolve_jumps();
+
+    let Compiler {
+        mut commands,
+        diagnostics,
+        ..
+    } = compiler;
+    if commands.last() != Some(&CinematicCommand::CinematicCommandCommit) {
+        commands.push(CinematicCommand::CinematicCommandCommit);
+    }
+
+    (commands, diagnostics)
+}
+
+/// Who's on stage and who's speaking, as far as the compiler can tell.
+#[derive(Debug, Clone, Default)]
+struct Stage {
+    characters: BTreeMap<CinematicCharacterSlot, CinematicCharacter>,
+    speaker: Option<CinematicCharacterSlot>,
+}
+
+impl Stage {
+    /// Keep only what's also on the other stage. Moods can differ.
+    fn merge(&mut self, other: &Stage) {
+        self.characters.retain(|slot, character| {
+            other
+                .characters
+                .get(slot)
+                .is_some_and(|other_character| other_character.id == character.id)
+        });
+        if self.speaker != other.speaker {
+            self.speaker = None;
+        }
+    }
+}
+
+struct Compiler<'a, M, B> {
+    tables: &'a CinematicTables,
+    lookup_mood: M,
+    lookup_background: B,
+    /// Slot from the last `!set left …` or `!set right …`,
+    /// waiting for a speaker line and a mood line to say who goes there.
+    set_character_slot: Option<&'a Spanned<CinematicCharacterSlot>>,
+    set_character_id: Option<usize>,
+    stage: Stage,
+    /// Command index and definition for every label so far.
+    labels: BTreeMap<&'a str, (usize, Span)>,
+    /// Jump commands to point at their labels once all the labels are known.
+    jumps: Vec<(usize, &'a Spanned<String>)>,
+    commands: Vec<CinematicCommand>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a, M, B> Compiler<'a, M, B>
+where
+    M: FnMut(usize, &str) -> anyhow::Result<usize>,
+    B: FnMut(&str) -> anyhow::Result<usize>,
+{
+    fn block(&mut self, statements: &'a [Spanned<Statement>]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.finish_entering();
+    }
+
+    fn statement(&mut self, statement: &'a Spanned<Statement>) {
+        match &statement.node {
+            Statement::SetBackground(name) => match (self.lookup_background)(&name.node) {
+                Ok(id) => self
+                    .commands
+                    .push(CinematicCommand::CinematicCommandSetBackground { id }),
+                Err(e) => self.diagnostics.push(
+                    Diagnostic::error(e.to_string(), name.span).with_label("unknown background"),
+                ),
+            },
+            Statement::SetMaterial(name) => match self.tables.materials.get(&name.node) {
+                Some(id) => self
+                    .commands
+                    .push(CinematicCommand::CinematicCommandSetMaterial { id }),
+                None => self.diagnostics.push(
+                    Diagnostic::error(format!("Couldn't find material: {}", name.node), name.span)
+                        .with_label("unknown material")
+                        .with_help(format!(
+                            "materials are listed by ID and name in {ITEMS_PATH}"
+                        )),
+                ),
+            },
+            Statement::Enter(slot) => {
+                self.finish_entering();
+                self.set_character_slot = Some(slot);
+                self.set_character_id = None;
+            }
+            Statement::Unset(target) => match target.node {
+                UnsetTarget::Background => self
+                    .commands
+                    .push(CinematicCommand::CinematicCommandClearBackground),
+                UnsetTarget::Material => self
+                    .commands
+                    .push(CinematicCommand::CinematicCommandClearMaterial),
+                UnsetTarget::Speaker => {
+                    self.commands
+                        .push(CinematicCommand::CinematicCommandClearSpeaker);
+                    self.stage.speaker = None;
+                }
+                UnsetTarget::Text => self
+                    .commands
+                    .push(CinematicCommand::CinematicCommandClearText),
+                UnsetTarget::Character(slot) => {
+                    if self.stage.speaker == Some(slot) {
+                        self.commands
+                            .push(CinematicCommand::CinematicCommandClearSpeaker);
+                        self.stage.speaker = None;
+                    }
+                    self.commands
+                        .push(CinematicCommand::CinematicCommandClearCharacter { slot });
+                    self.stage.characters.remove(&slot);
+                }
+            },
+            Statement::Speaker(name) => self.speaker(name),
+            Statement::Mood(name) => self.mood(name),
+            Statement::Text(text) => {
+                self.commands
+                    .push(CinematicCommand::CinematicCommandSetText { text: text.clone() });
+                self.commands.push(CinematicCommand::CinematicCommandCommit);
+            }
+            Statement::Label(name) => {
+                if self.labels.contains_key(name.node.as_str()) {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            format!("Label {} is defined more than once", name.node),
+                            name.span,
+                        )
+                        .with_label("already defined earlier in the script"),
+                    );
+                    return;
+                }
+                self.labels
+                    .insert(&name.node, (self.commands.len(), name.span));
+            }
+            Statement::Jump(name) => {
+                self.jumps.push((self.commands.len(), name));
+                self.commands
+                    .push(CinematicCommand::CinematicCommandJump { target: 0 });
+            }
+            Statement::Pan { x, y, frames } => {
+                self.commands
+                    .push(CinematicCommand::CinematicCommandPanCamera {
+                        x: *x,
+                        y: *y,
+                        frames: *frames,
+                    })
+            }
+            Statement::Shake { magnitude, frames } => {
+                self.commands
+                    .push(CinematicCommand::CinematicCommandShakeCamera {
+                        magnitude: *magnitude,
+                        frames: *frames,
+                    })
+            }
+            Statement::SetFlag {
+                flag,
+                operator,
+                value,
+            } => self.set_flag(flag, *operator, value),
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.finish_entering();
+                // If the condition is wrong there's nothing to run anyway,
+                // but keep checking the blocks.
+                let condition = self.condition(condition).unwrap_or(CinematicCondition {
+                    flag: 0,
+                    comparison: CinematicComparison::Ne,
+                    value: 0,
+                });
+                let jump_unless = self.commands.len();
+                self.commands
+                    .push(CinematicCommand::CinematicCommandJumpUnless {
+                        condition,
+                        target: 0,
+                    });
+                let before = self.stage.clone();
+                self.block(then);
+                if otherwise.is_empty() {
+                    self.set_target(jump_unless, self.commands.len());
+                    self.stage.merge(&before);
+                } else {
+                    let jump = self.commands.len();
+                    self.commands
+                        .push(CinematicCommand::CinematicCommandJump { target: 0 });
+                    self.set_target(jump_unless, self.commands.len());
+                    let after_then = std::mem::replace(&mut self.stage, before);
+                    self.block(otherwise);
+                    self.set_target(jump, self.commands.len());
+                    self.stage.merge(&after_then);
+                }
+            }
+            Statement::Choice(options) => {
+                self.finish_entering();
+                let choice = self.commands.len();
+                self.commands
+                    .push(CinematicCommand::CinematicCommandChoice {
+                        options: options
+                            .iter()
+                            .map(|option| CinematicChoice {
+                                text: option.text.node.clone(),
+                                target: 0,
+                            })
+                            .collect(),
+                    });
+                let before = self.stage.clone();
+                let mut after: Option<Stage> = None;
+                let mut jumps_to_end = Vec::new();
+                for (i, option) in options.iter().enumerate() {
+                    let target = self.commands.len();
+                    if let CinematicCommand::CinematicCommandChoice { options } =
+                        &mut self.commands[choice]
+                    {
+                        options[i].target = target;
+                    }
+                    self.stage = before.clone();
+                    self.block(&option.body);
+                    match &mut after {
+                        Some(after) => after.merge(&self.stage),
+                        None => after = Some(self.stage.clone()),
+                    }
+                    if i + 1 < options.len() {
+                        jumps_to_end.push(self.commands.len());
+                        self.commands
+                            .push(CinematicCommand::CinematicCommandJump { target: 0 });
+                    }
+                }
+                let end = self.commands.len();
+                for jump in jumps_to_end {
+                    self.set_target(jump, end);
+                }
+                self.stage = after.unwrap_or(before);
+            }
+        }
+    }
+
+    fn speaker(&mut self, name: &Spanned<String>) {
+        let Some((id, _)) = self.tables.characters.get(&name.node) else {
+            self.diagnostics.push(
+                Diagnostic::error(format!("Couldn't find character: {}", name.node), name.span)
+                    .with_label("unknown character")
+                    .with_help(format!(
+                        "known characters are {known}, from {CHARACTERS_PATH}",
+                        known = self
+                            .tables
+                            .characters
+                            .iter()
+                            .map(|character| character.id.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+            );
+            return;
+        };
+        if self.set_character_slot.is_some() {
+            self.set_character_id = Some(id);
+            return;
+        }
+        let Some((slot, _)) = self
+            .stage
+            .characters
+            .iter()
+            .find(|(_, character)| character.id == id)
+        else {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!(
+                        "Character isn't on stage, can't set them as speaker: {}",
+                        name.node
+                    ),
+                    name.span,
+                )
+                .with_label("not on stage")
+                .with_help("bring them on first with `!set left …` or `!set right …`"),
+            );
+            return;
+        };
+        let slot = *slot;
+        self.commands
+            .push(CinematicCommand::CinematicCommandSetSpeaker { slot });
+        self.stage.speaker = Some(slot);
+    }
+
+    fn mood(&mut self, name: &Spanned<String>) {
+        if let Some(slot) = self.set_character_slot {
+            let slot = slot.node;
+            let Some(id) = self.set_character_id else {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("Can't set mood for unknown character: {}", name.node),
+                        name.span,
+                    )
+                    .with_label("nobody named since `!set`")
+                    .with_help("name the character before their mood, like `ESRI:`"),
+                );
+                return;
+            };
+            // Put them on stage even if the mood is wrong,
+            // so that their lines don't get reported too.
+            let mood = (self.lookup_mood)(id, &name.node).unwrap_or_else(|e| {
+                self.diagnostics
+                    .push(Diagnostic::error(e.to_string(), name.span).with_label("unknown mood"));
+                0
+            });
+            let character = CinematicCharacter { id, mood };
+            self.commands
+                .push(CinematicCommand::CinematicCommandSetCharacter { slot, character });
+            self.commands
+                .push(CinematicCommand::CinematicCommandSetSpeaker { slot });
+            self.stage.characters.insert(slot, character);
+            self.set_character_slot = None;
+            self.set_character_id = None;
+            self.stage.speaker = Some(slot);
+            return;
+        }
+
+        let Some(slot) = self.stage.speaker else {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "Can't set mood for speaker if nobody is speaking",
+                    name.span,
+                )
+                .with_label("no speaker"),
+            );
+            return;
+        };
+        let Some(character) = self.stage.characters.get_mut(&slot) else {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("Speaker is slot {slot:?} but nobody is on stage there"),
+                    name.span,
+                )
+                .with_label("no speaker"),
+            );
+            return;
+        };
+        match (self.lookup_mood)(character.id, &name.node) {
+            Ok(mood) => {
+                self.commands
+                    .push(CinematicCommand::CinematicCommandSetMood { slot, mood });
+                character.mood = mood;
+            }
+            Err(e) => self
+                .diagnostics
+                .push(Diagnostic::error(e.to_string(), name.span).with_label("unknown mood")),
+        }
+    }
+
+    /// ID and kind of a declared story flag.
+    fn flag(&mut self, name: &Spanned<String>) -> Option<(usize, StoryFlagKind)> {
+        let Some((id, flag)) = self.tables.flags.get(&name.node) else {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("Couldn't find story flag: {}", name.node),
+                    name.span,
+                )
+                .with_label("unknown flag")
+                .with_help(format!(
+                    "declare it in `{STORY_FLAGS_PATH}`, like `bool {name}` or `int {name}`",
+                    name = name.node
+                )),
+            );
+            return None;
+        };
+        Some((id, flag.kind))
+    }
+
+    fn set_flag(
+        &mut self,
+        name: &Spanned<String>,
+        operator: FlagOperator,
+        value: &Spanned<FlagValue>,
+    ) {
+        let Some((flag, kind)) = self.flag(name) else {
+            return;
+        };
+        let command = match (kind, operator, value.node) {
+            (StoryFlagKind::Bool, FlagOperator::Set, FlagValue::Bool(b)) => {
+                CinematicCommand::CinematicCommandSetFlag {
+                    flag,
+                    value: b as i16,
+                }
+            }
+            (StoryFlagKind::Int, FlagOperator::Set, FlagValue::Int(value)) => {
+                CinematicCommand::CinematicCommandSetFlag { flag, value }
+            }
+            (StoryFlagKind::Int, FlagOperator::Add, FlagValue::Int(amount)) => {
+                CinematicCommand::CinematicCommandAddFlag { flag, amount }
+            }
+            (StoryFlagKind::Int, FlagOperator::Subtract, FlagValue::Int(amount)) => {
+                let Some(amount) = amount.checked_neg() else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("Can't subtract {amount}"), value.span)
+                            .with_label("too big to take away"),
+                    );
+                    return;
+                };
+                CinematicCommand::CinematicCommandAddFlag { flag, amount }
+            }
+            (StoryFlagKind::Bool, _, _) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("{} is a bool flag", name.node), value.span)
+                        .with_label("expected `true` or `false`")
+                        .with_help(format!(
+                            "set bool flags with `!flag {name} = true` or `!flag {name} = false`",
+                            name = name.node
+                        )),
+                );
+                return;
+            }
+            (StoryFlagKind::Int, _, _) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("{} is an int flag", name.node), value.span)
+                        .with_label("expected a number"),
+                );
+                return;
+            }
+        };
+        self.commands.push(command);
+    }
+
+    fn condition(&mut self, condition: &Condition) -> Option<CinematicCondition> {
+        let (flag, kind) = self.flag(&condition.flag)?;
+        let (comparison, value) = match &condition.test {
+            ConditionTest::True => (CinematicComparison::Ne, 0),
+            ConditionTest::False => (CinematicComparison::Eq, 0),
+            ConditionTest::Compare(comparison, value) => {
+                if kind == StoryFlagKind::Bool {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            format!(
+                                "{} is a bool flag and can't be compared to a number",
+                                condition.flag.node
+                            ),
+                            value.span,
+                        )
+                        .with_label("only int flags have numbers")
+                        .with_help(format!(
+                            "test bool flags with `!if {name}` or `!if not {name}`",
+                            name = condition.flag.node
+                        )),
+                    );
+                    return None;
+                }
+                (*comparison, value.node)
+            }
+        };
+        Some(CinematicCondition {
+            flag,
+            comparison,
+            value,
+        })
+    }
+
+    /// Report a `!set left …` or `!set right …` that nobody came on stage for.
+    fn finish_entering(&mut self) {
+        if let Some(slot) = self.set_character_slot.take() {
+            self.diagnostics.push(never_entered(slot));
+        }
+        self.set_character_id = None;
+    }
+
+    fn set_target(&mut self, index: usize, new_target: usize) {
+        match &mut self.commands[index] {
+            CinematicCommand::CinematicCommandJump { target }
+            | CinematicCommand::CinematicCommandJumpUnless { target, .. } => *target = new_target,
+            command => unreachable!("Not a jump: {command:?}"),
+        }
+    }
+
+    fn resolve_jumps(&mut self) {
+        for (index, name) in std::mem::take(&mut self.jumps) {
+            match self.labels.get(name.node.as_str()) {
+                Some((target, _)) => self.set_target(index, *target),
+                None => self.diagnostics.push(
+                    Diagnostic::error(format!("Couldn't find label: {}", name.node), name.span)
+                        .with_label("unknown label")
+                        .with_help(format!("mark where to jump to with `!label {}`", name.node)),
+                ),
+            }
+        }
+    }
+}
+
+fn never_entered(slot: &Spanned<CinematicCharacterSlot>) -> Diagnostic {
+    Diagnostic::error(
+        format!("Nobody came on stage at {:?}", slot.node),
+        slot.span,
+    )
+    .with_label("expected a speaker line and a mood line after this")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cinematic_script::{Characters, StoryFlags};
+
+    fn lookup_mood(id: usize, name: &str) -> anyhow::Result<usize> {
+        match (id, name) {
+            (0, "pleased") => Ok(0),
+            (2, "catface") => Ok(1),
+            _ => anyhow::bail!("Couldn't find mood for character {id}: {name}"),
+        }
+    }
+
+    fn lookup_background(name: &str) -> anyhow::Result<usize> {
+        match name {
+            "atelier_interior" => Ok(128),
+            _ => anyhow::bail!("Couldn't find background: {name}"),
+        }
+    }
+
+    fn compile_source(source: &str) -> (Vec<CinematicCommand>, Vec<Diagnostic>) {
+        let (script, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+        let tables = CinematicTables {
+            flags: StoryFlags::parse("bool lied_to_parents\nint times_lied\n").unwrap(),
+            characters: Characters::parse(
+                r#"[
+                    {"id": "ESRI", "name": "Esri", "portrait": "Esri"},
+                    {"id": "ALLIE", "name": "Allie", "portrait": "Allie"},
+                    {"id": "SAE", "name": "Sae", "portrait": "Sae"}
+                ]"#,
+            )
+            .unwrap(),
+            materials: [("ore_copper", "Crimson Ore"), ("water", "Drinking Water")]
+                .into_iter()
+                .collect(),
+        };
+        compile(&script, &tables, lookup_mood, lookup_background)
+    }
+
+    #[test]
+    fn test_compile() {
+        let (commands, diagnostics) = compile_source(
+            "!set background atelier_interior\n!set left …\nESRI:\n[pleased]\nHi.\n!unset left\n",
+        );
+        assert!(diagnostics.is_empty());
+        let slot = CinematicCharacterSlot::Left;
+        assert_eq!(
+            commands,
+            vec![
+                CinematicCommand::CinematicCommandSetBackground { id: 128 },
+                CinematicCommand::CinematicCommandSetCharacter {
+                    slot,
+                    character: CinematicCharacter { id: 0, mood: 0 },
+                },
+                CinematicCommand::CinematicCommandSetSpeaker { slot },
+                CinematicCommand::CinematicCommandSetText {
+                    text: "Hi.".to_string()
+                },
+                CinematicCommand::CinematicCommandCommit,
+                CinematicCommand::CinematicCommandClearSpeaker,
+                CinematicCommand::CinematicCommandClearCharacter { slot },
+                CinematicCommand::CinematicCommandCommit,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_materials() {
+        let (commands, diagnostics) =
+            compile_source("!set material water\n!set material Crimson Ore\n");
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            commands,
+            vec![
+                CinematicCommand::CinematicCommandSetMaterial { id: 1 },
+                CinematicCommand::CinematicCommandSetMaterial { id: 0 },
+                CinematicCommand::CinematicCommandCommit,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_reports_every_mistake() {
+        let (_, diagnostics) = compile_source(
+            "!set background nowhere\n!set left …\nESRI:\n[grumpy]\nSAE:\n!set material Mud\n!set right …\n",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Couldn't find background: nowhere",
+                "Couldn't find mood for character 0: grumpy",
+                "Character isn't on stage, can't set them as speaker: SAE",
+                "Couldn't find material: Mud",
+                "Nobody came on stage at Right",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_branches() {
+        let (commands, diagnostics) = compile_source(
+            "!if times_lied >= 2\nUh oh.\n!else\n!jump end\n!end\n!choice\n!option Lie\n!flag lied_to_parents = true\n!flag times_lied += 1\n!option Don't\n!flag times_lied -= 1\n!end\n!label end\n",
+        );
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            commands,
+            vec![
+                CinematicCommand::CinematicCommandJumpUnless {
+                    condition: CinematicCondition {
+                        flag: 1,
+                        comparison: CinematicComparison::Ge,
+                        value: 2,
+                    },
+                    target: 4,
+                },
+                CinematicCommand::CinematicCommandSetText {
+                    text: "Uh oh.".to_string()
+                },
+                CinematicCommand::CinematicCommandCommit,
+                CinematicCommand::CinematicCommandJump { target: 5 },
+                CinematicCommand::CinematicCommandJump { target: 10 },
+                CinematicCommand::CinematicCommandChoice {
+                    options: vec![
+                        CinematicChoice {
+                            text: "Lie".to_string(),
+                            target: 6,
+                        },
+                        CinematicChoice {
+                            text: "Don't".to_string(),
+                            target: 9,
+                        },
+                    ],
+                },
+                CinematicCommand::CinematicCommandSetFlag { flag: 0, value: 1 },
+                CinematicCommand::CinematicCommandAddFlag { flag: 1, amount: 1 },
+                CinematicCommand::CinematicCommandJump { target: 10 },
+                CinematicCommand::CinematicCommandAddFlag {
+                    flag: 1,
+                    amount: -1
+                },
+                CinematicCommand::CinematicCommandCommit,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_reports_flag_and_label_mistakes() {
+        let (_, diagnostics) = compile_source(
+            "!flag lied = true\n!flag lied_to_parents += 1\n!flag times_lied = false\n!if lied_to_parents > 1\n!end\n!jump nowhere\n!label here\n!label here\n",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Couldn't find story flag: lied",
+                "lied_to_parents is a bool flag",
+                "times_lied is an int flag",
+                "lied_to_parents is a bool flag and can't be compared to a number",
+                "Label here is defined more than once",
+                "Couldn't find label: nowhere",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_merges_branches() {
+        // Esri is on stage after the `!if` either way, but only one branch says who's speaking.
+        let (_, diagnostics) = compile_source(
+            "!set left …\nESRI:\n[pleased]\nHi.\n!if lied_to_parents\n!unset speaker\nHm.\n!end\nESRI:\n[pleased]\n!if lied_to_parents\n!unset speaker\n!end\n[pleased]\n",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["Can't set mood for speaker if nobody is speaking"]
+        );
+    }
+}
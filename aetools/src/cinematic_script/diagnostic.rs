@@ -0,0 +1,166 @@
+//! Script errors, reported like `rustc` does: message, file, line, and column,
+//! then the offending line with the problem underlined.
+
+use crate::cinematic_script::ast::Span;
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Diagnostic {
+    pub(crate) message: String,
+    pub(crate) span: Span,
+    /// Short note shown next to the underline.
+    pub(crate) label: Option<String>,
+    /// Suggestion for fixing it, shown after the snippet.
+    pub(crate) help: Option<String>,
+}
+
+impl Diagnostic {
+    pub(crate) fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            label: None,
+            help: None,
+        }
+    }
+
+    pub(crate) fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub(crate) fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+/// Every error found in one script, with the source needed to show where they are.
+/// Displays as the whole report.
+#[derive(Debug)]
+pub(crate) struct Diagnostics {
+    pub(crate) path: PathBuf,
+    pub(crate) source: String,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// 1-based line and column of a byte offset. Columns count characters, not bytes.
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let before = &self.source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+
+    /// Byte range of the line containing a byte offset, without its line ending.
+    fn line_range(&self, offset: usize) -> (usize, usize) {
+        let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[offset..]
+            .find('\n')
+            .map_or(self.source.len(), |i| offset + i);
+        let text = self.source[line_start..line_end].trim_end_matches('\r');
+        (line_start, line_start + text.len())
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, diagnostic: &Diagnostic) -> fmt::Result {
+        let (line, col) = self.line_col(diagnostic.span.start);
+        let (line_start, line_end) = self.line_range(diagnostic.span.start);
+        let text = &self.source[line_start..line_end];
+        // Underline at least one column, even for empty spans, but never past the end of the line.
+        let span_end = diagnostic.span.end.min(line_end).max(diagnostic.span.start);
+        let underline = self.source[diagnostic.span.start..span_end]
+            .chars()
+            .count()
+            .max(1);
+        let gutter = " ".repeat(line.to_string().len());
+
+        writeln!(f, "error: {message}", message = diagnostic.message)?;
+        writeln!(
+            f,
+            "{gutter}--> {path}:{line}:{col}",
+            path = self.path.display()
+        )?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{line} | {text}")?;
+        write!(
+            f,
+            "{gutter} | {indent}{carets}",
+            indent = " ".repeat(col - 1),
+            carets = "^".repeat(underline)
+        )?;
+        if let Some(label) = &diagnostic.label {
+            write!(f, " {label}")?;
+        }
+        writeln!(f)?;
+        if let Some(help) = &diagnostic.help {
+            writeln!(f, "{gutter} |")?;
+            writeln!(f, "{gutter} = help: {help}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            self.render(f, diagnostic)?;
+            writeln!(f)?;
+        }
+        let count = self.diagnostics.len();
+        write!(
+            f,
+            "error: could not compile `{path}` due to {count} previous error{s}",
+            path = self.path.display(),
+            s = if count == 1 { "" } else { "s" }
+        )
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let diagnostics = Diagnostics {
+            path: PathBuf::from("cinematics/test.aecinematic"),
+            source: "!set left …\n\nBOB:\n".to_string(),
+            diagnostics: vec![
+                Diagnostic::error("Couldn't find character: BOB", Span::new(15, 18))
+                    .with_label("unknown character")
+                    .with_help("known characters are ALLIE, ESRI, SAE"),
+            ],
+        };
+        assert_eq!(
+            diagnostics.to_string(),
+            "\
+error: Couldn't find character: BOB
+ --> cinematics/test.aecinematic:3:1
+  |
+3 | BOB:
+  | ^^^ unknown character
+  |
+  = help: known characters are ALLIE, ESRI, SAE
+
+error: could not compile `cinematics/test.aecinematic` due to 1 previous error"
+        );
+    }
+
+    #[test]
+    fn test_render_columns_count_chars() {
+        let diagnostics = Diagnostics {
+            path: PathBuf::from("test.aecinematic"),
+            source: "!set left … x\n".to_string(),
+            diagnostics: vec![Diagnostic::error("Unexpected text", Span::new(14, 15))],
+        };
+        let rendered = diagnostics.to_string();
+        assert!(rendered.contains("--> test.aecinematic:1:13\n"));
+        assert!(rendered.contains("  |             ^\n"));
+    }
+}
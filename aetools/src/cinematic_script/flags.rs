@@ -0,0 +1,130 @@
+//! Story flags that cinematics can set and test, declared in `cinematics/story_flags.txt`.
+//!
+//! Each line of that file declares one flag, as `bool <name>` or `int <name>`.
+//! Blank lines and lines starting with `#` are skipped.
+//! A flag's ID is its position in the file, and saved games store flags by ID,
+//! so new flags go at the end and old ones never move.
+
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Where the flag table lives, relative to the assets directory.
+pub(crate) const STORY_FLAGS_PATH: &str = "cinematics/story_flags.txt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StoryFlagKind {
+    Bool,
+    /// 16-bit signed integer.
+    Int,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct StoryFlag {
+    pub(crate) name: String,
+    pub(crate) kind: StoryFlagKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(transparent)]
+pub(crate) struct StoryFlags {
+    flags: Vec<StoryFlag>,
+}
+
+impl StoryFlags {
+    /// Read the flag table from an assets directory. A missing table means no flags.
+    pub(crate) fn load(asset_base_dir: &Path) -> anyhow::Result<Self> {
+        let path = asset_base_dir.join(STORY_FLAGS_PATH);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::anyhow!("{path}: {e}", path = path.display()))
+    }
+
+    pub(crate) fn parse(source: &str) -> anyhow::Result<Self> {
+        let mut flags = Self::default();
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let [kind, name] = words[..] else {
+                anyhow::bail!(
+                    "line {n}: expected `bool <name>` or `int <name>`, got: {line}",
+                    n = i + 1
+                );
+            };
+            let kind = match kind {
+                "bool" => StoryFlagKind::Bool,
+                "int" => StoryFlagKind::Int,
+                _ => anyhow::bail!(
+                    "line {n}: unknown flag type {kind}, expected `bool` or `int`",
+                    n = i + 1
+                ),
+            };
+            if flags.get(name).is_some() {
+                anyhow::bail!("line {n}: flag {name} is declared twice", n = i + 1);
+            }
+            flags.flags.push(StoryFlag {
+                name: name.to_string(),
+                kind,
+            });
+        }
+        Ok(flags)
+    }
+
+    /// ID and declaration of a flag by name.
+    pub(crate) fn get(&self, name: &str) -> Option<(usize, &StoryFlag)> {
+        self.flags
+            .iter()
+            .enumerate()
+            .find(|(_, flag)| flag.name == name)
+    }
+
+    pub(crate) fn kind(&self, id: usize) -> StoryFlagKind {
+        self.flags[id].kind
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &StoryFlag> {
+        self.flags.iter()
+    }
+
+    /// Position of a flag among the flags of the same kind,
+    /// for runtimes that keep bools and ints apart.
+    pub(crate) fn index_within_kind(&self, id: usize) -> usize {
+        let kind = self.flags[id].kind;
+        self.flags[..id]
+            .iter()
+            .filter(|flag| flag.kind == kind)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let flags =
+            StoryFlags::parse("# comment\nbool lied_to_parents\n\nint times_lied\nbool met_sae\n")
+                .unwrap();
+        assert_eq!(
+            flags.get("met_sae"),
+            Some((
+                2,
+                &StoryFlag {
+                    name: "met_sae".to_string(),
+                    kind: StoryFlagKind::Bool
+                }
+            ))
+        );
+        assert_eq!(flags.index_within_kind(1), 0);
+        assert_eq!(flags.index_within_kind(2), 1);
+        assert!(StoryFlags::parse("bool a\nint a\n").is_err());
+        assert!(StoryFlags::parse("float a\n").is_err());
+    }
+}
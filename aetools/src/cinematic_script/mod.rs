@@ -0,0 +1,155 @@
+//! `.aecinematic` cinematic scripts: parsing, checking, and compiling them to `CinematicCommand`s,
+//! which the Mac and WASM-4 editions each write out in their own way, and which can be dumped as JSON.
+//! See [`ast`] for the script syntax.
+
+mod ast;
+mod compile;
+mod diagnostic;
+mod flags;
+mod parse;
+mod roster;
+
+pub(crate) use compile::compile_script;
+pub(crate) use diagnostic::Diagnostics;
+pub(crate) use flags::{StoryFlagKind, StoryFlags};
+pub(crate) use roster::{Characters, Materials};
+
+use serde::Serialize;
+use std::path::Path;
+
+/// Everything besides moods and backgrounds that scripts refer to by name.
+/// Moods and backgrounds are looked up by each edition, since they come from its own assets.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CinematicTables {
+    pub(crate) flags: StoryFlags,
+    pub(crate) characters: Characters,
+    pub(crate) materials: Materials,
+}
+
+impl CinematicTables {
+    /// Read the flag table, character roster, and materials from an assets directory.
+    pub(crate) fn load(asset_base_dir: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            flags: StoryFlags::load(asset_base_dir)?,
+            characters: Characters::load(asset_base_dir)?,
+            materials: Materials::load(asset_base_dir)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) struct CinematicCharacter {
+    pub(crate) id: usize,
+    pub(crate) mood: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub(crate) enum CinematicCharacterSlot {
+    Left = 0,
+    Right = 1,
+}
+
+impl TryFrom<&str> for CinematicCharacterSlot {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "left" => Self::Left,
+            "right" => Self::Right,
+            _ => anyhow::bail!("Invalid character slot: {value}"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) enum CinematicComparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Compares a story flag to a value. Bool flags are 0 for false and 1 for true.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) struct CinematicCondition {
+    /// ID from [`StoryFlags`].
+    pub(crate) flag: usize,
+    pub(crate) comparison: CinematicComparison,
+    pub(crate) value: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct CinematicChoice {
+    pub(crate) text: String,
+    /// Index of the command to go on from if this is picked.
+    pub(crate) target: usize,
+}
+
+/// Jump targets are indexes into the script's command list.
+/// A target just past the end of the list ends the script.
+// Variants are named after the Mac edition's C++ structs.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) enum CinematicCommand {
+    CinematicCommandCommit,
+    CinematicCommandSetCharacter {
+        slot: CinematicCharacterSlot,
+        character: CinematicCharacter,
+    },
+    CinematicCommandSetMood {
+        slot: CinematicCharacterSlot,
+        mood: usize,
+    },
+    CinematicCommandClearCharacter {
+        slot: CinematicCharacterSlot,
+    },
+    CinematicCommandSetSpeaker {
+        slot: CinematicCharacterSlot,
+    },
+    CinematicCommandClearSpeaker,
+    CinematicCommandSetText {
+        text: String,
+    },
+    CinematicCommandClearText,
+    CinematicCommandSetBackground {
+        id: usize,
+    },
+    CinematicCommandClearBackground,
+    CinematicCommandSetMaterial {
+        id: usize,
+    },
+    CinematicCommandClearMaterial,
+    CinematicCommandSetFlag {
+        flag: usize,
+        value: i16,
+    },
+    /// Int flags only.
+    CinematicCommandAddFlag {
+        flag: usize,
+        amount: i16,
+    },
+    CinematicCommandJump {
+        target: usize,
+    },
+    CinematicCommandJumpUnless {
+        condition: CinematicCondition,
+        target: usize,
+    },
+    /// Shows the page like a commit, with 2 to 4 options to pick from,
+    /// then goes on from the picked option's target.
+    CinematicCommandChoice {
+        options: Vec<CinematicChoice>,
+    },
+    /// Centers the view of the map behind the stage on a map point.
+    CinematicCommandPanCamera {
+        x: i16,
+        y: i16,
+        frames: u16,
+    },
+    CinematicCommandShakeCamera {
+        magnitude: u16,
+        frames: u16,
+    },
+}
@@ -0,0 +1,755 @@
+//! Turn script source into a syntax tree.
+
+use crate::cinematic_script::ast::{
+    ChoiceOption, Condition, ConditionTest, FlagOperator, FlagValue, Script, Span, Spanned,
+    Statement, UnsetTarget,
+};
+use crate::cinematic_script::diagnostic::Diagnostic;
+use crate::cinematic_script::{CinematicCharacterSlot, CinematicComparison};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref SCRIPT_SPEAKER: Regex =
+        Regex::new(r"^([A-Z0-9_]+):$").expect("Couldn't compile SCRIPT_SPEAKER regex");
+    static ref SCRIPT_MOOD: Regex =
+        Regex::new(r"^\[([a-z0-9_]+)\]$").expect("Couldn't compile SCRIPT_MOOD regex");
+}
+
+const SET_TARGETS: &str = "`background`, `material`, `left`, or `right`";
+const UNSET_TARGETS: &str = "`background`, `material`, `speaker`, `text`, `left`, or `right`";
+const FLAG_OPERATORS: &str = "`=`, `+=`, or `-=`";
+const COMPARISONS: &str = "`==`, `!=`, `<`, `<=`, `>`, or `>=`";
+const DIRECTIVES: &str =
+    "`!set`, `!unset`, `!flag`, `!if`, `!choice`, `!label`, `!jump`, `!pan`, or `!shake`";
+
+/// What one line of a script turned out to be.
+enum Line {
+    Statement(Statement),
+    If(Condition),
+    Else,
+    End,
+    Choice,
+    Option(Spanned<String>),
+}
+
+/// A block that has started but not ended yet.
+enum Block {
+    If {
+        span: Span,
+        condition: Condition,
+        then: Vec<Spanned<Statement>>,
+        /// Set once `!else` is seen.
+        otherwise: Option<Vec<Spanned<Statement>>>,
+    },
+    Choice {
+        span: Span,
+        options: Vec<ChoiceOption>,
+    },
+    /// Stands in for an `!if` or `!choice` line that didn't parse, so that its `!else`, `!option`s,
+    /// and `!end` still match up. Everything in it is left out.
+    Invalid { span: Span },
+}
+
+impl Block {
+    fn push(&mut self, statement: Spanned<Statement>) -> Result<(), Diagnostic> {
+        match self {
+            Block::If {
+                then, otherwise, ..
+            } => otherwise.as_mut().unwrap_or(then).push(statement),
+            Block::Choice { options, .. } => {
+                let Some(option) = options.last_mut() else {
+                    return Err(Diagnostic::error(
+                        "Expected !option after !choice",
+                        statement.span,
+                    )
+                    .with_label("not part of any option"));
+                };
+                option.body.push(statement);
+            }
+            Block::Invalid { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// The finished block as a statement, if it's valid.
+    fn end(self) -> Result<Option<Spanned<Statement>>, Diagnostic> {
+        match self {
+            Block::If {
+                span,
+                condition,
+                then,
+                otherwise,
+            } => Ok(Some(Spanned::new(
+                Statement::If {
+                    condition,
+                    then,
+                    otherwise: otherwise.unwrap_or_default(),
+                },
+                span,
+            ))),
+            Block::Choice { span, options } => {
+                if !(2..=4).contains(&options.len()) {
+                    return Err(Diagnostic::error(
+                        format!(
+                            "A choice needs 2 to 4 options, but this one has {n}",
+                            n = options.len()
+                        ),
+                        span,
+                    )
+                    .with_label("wrong number of `!option`s"));
+                }
+                Ok(Some(Spanned::new(Statement::Choice(options), span)))
+            }
+            Block::Invalid { .. } => Ok(None),
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            Block::If { span, .. } | Block::Choice { span, .. } | Block::Invalid { span } => *span,
+        }
+    }
+}
+
+/// Parse a whole script. Lines that don't parse are reported and left out,
+/// so that one mistake doesn't hide the ones after it.
+pub(crate) fn parse(source: &str) -> (Script, Vec<Diagnostic>) {
+    let mut script = Script::default();
+    let mut diagnostics = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut offset = 0;
+    for raw_line in source.split_inclusive('\n') {
+        let start = offset;
+        offset += raw_line.len();
+        let line = raw_line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let span = line_span(line, start);
+        let result = match parse_line(line, start) {
+            Ok(Line::Statement(statement)) => {
+                push(&mut script, &mut blocks, Spanned::new(statement, span))
+            }
+            Ok(Line::If(condition)) => {
+                blocks.push(Block::If {
+                    span,
+                    condition,
+                    then: Vec::new(),
+                    otherwise: None,
+                });
+                Ok(())
+            }
+            Ok(Line::Choice) => {
+                blocks.push(Block::Choice {
+                    span,
+                    options: Vec::new(),
+                });
+                Ok(())
+            }
+            Ok(Line::Else) => match blocks.last_mut() {
+                Some(Block::If {
+                    otherwise: otherwise @ None,
+                    ..
+                }) => {
+                    *otherwise = Some(Vec::new());
+                    Ok(())
+                }
+                Some(Block::If { .. }) => {
+                    Err(Diagnostic::error("This !if already has an !else", span)
+                        .with_label("second `!else`"))
+                }
+                Some(Block::Invalid { .. }) => Ok(()),
+                _ => Err(Diagnostic::error("!else without !if", span)
+                    .with_label("not inside an `!if` block")),
+            },
+            Ok(Line::Option(text)) => match blocks.last_mut() {
+                Some(Block::Choice { options, .. }) => {
+                    options.push(ChoiceOption {
+                        text,
+                        body: Vec::new(),
+                    });
+                    Ok(())
+                }
+                Some(Block::Invalid { .. }) => Ok(()),
+                _ => Err(Diagnostic::error("!option without !choice", span)
+                    .with_label("not inside a `!choice` block")),
+            },
+            Ok(Line::End) => match blocks.pop() {
+                Some(block) => block.end().and_then(|statement| match statement {
+                    Some(statement) => push(&mut script, &mut blocks, statement),
+                    None => Ok(()),
+                }),
+                None => Err(Diagnostic::error("!end without !if or !choice", span)
+                    .with_label("nothing to end")),
+            },
+            Err(diagnostic) => {
+                if matches!(line.split_whitespace().next(), Some("!if" | "!choice")) {
+                    blocks.push(Block::Invalid { span });
+                }
+                Err(diagnostic)
+            }
+        };
+        if let Err(diagnostic) = result {
+            diagnostics.push(diagnostic);
+        }
+    }
+    for block in blocks {
+        diagnostics.push(
+            Diagnostic::error("Missing !end", block.span()).with_label("this block never ends"),
+        );
+    }
+    (script, diagnostics)
+}
+
+/// Add a statement to the innermost unfinished block, or to the script if there isn't one.
+fn push(
+    script: &mut Script,
+    blocks: &mut [Block],
+    statement: Spanned<Statement>,
+) -> Result<(), Diagnostic> {
+    match blocks.last_mut() {
+        Some(block) => block.push(statement),
+        None => {
+            script.statements.push(statement);
+            Ok(())
+        }
+    }
+}
+
+/// `start` is the byte offset of the line in the script.
+fn parse_line(line: &str, start: usize) -> Result<Line, Diagnostic> {
+    if line.starts_with('!') {
+        return parse_directive(line, start);
+    }
+    if let Some(name) = SCRIPT_SPEAKER.captures(line).and_then(|c| c.get(1)) {
+        return Ok(Line::Statement(Statement::Speaker(spanned(
+            name.as_str(),
+            start + name.start(),
+        ))));
+    }
+    if let Some(name) = SCRIPT_MOOD.captures(line).and_then(|c| c.get(1)) {
+        return Ok(Line::Statement(Statement::Mood(spanned(
+            name.as_str(),
+            start + name.start(),
+        ))));
+    }
+    if line.starts_with('[') && line.ends_with(']') {
+        return Err(
+            Diagnostic::error(format!("Invalid mood: {line}"), line_span(line, start))
+                .with_label("not a mood name")
+                .with_help(
+                    "moods are lowercase letters, digits, and underscores, like `[mouth_open]`",
+                ),
+        );
+    }
+    Ok(Line::Statement(Statement::Text(line.to_string())))
+}
+
+fn parse_directive(line: &str, start: usize) -> Result<Line, Diagnostic> {
+    let words = words(line, start);
+    let directive = &words[0];
+    match directive.node {
+        "!set" => {
+            let Some(target) = words.get(1) else {
+                return Err(Diagnostic::error("Missing slot for !set", directive.span)
+                    .with_label(format!("expected {SET_TARGETS} after this")));
+            };
+            let value = rest_of_line(line, start, &words, 2).ok_or_else(|| {
+                Diagnostic::error(
+                    format!("Missing value for !set {target}", target = target.node),
+                    target.span,
+                )
+                .with_label("expected a name after this")
+            })?;
+            match target.node {
+                "background" => Ok(Line::Statement(Statement::SetBackground(value))),
+                "material" => Ok(Line::Statement(Statement::SetMaterial(value))),
+                "left" | "right" => {
+                    if value.node != "…" {
+                        return Err(Diagnostic::error(
+                            format!(
+                                "!set {slot} doesn't take a name, only '…', but got {name}",
+                                slot = target.node,
+                                name = value.node
+                            ),
+                            value.span,
+                        )
+                        .with_label("expected `…`")
+                        .with_help(
+                            "name the character on the next line, like `ESRI:`, then give their mood, like `[pleased]`",
+                        ));
+                    }
+                    Ok(Line::Statement(Statement::Enter(Spanned::new(
+                        slot(target.node),
+                        target.span,
+                    ))))
+                }
+                _ => Err(Diagnostic::error(
+                    format!("Unknown slot for !set: {target}", target = target.node),
+                    target.span,
+                )
+                .with_label(format!("expected {SET_TARGETS}"))),
+            }
+        }
+        "!unset" => {
+            let Some(target) = words.get(1) else {
+                return Err(Diagnostic::error("Missing slot for !unset", directive.span)
+                    .with_label(format!("expected {UNSET_TARGETS} after this")));
+            };
+            if let Some(extra) = words.get(2) {
+                return Err(Diagnostic::error(
+                    format!("!unset {target} doesn't take a value", target = target.node),
+                    Span::new(extra.span.start, start + line.len()),
+                )
+                .with_label("unexpected"));
+            }
+            let unset_target = match target.node {
+                "background" => UnsetTarget::Background,
+                "material" => UnsetTarget::Material,
+                "speaker" => UnsetTarget::Speaker,
+                "text" => UnsetTarget::Text,
+                "left" | "right" => UnsetTarget::Character(slot(target.node)),
+                _ => {
+                    return Err(Diagnostic::error(
+                        format!("Unknown slot for !unset: {target}", target = target.node),
+                        target.span,
+                    )
+                    .with_label(format!("expected {UNSET_TARGETS}")))
+                }
+            };
+            Ok(Line::Statement(Statement::Unset(Spanned::new(
+                unset_target,
+                target.span,
+            ))))
+        }
+        "!flag" => {
+            let Some(flag) = words.get(1) else {
+                return Err(Diagnostic::error("Missing flag for !flag", directive.span)
+                    .with_label("expected a flag name after this"));
+            };
+            let Some(operator) = words.get(2) else {
+                return Err(Diagnostic::error(
+                    format!("Missing value for !flag {flag}", flag = flag.node),
+                    flag.span,
+                )
+                .with_label(format!("expected {FLAG_OPERATORS} and a value after this")));
+            };
+            let operator_node = match operator.node {
+                "=" => FlagOperator::Set,
+                "+=" => FlagOperator::Add,
+                "-=" => FlagOperator::Subtract,
+                _ => {
+                    return Err(Diagnostic::error(
+                        format!(
+                            "Unknown operator for !flag: {operator}",
+                            operator = operator.node
+                        ),
+                        operator.span,
+                    )
+                    .with_label(format!("expected {FLAG_OPERATORS}")))
+                }
+            };
+            let Some(value) = words.get(3) else {
+                return Err(Diagnostic::error(
+                    format!("Missing value for !flag {flag}", flag = flag.node),
+                    operator.span,
+                )
+                .with_label("expected `true`, `false`, or a number after this"));
+            };
+            expect_end(line, start, &words, 4, "!flag")?;
+            let value_node = match value.node {
+                "true" => FlagValue::Bool(true),
+                "false" => FlagValue::Bool(false),
+                _ => FlagValue::Int(number(value)?.node),
+            };
+            Ok(Line::Statement(Statement::SetFlag {
+                flag: spanned(flag.node, flag.span.start),
+                operator: operator_node,
+                value: Spanned::new(value_node, value.span),
+            }))
+        }
+        "!if" => {
+            let condition = match &words[1..] {
+                [flag] => Condition {
+                    flag: spanned(flag.node, flag.span.start),
+                    test: ConditionTest::True,
+                },
+                [not, flag] if not.node == "not" => Condition {
+                    flag: spanned(flag.node, flag.span.start),
+                    test: ConditionTest::False,
+                },
+                [flag, comparison, value] => {
+                    let comparison = match comparison.node {
+                        "==" => CinematicComparison::Eq,
+                        "!=" => CinematicComparison::Ne,
+                        "<" => CinematicComparison::Lt,
+                        "<=" => CinematicComparison::Le,
+                        ">" => CinematicComparison::Gt,
+                        ">=" => CinematicComparison::Ge,
+                        _ => {
+                            return Err(Diagnostic::error(
+                                format!(
+                                    "Unknown comparison for !if: {comparison}",
+                                    comparison = comparison.node
+                                ),
+                                comparison.span,
+                            )
+                            .with_label(format!("expected {COMPARISONS}")))
+                        }
+                    };
+                    Condition {
+                        flag: spanned(flag.node, flag.span.start),
+                        test: ConditionTest::Compare(comparison, number(value)?),
+                    }
+                }
+                _ => {
+                    return Err(Diagnostic::error(
+                        "Invalid condition for !if",
+                        line_span(line, start),
+                    )
+                    .with_label("expected a condition")
+                    .with_help(format!(
+                        "conditions look like `!if <flag>`, `!if not <flag>`, or `!if <flag> <op> <number>`, with `op` one of {COMPARISONS}"
+                    )))
+                }
+            };
+            Ok(Line::If(condition))
+        }
+        "!else" => {
+            expect_end(line, start, &words, 1, "!else")?;
+            Ok(Line::Else)
+        }
+        "!end" => {
+            expect_end(line, start, &words, 1, "!end")?;
+            Ok(Line::End)
+        }
+        "!choice" => {
+            expect_end(line, start, &words, 1, "!choice")?;
+            Ok(Line::Choice)
+        }
+        "!option" => {
+            let text = rest_of_line(line, start, &words, 1).ok_or_else(|| {
+                Diagnostic::error("Missing text for !option", directive.span)
+                    .with_label("expected what the option says after this")
+            })?;
+            Ok(Line::Option(text))
+        }
+        "!label" | "!jump" => {
+            let Some(label) = words.get(1) else {
+                return Err(Diagnostic::error(
+                    format!("Missing label for {directive}", directive = directive.node),
+                    directive.span,
+                )
+                .with_label("expected a label name after this"));
+            };
+            expect_end(line, start, &words, 2, directive.node)?;
+            let label = spanned(label.node, label.span.start);
+            Ok(Line::Statement(if directive.node == "!label" {
+                Statement::Label(label)
+            } else {
+                Statement::Jump(label)
+            }))
+        }
+        "!pan" => {
+            let [_, x, y, frames] = &words[..] else {
+                return Err(Diagnostic::error(
+                    "!pan takes a map point and a number of frames",
+                    line_span(line, start),
+                )
+                .with_label("expected `!pan <x> <y> <frames>`"));
+            };
+            Ok(Line::Statement(Statement::Pan {
+                x: number(x)?.node,
+                y: number(y)?.node,
+                frames: count(frames)?.node,
+            }))
+        }
+        "!shake" => {
+            let [_, magnitude, frames] = &words[..] else {
+                return Err(Diagnostic::error(
+                    "!shake takes a number of pixels and a number of frames",
+                    line_span(line, start),
+                )
+                .with_label("expected `!shake <pixels> <frames>`"));
+            };
+            Ok(Line::Statement(Statement::Shake {
+                magnitude: count(magnitude)?.node,
+                frames: count(frames)?.node,
+            }))
+        }
+        _ => Err(Diagnostic::error(
+            format!("Unknown command: {directive}", directive = directive.node),
+            directive.span,
+        )
+        .with_label(format!("expected {DIRECTIVES}"))),
+    }
+}
+
+/// Everything from the word at `index` to the end of the line, if there's anything there.
+fn rest_of_line(
+    line: &str,
+    start: usize,
+    words: &[Spanned<&str>],
+    index: usize,
+) -> Option<Spanned<String>> {
+    let value_start = words.get(index)?.span.start;
+    Some(Spanned::new(
+        line[value_start - start..].to_string(),
+        Span::new(value_start, start + line.len()),
+    ))
+}
+
+/// Report anything after the first `count` words of a directive.
+fn expect_end(
+    line: &str,
+    start: usize,
+    words: &[Spanned<&str>],
+    count: usize,
+    directive: &str,
+) -> Result<(), Diagnostic> {
+    match words.get(count) {
+        Some(extra) => Err(Diagnostic::error(
+            format!("Too much on one line for {directive}"),
+            Span::new(extra.span.start, start + line.len()),
+        )
+        .with_label("unexpected")),
+        None => Ok(()),
+    }
+}
+
+fn number(word: &Spanned<&str>) -> Result<Spanned<i16>, Diagnostic> {
+    match word.node.parse::<i16>() {
+        Ok(n) => Ok(Spanned::new(n, word.span)),
+        Err(_) => Err(Diagnostic::error(
+            format!("Invalid number: {word}", word = word.node),
+            word.span,
+        )
+        .with_label(format!(
+            "expected a whole number from {min} to {max}",
+            min = i16::MIN,
+            max = i16::MAX
+        ))),
+    }
+}
+
+/// Like [`number`], but for things that can't be negative, like frames.
+fn count(word: &Spanned<&str>) -> Result<Spanned<u16>, Diagnostic> {
+    match word.node.parse::<u16>() {
+        Ok(n) => Ok(Spanned::new(n, word.span)),
+        Err(_) => Err(Diagnostic::error(
+            format!("Invalid count: {word}", word = word.node),
+            word.span,
+        )
+        .with_label(format!(
+            "expected a whole number from 0 to {max}",
+            max = u16::MAX
+        ))),
+    }
+}
+
+fn slot(name: &str) -> CinematicCharacterSlot {
+    CinematicCharacterSlot::try_from(name).expect("Caller should have checked slot name")
+}
+
+fn spanned(text: &str, start: usize) -> Spanned<String> {
+    Spanned::new(text.to_string(), Span::new(start, start + text.len()))
+}
+
+fn line_span(line: &str, start: usize) -> Span {
+    Span::new(start, start + line.len())
+}
+
+/// Whitespace-separated words of a line, with their spans.
+fn words(line: &str, start: usize) -> Vec<Spanned<&str>> {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (c.is_whitespace(), word_start) {
+            (true, Some(ws)) => {
+                words.push(Spanned::new(&line[ws..i], Span::new(start + ws, start + i)));
+                word_start = None;
+            }
+            (false, None) => word_start = Some(i),
+            _ => {}
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let source = "# comment\n!set background atelier_interior\n\n!set right …\nESRI:\n[pleased]\nAw crap.\n!unset right\n";
+        let (script, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+        let statements: Vec<Statement> = script.statements.into_iter().map(|s| s.node).collect();
+        assert_eq!(
+            statements,
+            vec![
+                Statement::SetBackground(Spanned::new(
+                    "atelier_interior".to_string(),
+                    Span::new(26, 42)
+                )),
+                Statement::Enter(Spanned::new(
+                    CinematicCharacterSlot::Right,
+                    Span::new(49, 54)
+                )),
+                Statement::Speaker(Spanned::new("ESRI".to_string(), Span::new(59, 63))),
+                Statement::Mood(Spanned::new("pleased".to_string(), Span::new(66, 73))),
+                Statement::Text("Aw crap.".to_string()),
+                Statement::Unset(Spanned::new(
+                    UnsetTarget::Character(CinematicCharacterSlot::Right),
+                    Span::new(91, 96)
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_reports_every_bad_line() {
+        let source = "!sett background x\n!set left Sae\n!unset\n[Pleased]\nFine.\n";
+        let (script, diagnostics) = parse(source);
+        assert_eq!(script.statements.len(), 1);
+        let spans: Vec<Span> = diagnostics.iter().map(|d| d.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 5),
+                Span::new(29, 32),
+                Span::new(33, 39),
+                Span::new(40, 49),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_blocks() {
+        let source = "!if not lied\n!choice\n!option Yes.\n!flag lied = true\n!option No.\n!jump end\n!end\n!else\nHm.\n!end\n!label end\n";
+        let (script, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+        let statements: Vec<Statement> = script.statements.into_iter().map(|s| s.node).collect();
+        assert_eq!(
+            statements,
+            vec![
+                Statement::If {
+                    condition: Condition {
+                        flag: Spanned::new("lied".to_string(), Span::new(8, 12)),
+                        test: ConditionTest::False,
+                    },
+                    then: vec![Spanned::new(
+                        Statement::Choice(vec![
+                            ChoiceOption {
+                                text: Spanned::new("Yes.".to_string(), Span::new(29, 33)),
+                                body: vec![Spanned::new(
+                                    Statement::SetFlag {
+                                        flag: Spanned::new("lied".to_string(), Span::new(40, 44)),
+                                        operator: FlagOperator::Set,
+                                        value: Spanned::new(
+                                            FlagValue::Bool(true),
+                                            Span::new(47, 51)
+                                        ),
+                                    },
+                                    Span::new(34, 51)
+                                )],
+                            },
+                            ChoiceOption {
+                                text: Spanned::new("No.".to_string(), Span::new(60, 63)),
+                                body: vec![Spanned::new(
+                                    Statement::Jump(Spanned::new(
+                                        "end".to_string(),
+                                        Span::new(70, 73)
+                                    )),
+                                    Span::new(64, 73)
+                                )],
+                            },
+                        ]),
+                        Span::new(13, 20)
+                    )],
+                    otherwise: vec![Spanned::new(
+                        Statement::Text("Hm.".to_string()),
+                        Span::new(85, 88)
+                    )],
+                },
+                Statement::Label(Spanned::new("end".to_string(), Span::new(101, 104))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_camera() {
+        let (script, diagnostics) = parse("!pan 120 -8 60\n!shake 3 20\n!pan 1 2\n!shake 3 -1\n");
+        let statements: Vec<Statement> = script.statements.into_iter().map(|s| s.node).collect();
+        assert_eq!(
+            statements,
+            vec![
+                Statement::Pan {
+                    x: 120,
+                    y: -8,
+                    frames: 60
+                },
+                Statement::Shake {
+                    magnitude: 3,
+                    frames: 20
+                },
+            ]
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "!pan takes a map point and a number of frames",
+                "Invalid count: -1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_reports_bad_blocks() {
+        let source =
+            "!end\n!choice\nHi.\n!option A\n!end\n!if x >> 1\n!flag x = 99999\n!else\n!if y\n";
+        let (_, diagnostics) = parse(source);
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "!end without !if or !choice",
+                "Expected !option after !choice",
+                "A choice needs 2 to 4 options, but this one has 1",
+                "Unknown comparison for !if: >>",
+                "Invalid number: 99999",
+                "Missing !end",
+                "Missing !end",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_keeps_blocks_balanced_after_bad_if() {
+        let source = "!choice
+!option A
+!if x >> 1
+Hi.
+!else
+Bye.
+!end
+!option B
+!end
+";
+        let (script, diagnostics) = parse(source);
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["Unknown comparison for !if: >>"]);
+        let [Spanned {
+            node: Statement::Choice(options),
+            ..
+        }] = script.statements.as_slice()
+        else {
+            panic!("expected one choice, got {:?}", script.statements);
+        };
+        assert_eq!(options.len(), 2);
+        assert!(options[0].body.is_empty());
+    }
+}
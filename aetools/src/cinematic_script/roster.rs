@@ -0,0 +1,173 @@
+//! Characters and materials that cinematics refer to by name.
+//!
+//! Characters are listed in `cinematics/characters.json`, as an array of objects with:
+//! - `id`: what scripts call them in speaker lines, like `ESRI`
+//! - `name`: what players see them called, like `Esri`
+//! - `portrait`: the prefix of their portraits, like `Esri` for `avatars/Esri_pleased.png`
+//!
+//! A character's numeric ID is their position in that array, so new characters go at the end.
+//! Materials come from `items.json`, and scripts can use either a material's ID or its name.
+
+use crate::items;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Where the character roster lives, relative to the assets directory.
+pub(crate) const CHARACTERS_PATH: &str = "cinematics/characters.json";
+/// Where the material table lives, relative to the assets directory.
+pub(crate) const ITEMS_PATH: &str = "items.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Character {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) portrait: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(transparent)]
+pub(crate) struct Characters {
+    characters: Vec<Character>,
+}
+
+impl Characters {
+    /// Read the roster from an assets directory.
+    pub(crate) fn load(asset_base_dir: &Path) -> anyhow::Result<Self> {
+        let path = asset_base_dir.join(CHARACTERS_PATH);
+        Self::parse(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::anyhow!("{path}: {e}", path = path.display()))
+    }
+
+    pub(crate) fn parse(source: &str) -> anyhow::Result<Self> {
+        let characters: Vec<Character> = serde_json::from_str(source)?;
+        for (i, character) in characters.iter().enumerate() {
+            if characters[..i].iter().any(|other| other.id == character.id) {
+                anyhow::bail!("character {id} is listed twice", id = character.id);
+            }
+            if characters[..i]
+                .iter()
+                .any(|other| other.portrait == character.portrait)
+            {
+                anyhow::bail!(
+                    "characters can't share portraits: {portrait}",
+                    portrait = character.portrait
+                );
+            }
+        }
+        Ok(Self { characters })
+    }
+
+    /// Numeric ID and roster entry of a character by script ID.
+    pub(crate) fn get(&self, id: &str) -> Option<(usize, &Character)> {
+        self.characters
+            .iter()
+            .enumerate()
+            .find(|(_, character)| character.id == id)
+    }
+
+    /// Numeric ID of a character by portrait prefix.
+    pub(crate) fn by_portrait(&self, portrait: &str) -> Option<usize> {
+        self.characters
+            .iter()
+            .position(|character| character.portrait == portrait)
+    }
+
+    pub(crate) fn character(&self, id: usize) -> anyhow::Result<&Character> {
+        self.characters
+            .get(id)
+            .ok_or(anyhow::anyhow!("Couldn't find character with ID {id}"))
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Character> {
+        self.characters.iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Material {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    /// Slice name of the material's icon in the item sprite sheets.
+    pub(crate) icon: String,
+}
+
+/// Materials in `items.json` order, which is the order the WASM-4 edition's `MATERIALS` are in.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(transparent)]
+pub(crate) struct Materials {
+    materials: Vec<Material>,
+}
+
+impl Materials {
+    /// Read the materials from an assets directory.
+    pub(crate) fn load(asset_base_dir: &Path) -> anyhow::Result<Self> {
+        let path = asset_base_dir.join(ITEMS_PATH);
+        let materials = items::material_labels(&path)
+            .map_err(|e| anyhow::anyhow!("{path}: {e}", path = path.display()))?
+            .into_iter()
+            .map(|(id, name, icon)| Material { id, name, icon })
+            .collect();
+        Ok(Self { materials })
+    }
+
+    /// Index of a material by ID or name.
+    pub(crate) fn get(&self, id_or_name: &str) -> Option<usize> {
+        self.materials
+            .iter()
+            .position(|material| material.id == id_or_name || material.name == id_or_name)
+    }
+
+    pub(crate) fn material(&self, index: usize) -> anyhow::Result<&Material> {
+        self.materials
+            .get(index)
+            .ok_or(anyhow::anyhow!("Couldn't find material with index {index}"))
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Material> {
+        self.materials.iter()
+    }
+}
+
+#[cfg(test)]
+impl FromIterator<(&'static str, &'static str)> for Materials {
+    fn from_iter<T: IntoIterator<Item = (&'static str, &'static str)>>(iter: T) -> Self {
+        Self {
+            materials: iter
+                .into_iter()
+                .map(|(id, name)| Material {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                    icon: id.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_characters() {
+        let characters = Characters::parse(
+            r#"[
+                {"id": "ESRI", "name": "Esri", "portrait": "Esri"},
+                {"id": "SAE", "name": "Sae", "portrait": "Sae"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(characters.get("SAE").map(|(id, _)| id), Some(1));
+        assert_eq!(characters.by_portrait("Esri"), Some(0));
+        assert_eq!(characters.character(1).unwrap().name, "Sae");
+        assert!(characters.character(2).is_err());
+        assert!(Characters::parse(
+            r#"[
+                {"id": "ESRI", "name": "Esri", "portrait": "Esri"},
+                {"id": "ESRI", "name": "Other Esri", "portrait": "Esri2"}
+            ]"#,
+        )
+        .is_err());
+    }
+}
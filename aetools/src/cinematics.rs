@@ -0,0 +1,578 @@
+//! Generate WASM-4 cinematic data, or a JSON version for anything else, from `.aecinematic` scripts.
+
+use crate::cinematic_script::{
+    compile_script, Characters, CinematicCharacterSlot, CinematicCommand, CinematicComparison,
+    CinematicCondition, CinematicTables, Diagnostics, Materials, StoryFlagKind, StoryFlags,
+};
+use crate::unisprite;
+use convert_case::{Case, Casing};
+use glob::glob;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Bool story flags are `story::StoryFlag`s, which are `u8`s.
+const MAX_BOOL_FLAGS: usize = 256;
+/// `story::INT_FLAG_COUNT`, the int story flags there's room to save.
+const MAX_INT_FLAGS: usize = 16;
+
+/// Where the WASM-4 edition's stand-ins for background art live, relative to the assets directory.
+const WASM4_BACKGROUNDS_PATH: &str = "cinematics/wasm4_backgrounds.json";
+/// `cinematic::STAGE_H`: backgrounds fill the screen down to the dialog box.
+const STAGE_H: u8 = 120;
+
+/// How the WASM-4 edition draws a background, since it doesn't have room for the art:
+/// a wall down to the floor line, and then floor.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Wasm4Background {
+    name: String,
+    /// Where the floor starts, in pixels from the top of the screen.
+    /// Backgrounds without any floor have it at the bottom of the stage.
+    floor_y: u8,
+    /// WASM-4 palette colors, from 1 to 4.
+    wall_color: u8,
+    floor_color: u8,
+}
+
+impl Wasm4Background {
+    /// Read the stand-ins from an assets directory.
+    fn load(asset_base_dir: &Path) -> anyhow::Result<Vec<Self>> {
+        let path = asset_base_dir.join(WASM4_BACKGROUNDS_PATH);
+        Self::parse(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::anyhow!("{path}: {e}", path = path.display()))
+    }
+
+    fn parse(source: &str) -> anyhow::Result<Vec<Self>> {
+        let backgrounds: Vec<Self> = serde_json::from_str(source)?;
+        for (i, background) in backgrounds.iter().enumerate() {
+            let name = &background.name;
+            if backgrounds[..i].iter().any(|other| other.name == *name) {
+                anyhow::bail!("background {name} is listed twice");
+            }
+            if background.floor_y > STAGE_H {
+                anyhow::bail!("background {name} has its floor below the stage");
+            }
+            if ![background.wall_color, background.floor_color]
+                .iter()
+                .all(|color| (1..=4).contains(color))
+            {
+                anyhow::bail!("background {name} has colors that aren't from 1 to 4");
+            }
+        }
+        Ok(backgrounds)
+    }
+
+    fn to_rust(&self) -> String {
+        format!(
+            "CinematicBackground {{ floor_y: {floor_y}, wall_color: {wall_color}, floor_color: {floor_color} }}",
+            floor_y = self.floor_y,
+            wall_color = self.wall_color,
+            floor_color = self.floor_color,
+        )
+    }
+}
+
+/// Generate a Rust module with a `CinematicCommand` slice for every script in `cinematics/*.aecinematic`
+/// under the assets directory, plus the character, material, portrait, and background tables they index.
+/// Only portraits and backgrounds that some script uses are included, since cart space is tight.
+/// Every background a script uses must be in `cinematics/wasm4_backgrounds.json`.
+/// Output is not formatted; run `rustfmt` on it if you want to read it.
+pub fn code(asset_base_dir: &Path, output_path: &Path) -> anyhow::Result<()> {
+    let tables = CinematicTables::load(asset_base_dir)?;
+    let wasm4_backgrounds = Wasm4Background::load(asset_base_dir)?;
+    let flags = &tables.flags;
+    for (kind, max) in [
+        (StoryFlagKind::Bool, MAX_BOOL_FLAGS),
+        (StoryFlagKind::Int, MAX_INT_FLAGS),
+    ] {
+        if flags.iter().filter(|flag| flag.kind == kind).count() > max {
+            anyhow::bail!("WASM-4 only has room for {max} {kind:?} story flags");
+        }
+    }
+
+    // Portrait index for every character and mood used so far, and the portraits in that order.
+    let mut portrait_indexes = BTreeMap::<(usize, String), usize>::new();
+    let mut portraits = Vec::<String>::new();
+    // Backgrounds by name, in order of first use.
+    let mut backgrounds = Vec::<String>::new();
+
+    let compiled = compile_scripts(
+        asset_base_dir,
+        &tables,
+        |id: usize, mood: &str| -> anyhow::Result<usize> {
+            let key = (id, mood.to_string());
+            if let Some(index) = portrait_indexes.get(&key) {
+                return Ok(*index);
+            }
+            let path = portrait_path(asset_base_dir, &tables.characters, id, mood)?;
+            let image = image::open(&path)
+                .map_err(|e| {
+                    anyhow::anyhow!("Couldn't load portrait {path}: {e}", path = path.display())
+                })?
+                .to_luma_alpha8();
+            portraits.push(unisprite_to_rust(&unisprite::encode_image(&image)));
+            let index = portraits.len() - 1;
+            portrait_indexes.insert(key, index);
+            Ok(index)
+        },
+        |background: &str| -> anyhow::Result<usize> {
+            if !wasm4_backgrounds
+                .iter()
+                .any(|known| known.name == background)
+            {
+                anyhow::bail!(
+                    "Background {background} isn't in {WASM4_BACKGROUNDS_PATH}, \
+                    so the WASM-4 edition can't draw it"
+                );
+            }
+            Ok(background_index(&mut backgrounds, background))
+        },
+    )?;
+
+    let mut scripts = Vec::<String>::new();
+    for (name, script) in &compiled {
+        let commands = script
+            .iter()
+            .map(|command| command_to_rust(command, flags))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|e| anyhow::anyhow!("Couldn't convert cinematic {name}: {e}"))?;
+        scripts.push(format!(
+            "({name:?}, &[{commands}])",
+            commands = commands.join(", ")
+        ));
+    }
+
+    let acc = [
+        "// Generated from cinematic scripts by `aetools cinematics-code`. Do not edit.\n"
+            .to_string(),
+        "#[allow(unused_imports)]\nuse crate::cinematic::{CinematicBackground, CinematicCharacter, CinematicCommand, Comparison, Condition, Slot};\n"
+            .to_string(),
+        format!(
+            "pub const CHARACTER_NAMES: &[&str] = &[{names}];\n",
+            names = tables
+                .characters
+                .iter()
+                .map(|character| Ok(format!("{:?}", to_ascii(&character.name)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ),
+        format!(
+            "pub const MATERIAL_NAMES: &[&str] = &[{names}];\n",
+            names = tables
+                .materials
+                .iter()
+                .map(|material| Ok(format!("{:?}", to_ascii(&material.name)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ),
+        format!(
+            "pub const PORTRAITS: &[&aesprite::Unisprite<&[u8]>] = &[{portraits}];\n",
+            portraits = portraits.join(", ")
+        ),
+        format!(
+            "pub const BACKGROUNDS: &[CinematicBackground] = &[{backgrounds}];\n",
+            backgrounds = backgrounds
+                .iter()
+                .filter_map(|name| wasm4_backgrounds.iter().find(|known| known.name == *name))
+                .map(Wasm4Background::to_rust)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        format!(
+            "pub const SCRIPTS: &[(&str, &[CinematicCommand])] = &[{scripts}];\n",
+            scripts = scripts.join(", ")
+        ),
+    ];
+
+    let mut rs = BufWriter::new(File::create(output_path)?);
+    write!(rs, "{src}", src = acc.join("\n"))?;
+    Ok(())
+}
+
+/// Everything the scripts refer to by index, and the scripts themselves,
+/// in a form that doesn't depend on any edition of the game.
+#[derive(Serialize)]
+struct CinematicsJson<'a> {
+    flags: &'a StoryFlags,
+    characters: &'a Characters,
+    materials: &'a Materials,
+    portraits: Vec<PortraitJson>,
+    backgrounds: Vec<String>,
+    scripts: BTreeMap<String, Vec<CinematicCommand>>,
+}
+
+#[derive(Serialize)]
+struct PortraitJson {
+    /// Index into `characters`.
+    character: usize,
+    mood: String,
+}
+
+/// Write every script in `cinematics/*.aecinematic` under the assets directory as JSON,
+/// with the flag, character, material, portrait, and background tables that the commands index,
+/// for tools and game editions that don't get generated code of their own.
+pub fn json(asset_base_dir: &Path, output_path: &Path) -> anyhow::Result<()> {
+    let tables = CinematicTables::load(asset_base_dir)?;
+    let mut portraits = Vec::<PortraitJson>::new();
+    let mut backgrounds = Vec::<String>::new();
+
+    let scripts = compile_scripts(
+        asset_base_dir,
+        &tables,
+        |id: usize, mood: &str| -> anyhow::Result<usize> {
+            if let Some(index) = portraits
+                .iter()
+                .position(|portrait| portrait.character == id && portrait.mood == mood)
+            {
+                return Ok(index);
+            }
+            let path = portrait_path(asset_base_dir, &tables.characters, id, mood)?;
+            if !path.exists() {
+                anyhow::bail!("Couldn't find portrait {path}", path = path.display());
+            }
+            portraits.push(PortraitJson {
+                character: id,
+                mood: mood.to_string(),
+            });
+            Ok(portraits.len() - 1)
+        },
+        |background: &str| -> anyhow::Result<usize> {
+            Ok(background_index(&mut backgrounds, background))
+        },
+    )?;
+
+    let cinematics = CinematicsJson {
+        flags: &tables.flags,
+        characters: &tables.characters,
+        materials: &tables.materials,
+        portraits,
+        backgrounds,
+        scripts,
+    };
+    serde_json::to_writer_pretty(BufWriter::new(File::create(output_path)?), &cinematics)?;
+    Ok(())
+}
+
+/// Compile every script in `cinematics/*.aecinematic` under the assets directory, by snake case name.
+/// Mistakes in every script are reported, not just the first one with any.
+fn compile_scripts(
+    asset_base_dir: &Path,
+    tables: &CinematicTables,
+    mut lookup_mood: impl FnMut(usize, &str) -> anyhow::Result<usize>,
+    mut lookup_background: impl FnMut(&str) -> anyhow::Result<usize>,
+) -> anyhow::Result<BTreeMap<String, Vec<CinematicCommand>>> {
+    let mut scripts = BTreeMap::<String, Vec<CinematicCommand>>::new();
+    let mut script_errors = Vec::<String>::new();
+
+    let pattern = asset_base_dir.join("cinematics").join("*.aecinematic");
+    for glob_result in glob(&pattern.to_string_lossy())? {
+        let src = glob_result?;
+        let name = src
+            .file_stem()
+            .ok_or(anyhow::anyhow!("Couldn't get file stem for cinematic"))?
+            .to_string_lossy()
+            .to_case(Case::Snake);
+
+        let script = match compile_script(&src, tables, &mut lookup_mood, &mut lookup_background) {
+            Ok(script) => script,
+            Err(e) => match e.downcast::<Diagnostics>() {
+                Ok(diagnostics) => {
+                    script_errors.push(diagnostics.to_string());
+                    continue;
+                }
+                Err(e) => return Err(e),
+            },
+        };
+        if scripts.insert(name.clone(), script).is_some() {
+            anyhow::bail!("More than one cinematic is named {name}");
+        }
+    }
+
+    if !script_errors.is_empty() {
+        anyhow::bail!("{}", script_errors.join("\n\n"));
+    }
+    Ok(scripts)
+}
+
+fn portrait_path(
+    asset_base_dir: &Path,
+    characters: &Characters,
+    id: usize,
+    mood: &str,
+) -> anyhow::Result<PathBuf> {
+    Ok(asset_base_dir.join("avatars").join(format!(
+        "{portrait}_{mood}.png",
+        portrait = characters.character(id)?.portrait
+    )))
+}
+
+/// Index of a background by name, adding it if it's new.
+fn background_index(backgrounds: &mut Vec<String>, background: &str) -> usize {
+    if let Some(index) = backgrounds.iter().position(|name| name == background) {
+        return index;
+    }
+    backgrounds.push(background.to_string());
+    backgrounds.len() - 1
+}
+
+fn unisprite_to_rust(sprite: &aesprite::Unisprite<Vec<u8>>) -> String {
+    format!(
+        "&aesprite::Unisprite {{ w: {w}, h: {h}, luma: &{luma:?}, alpha: &{alpha:?}, }}",
+        w = sprite.w,
+        h = sprite.h,
+        luma = sprite.luma,
+        alpha = sprite.alpha,
+    )
+}
+
+fn slot_to_rust(slot: &CinematicCharacterSlot) -> &'static str {
+    match slot {
+        CinematicCharacterSlot::Left => "Slot::Left",
+        CinematicCharacterSlot::Right => "Slot::Right",
+    }
+}
+
+/// Bool flags are story bits and int flags are kept apart, each numbered on their own.
+fn condition_to_rust(condition: &CinematicCondition, flags: &StoryFlags) -> anyhow::Result<String> {
+    let index = flags.index_within_kind(condition.flag);
+    Ok(
+        match (
+            flags.kind(condition.flag),
+            condition.comparison,
+            condition.value,
+        ) {
+            (StoryFlagKind::Bool, CinematicComparison::Ne, 0) => {
+                format!("Condition::Flag({index}, true)")
+            }
+            (StoryFlagKind::Bool, CinematicComparison::Eq, 0) => {
+                format!("Condition::Flag({index}, false)")
+            }
+            (StoryFlagKind::Bool, _, _) => {
+                anyhow::bail!("Can't compare bool flag to a number: {condition:?}")
+            }
+            (StoryFlagKind::Int, comparison, value) => {
+                format!("Condition::IntFlag({index}, Comparison::{comparison:?}, {value})")
+            }
+        },
+    )
+}
+
+fn command_to_rust(command: &CinematicCommand, flags: &StoryFlags) -> anyhow::Result<String> {
+    Ok(match command {
+        CinematicCommand::CinematicCommandCommit => "CinematicCommand::Commit".to_string(),
+        CinematicCommand::CinematicCommandSetCharacter { slot, character } => format!(
+            "CinematicCommand::SetCharacter {{ slot: {slot}, character: CinematicCharacter {{ id: {id}, mood: {mood} }} }}",
+            slot = slot_to_rust(slot),
+            id = character.id,
+            mood = character.mood,
+        ),
+        CinematicCommand::CinematicCommandSetMood { slot, mood } => format!(
+            "CinematicCommand::SetMood {{ slot: {slot}, mood: {mood} }}",
+            slot = slot_to_rust(slot),
+        ),
+        CinematicCommand::CinematicCommandClearCharacter { slot } => format!(
+            "CinematicCommand::ClearCharacter {{ slot: {slot} }}",
+            slot = slot_to_rust(slot),
+        ),
+        CinematicCommand::CinematicCommandSetSpeaker { slot } => format!(
+            "CinematicCommand::SetSpeaker {{ slot: {slot} }}",
+            slot = slot_to_rust(slot),
+        ),
+        CinematicCommand::CinematicCommandClearSpeaker => {
+            "CinematicCommand::ClearSpeaker".to_string()
+        }
+        CinematicCommand::CinematicCommandSetText { text } => format!(
+            "CinematicCommand::SetText({text:?})",
+            text = to_ascii(text)?
+        ),
+        CinematicCommand::CinematicCommandClearText => "CinematicCommand::ClearText".to_string(),
+        CinematicCommand::CinematicCommandSetBackground { id } => {
+            format!("CinematicCommand::SetBackground({id})")
+        }
+        CinematicCommand::CinematicCommandClearBackground => {
+            "CinematicCommand::ClearBackground".to_string()
+        }
+        CinematicCommand::CinematicCommandSetMaterial { id } => {
+            format!("CinematicCommand::SetMaterial({id})")
+        }
+        CinematicCommand::CinematicCommandClearMaterial => {
+            "CinematicCommand::ClearMaterial".to_string()
+        }
+        CinematicCommand::CinematicCommandSetFlag { flag, value } => {
+            let index = flags.index_within_kind(*flag);
+            match flags.kind(*flag) {
+                StoryFlagKind::Bool => format!(
+                    "CinematicCommand::SetFlag({index}, {value})",
+                    value = *value != 0
+                ),
+                StoryFlagKind::Int => format!("CinematicCommand::SetIntFlag({index}, {value})"),
+            }
+        }
+        CinematicCommand::CinematicCommandAddFlag { flag, amount } => format!(
+            "CinematicCommand::AddIntFlag({index}, {amount})",
+            index = flags.index_within_kind(*flag)
+        ),
+        CinematicCommand::CinematicCommandJump { target } => {
+            format!("CinematicCommand::Jump({target})")
+        }
+        CinematicCommand::CinematicCommandJumpUnless { condition, target } => format!(
+            "CinematicCommand::JumpUnless({condition}, {target})",
+            condition = condition_to_rust(condition, flags)?
+        ),
+        CinematicCommand::CinematicCommandChoice { options } => format!(
+            "CinematicCommand::Choice(&[{options}])",
+            options = options
+                .iter()
+                .map(|option| Ok(format!(
+                    "({text:?}, {target})",
+                    text = to_ascii(&option.text)?,
+                    target = option.target
+                )))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ),
+        CinematicCommand::CinematicCommandPanCamera { x, y, frames } => {
+            format!("CinematicCommand::PanCamera {{ x: {x}, y: {y}, frames: {frames} }}")
+        }
+        CinematicCommand::CinematicCommandShakeCamera { magnitude, frames } => format!(
+            "CinematicCommand::ShakeCamera {{ magnitude: {magnitude}, frames: {frames} }}"
+        ),
+    })
+}
+
+/// The WASM-4 edition's tiny font only has printable ASCII,
+/// so replace the typographic punctuation scripts are written with.
+fn to_ascii(text: &str) -> anyhow::Result<String> {
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ' '..='~' => ascii.push(c),
+            '…' => ascii.push_str("..."),
+            '‘' | '’' => ascii.push('\''),
+            '“' | '”' => ascii.push('"'),
+            '–' => ascii.push('-'),
+            '—' => ascii.push_str("--"),
+            _ => anyhow::bail!("Can't show character {c:?} on WASM-4: {text}"),
+        }
+    }
+    Ok(ascii)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cinematic_script::CinematicChoice;
+
+    fn flags() -> StoryFlags {
+        StoryFlags::parse("bool met_sae\nint times_lied\nbool lied_to_parents\n").unwrap()
+    }
+
+    #[test]
+    fn test_condition_to_rust() {
+        let flags = flags();
+        let condition = |flag, comparison, value| CinematicCondition {
+            flag,
+            comparison,
+            value,
+        };
+        assert_eq!(
+            condition_to_rust(&condition(2, CinematicComparison::Ne, 0), &flags).unwrap(),
+            "Condition::Flag(1, true)"
+        );
+        assert_eq!(
+            condition_to_rust(&condition(0, CinematicComparison::Eq, 0), &flags).unwrap(),
+            "Condition::Flag(0, false)"
+        );
+        assert_eq!(
+            condition_to_rust(&condition(1, CinematicComparison::Ge, 3), &flags).unwrap(),
+            "Condition::IntFlag(0, Comparison::Ge, 3)"
+        );
+        assert!(condition_to_rust(&condition(0, CinematicComparison::Gt, 1), &flags).is_err());
+    }
+
+    #[test]
+    fn test_command_to_rust() {
+        let flags = flags();
+        let commands = [
+            CinematicCommand::CinematicCommandSetFlag { flag: 2, value: 1 },
+            CinematicCommand::CinematicCommandSetFlag { flag: 1, value: -2 },
+            CinematicCommand::CinematicCommandAddFlag { flag: 1, amount: 1 },
+            CinematicCommand::CinematicCommandJumpUnless {
+                condition: CinematicCondition {
+                    flag: 0,
+                    comparison: CinematicComparison::Ne,
+                    value: 0,
+                },
+                target: 7,
+            },
+            CinematicCommand::CinematicCommandJump { target: 2 },
+            CinematicCommand::CinematicCommandChoice {
+                options: vec![
+                    CinematicChoice {
+                        text: "Sure…".to_string(),
+                        target: 3,
+                    },
+                    CinematicChoice {
+                        text: "No.".to_string(),
+                        target: 9,
+                    },
+                ],
+            },
+            CinematicCommand::CinematicCommandSetMaterial { id: 4 },
+            CinematicCommand::CinematicCommandClearMaterial,
+        ];
+        let rust = commands
+            .iter()
+            .map(|command| command_to_rust(command, &flags).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rust,
+            vec![
+                "CinematicCommand::SetFlag(1, true)",
+                "CinematicCommand::SetIntFlag(0, -2)",
+                "CinematicCommand::AddIntFlag(0, 1)",
+                "CinematicCommand::JumpUnless(Condition::Flag(0, true), 7)",
+                "CinematicCommand::Jump(2)",
+                r#"CinematicCommand::Choice(&[("Sure...", 3), ("No.", 9)])"#,
+                "CinematicCommand::SetMaterial(4)",
+                "CinematicCommand::ClearMaterial",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        assert_eq!(
+            to_ascii("Huh. So if we were to get a bigger cauldron…").unwrap(),
+            "Huh. So if we were to get a bigger cauldron..."
+        );
+        assert_eq!(
+            to_ascii("“Mistress.” It’s fine").unwrap(),
+            "\"Mistress.\" It's fine"
+        );
+        assert!(to_ascii("Café").is_err());
+    }
+
+    #[test]
+    fn test_parse_wasm4_backgrounds() {
+        let backgrounds = Wasm4Background::parse(
+            r#"[{"name": "atelier_interior", "floor_y": 96, "wall_color": 1, "floor_color": 3}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            backgrounds[0].to_rust(),
+            "CinematicBackground { floor_y: 96, wall_color: 1, floor_color: 3 }"
+        );
+        assert!(Wasm4Background::parse(
+            r#"[{"name": "cave", "floor_y": 130, "wall_color": 1, "floor_color": 3}]"#,
+        )
+        .is_err());
+        assert!(Wasm4Background::parse(
+            r#"[{"name": "cave", "floor_y": 96, "wall_color": 0, "floor_color": 3}]"#,
+        )
+        .is_err());
+    }
+}
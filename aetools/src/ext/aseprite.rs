@@ -48,6 +48,27 @@ pub fn export_metadata(input: &Path, output: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// List the names of an Aseprite file's slices.
+pub fn list_slices(input: &Path) -> anyhow::Result<Vec<String>> {
+    let output = Command::new(ASEPRITE)
+        .arg("--batch")
+        .arg("--list-slices")
+        .arg(input)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{ASEPRITE} exited with code {status}",
+            status = output.status
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect())
+}
+
 /// Read data written by [export_metadata].
 pub fn read_metadata(input: &Path) -> anyhow::Result<Project> {
     let project = serde_json::from_reader(File::open(input)?)?;
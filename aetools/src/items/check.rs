@@ -0,0 +1,331 @@
+//! Semantic checks for items JSON: things the JSON schema can't express,
+//! like references between materials and the shape of recipe link graphs.
+
+use crate::assets::SPRITE_ASSETS;
+use crate::ext::aseprite;
+use crate::items::{load, Items, Recipe, RecipeNodeInput};
+use glob::glob;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// Something wrong with an items file, and where it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// JSONPath to the offending value.
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{path}: {message}",
+            path = self.path,
+            message = self.message
+        )
+    }
+}
+
+/// Print every problem with an items file, and fail if there were any.
+pub fn check(input_path: &Path, asset_base_dir: &Path) -> anyhow::Result<()> {
+    let items = load(input_path)?;
+    let icons = item_icon_names(asset_base_dir)?;
+    let problems = items.problems(&icons);
+    for problem in &problems {
+        eprintln!("{problem}");
+    }
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "Found {count} problem(s) in {input}",
+            count = problems.len(),
+            input = input_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Names of all the sprites in the item asset group, which are the valid material icons.
+fn item_icon_names(asset_base_dir: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let item_asset_group = SPRITE_ASSETS
+        .iter()
+        .find(|x| x.name == "item")
+        .ok_or(anyhow::anyhow!("Couldn't find item asset group"))?;
+
+    let mut names = BTreeSet::<String>::new();
+    for src_glob in item_asset_group.srcs {
+        for glob_result in glob(&asset_base_dir.join(src_glob).to_string_lossy())? {
+            let src = glob_result?;
+            match src.extension().and_then(|ext| ext.to_str()) {
+                Some("aseprite") => names.extend(aseprite::list_slices(&src)?),
+                Some("png") => {
+                    let base_name = src.file_stem().ok_or(anyhow::anyhow!(
+                        "Couldn't get file stem for asset file: {src}",
+                        src = src.to_string_lossy()
+                    ))?;
+                    names.insert(base_name.to_string_lossy().to_string());
+                }
+                _ => anyhow::bail!(
+                    "Unsupported file extension: {src}",
+                    src = src.to_string_lossy()
+                ),
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Append an object member to a JSONPath,
+/// using bracket notation if it's not a plain identifier.
+fn member(path: &str, key: &str) -> String {
+    let is_identifier = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        format!("{path}.{key}")
+    } else {
+        format!("{path}[{key:?}]")
+    }
+}
+
+/// Append an array index to a JSONPath.
+fn index(path: &str, i: usize) -> String {
+    format!("{path}[{i}]")
+}
+
+impl Items {
+    /// Find every problem with these items.
+    /// `icons` is the set of sprite names that material icons may refer to.
+    fn problems(&self, icons: &BTreeSet<String>) -> Vec<Problem> {
+        let mut problems = Vec::<Problem>::new();
+        let materials_path = member("$", "materials");
+        for (material_id, material) in &self.materials {
+            let material_path = member(&materials_path, material_id);
+
+            if !icons.contains(&material.icon) {
+                problems.push(Problem {
+                    path: member(&material_path, "icon"),
+                    message: format!("No item sprite named {icon}", icon = material.icon),
+                });
+            }
+
+            if let Some(recipe) = &material.recipe {
+                self.recipe_problems(recipe, &member(&material_path, "recipe"), &mut problems);
+            }
+        }
+        problems
+    }
+
+    fn recipe_problems(&self, recipe: &Recipe, recipe_path: &str, problems: &mut Vec<Problem>) {
+        let nodes_path = member(recipe_path, "nodes");
+        let links_path = member(recipe_path, "links");
+
+        // Node inputs and positions.
+        let mut nodes_by_grid_pos = BTreeMap::<(i8, i8), &String>::new();
+        for (node_id, node) in &recipe.nodes {
+            let node_path = member(&nodes_path, node_id);
+
+            if let RecipeNodeInput::Material(input_material_id) = &node.input {
+                if !self.materials.contains_key(input_material_id) {
+                    problems.push(Problem {
+                        path: member(&member(&node_path, "input"), "material"),
+                        message: format!("No material with ID {input_material_id}"),
+                    });
+                }
+            }
+
+            if let Some(other_node_id) = nodes_by_grid_pos.insert(node.grid_pos, node_id) {
+                problems.push(Problem {
+                    path: member(&node_path, "grid_pos"),
+                    message: format!(
+                        "Hex cell {grid_pos:?} is already used by node {other_node_id}",
+                        grid_pos = node.grid_pos
+                    ),
+                });
+            }
+        }
+
+        // Links. Each node gets its parent from the first link that targets it.
+        let mut parents = vec![None::<usize>; recipe.nodes.len()];
+        for (i, (parent_id, child_id)) in recipe.links.iter().enumerate() {
+            let link_path = index(&links_path, i);
+
+            let parent_index = recipe.nodes.get_index_of(parent_id);
+            if parent_index.is_none() {
+                problems.push(Problem {
+                    path: index(&link_path, 0),
+                    message: format!("No node with ID {parent_id}"),
+                });
+            }
+            let child_index = recipe.nodes.get_index_of(child_id);
+            if child_index.is_none() {
+                problems.push(Problem {
+                    path: index(&link_path, 1),
+                    message: format!("No node with ID {child_id}"),
+                });
+            }
+
+            let (Some(parent_index), Some(child_index)) = (parent_index, child_index) else {
+                continue;
+            };
+            if let Some(existing_parent_index) = parents[child_index] {
+                let (existing_parent_id, _) = recipe
+                    .nodes
+                    .get_index(existing_parent_index)
+                    .expect("Parent index out of range");
+                problems.push(Problem {
+                    path: link_path,
+                    message: format!(
+                        "Node {child_id} already has parent {existing_parent_id}, can't also link it from {parent_id}"
+                    ),
+                });
+            } else {
+                parents[child_index] = Some(parent_index);
+            }
+        }
+
+        // Cycles. Every node has at most one parent at this point,
+        // so a cycle is found by following parents until we revisit a node.
+        let mut reported = BTreeSet::<usize>::new();
+        for start in 0..parents.len() {
+            let mut walk = Vec::<usize>::new();
+            let mut current = Some(start);
+            while let Some(node_index) = current {
+                if let Some(cycle_start) = walk.iter().position(|i| *i == node_index) {
+                    let cycle = &walk[cycle_start..];
+                    if cycle.iter().all(|i| !reported.contains(i)) {
+                        reported.extend(cycle);
+                        // We walked from children to parents, so reverse that to get link order.
+                        let cycle_ids = std::iter::once(cycle[0])
+                            .chain(cycle[1..].iter().rev().copied())
+                            .chain(std::iter::once(cycle[0]))
+                            .map(|i| {
+                                recipe
+                                    .nodes
+                                    .get_index(i)
+                                    .expect("Node index out of range")
+                                    .0
+                                    .as_str()
+                            })
+                            .collect::<Vec<_>>();
+                        problems.push(Problem {
+                            path: links_path.clone(),
+                            message: format!(
+                                "Links form a cycle: {cycle}",
+                                cycle = cycle_ids.join(" -> ")
+                            ),
+                        });
+                    }
+                    break;
+                }
+                walk.push(node_index);
+                current = parents[node_index];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(json: &str) -> Vec<Problem> {
+        let items: Items = serde_json::from_str(json).unwrap();
+        let icons = BTreeSet::from(["ore_copper".to_string(), "bomb".to_string()]);
+        items.problems(&icons)
+    }
+
+    fn paths(problems: &[Problem]) -> Vec<&str> {
+        problems.iter().map(|p| p.path.as_str()).collect()
+    }
+
+    const ORE: &str =
+        r#""ore_copper": {"name": "Ore", "icon": "ore_copper", "categories": ["ore"]}"#;
+
+    fn bomb(nodes: &str, links: &str) -> String {
+        format!(
+            r#"{{"materials": {{{ORE}, "bomb": {{"name": "Bomb", "icon": "bomb", "categories": ["bombs"],
+                "recipe": {{"nodes": {{{nodes}}}, "links": {links}}}}}}}}}"#
+        )
+    }
+
+    fn node(x: i8, y: i8, input: &str) -> String {
+        format!(r#"{{"grid_pos": [{x}, {y}], "element": "fire", "input": {input}, "effects": []}}"#)
+    }
+
+    #[test]
+    fn test_valid() {
+        let json = bomb(
+            &format!(
+                r#""a": {a}, "b": {b}"#,
+                a = node(0, 0, r#"{"material": "ore_copper"}"#),
+                b = node(1, 0, r#"{"category": "sand"}"#),
+            ),
+            r#"[["a", "b"]]"#,
+        );
+        assert_eq!(Vec::<Problem>::new(), problems(&json));
+    }
+
+    #[test]
+    fn test_dangling_references() {
+        let json = format!(
+            r#"{{"materials": {{"bomb": {{"name": "Bomb", "icon": "bomb_big", "categories": ["bombs"],
+                "recipe": {{"nodes": {{"a": {a}}}, "links": [["a", "b"]]}}}}}}}}"#,
+            a = node(0, 0, r#"{"material": "ore_copper"}"#),
+        );
+        assert_eq!(
+            vec![
+                "$.materials.bomb.icon",
+                "$.materials.bomb.recipe.nodes.a.input.material",
+                "$.materials.bomb.recipe.links[0][1]",
+            ],
+            paths(&problems(&json))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_grid_pos() {
+        let json = bomb(
+            &format!(
+                r#""a": {a}, "b": {b}"#,
+                a = node(0, 0, r#"{"category": "sand"}"#),
+                b = node(0, 0, r#"{"category": "sand"}"#),
+            ),
+            r#"[["a", "b"]]"#,
+        );
+        assert_eq!(
+            vec!["$.materials.bomb.recipe.nodes.b.grid_pos"],
+            paths(&problems(&json))
+        );
+    }
+
+    #[test]
+    fn test_multiple_parents_and_cycles() {
+        let json = bomb(
+            &format!(
+                r#""a": {a}, "b": {b}, "c": {c}"#,
+                a = node(0, 0, r#"{"category": "sand"}"#),
+                b = node(1, 0, r#"{"category": "sand"}"#),
+                c = node(2, 0, r#"{"category": "sand"}"#),
+            ),
+            r#"[["a", "b"], ["b", "c"], ["c", "a"], ["a", "c"]]"#,
+        );
+        let problems = problems(&json);
+        assert_eq!(
+            vec![
+                "$.materials.bomb.recipe.links[3]",
+                "$.materials.bomb.recipe.links",
+            ],
+            paths(&problems)
+        );
+        assert_eq!("Links form a cycle: a -> b -> c -> a", problems[1].message);
+    }
+
+    #[test]
+    fn test_member_path() {
+        assert_eq!("$.materials", member("$", "materials"));
+        assert_eq!(r#"$["dragon eye"]"#, member("$", "dragon eye"));
+        assert_eq!(r#"$["1up"]"#, member("$", "1up"));
+    }
+}
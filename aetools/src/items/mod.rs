@@ -0,0 +1,547 @@
+mod check;
+mod render;
+mod simulate;
+
+pub use check::check;
+pub use render::render;
+pub use simulate::simulate;
+
+use anyhow;
+use enumset::{EnumSet, EnumSetType};
+use indexmap::IndexMap;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::num::{NonZeroU16, NonZeroU8};
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct Items {
+    materials: IndexMap<MaterialId, Material>,
+}
+
+type Lo5AssetId = String;
+
+type MaterialId = String;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct Material {
+    name: String,
+    icon: Lo5AssetId,
+    categories: EnumSet<Category>,
+    #[serde(default)]
+    recipe: Option<Recipe>,
+    /// Items of this material also count toward nodes up to this many links away.
+    #[serde(default)]
+    effect_spread: Option<EffectSpread>,
+}
+
+type RecipeNodeId = String;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct Recipe {
+    nodes: IndexMap<RecipeNodeId, RecipeNode>,
+    links: Vec<(RecipeNodeId, RecipeNodeId)>,
+}
+
+type Quality = NonZeroU16;
+
+type ElementCount = NonZeroU8;
+
+/// Number of recipe links that Effect Spread reaches across.
+type EffectSpread = NonZeroU8;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct RecipeNode {
+    grid_pos: (i8, i8),
+    /// Element used for display and for effect levels.
+    element: Element,
+    input: RecipeNodeInput,
+    effects: Vec<RecipeNodeEffect>,
+    #[serde(default)]
+    elemental_requirement: Option<RecipeNodeElementalRequirement>,
+    #[serde(default)]
+    quality_requirement: Option<Quality>,
+    /// Effect Spread doesn't go into, out of, or through nodes with halos around them.
+    #[serde(default)]
+    halo: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Effect {
+    Quality,
+    SynthQuantity,
+    FireDmg,
+}
+
+type EffectLevel = NonZeroU8;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct RecipeNodeEffect {
+    id: Effect,
+    level: EffectLevel,
+    count: ElementCount,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct RecipeNodeElementalRequirement {
+    element: Element,
+    count: ElementCount,
+}
+
+#[derive(EnumSetType, Debug, Serialize, Deserialize, JsonSchema)]
+#[enumset(serialize_as_list)]
+#[serde(rename_all = "snake_case")]
+enum Element {
+    Fire,
+    Ice,
+    Lightning,
+    Wind,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum RecipeNodeInput {
+    Material(MaterialId),
+    Category(Category),
+}
+
+/// Item category. Items may have more than one but should have at least one.
+/// Probably won't use all of these.
+#[derive(EnumSetType, Debug, Serialize, Deserialize, JsonSchema)]
+#[enumset(serialize_as_list)]
+#[serde(rename_all = "snake_case")]
+enum Category {
+    Water,
+    Plants,
+    Uni,
+    Flowers,
+    Medicinal,
+    Poisons,
+    Elixirs,
+    Sand,
+    Stone,
+    Ore,
+    Gemstones,
+    Gunpowder,
+    Fuel,
+    Edibles,
+    Fruit,
+    Beehives,
+    Mushrooms,
+    Seafood,
+    Bugs,
+    Threads,
+    Lumber,
+    Gases,
+    Puniballs,
+    AnimalProducts,
+    DragonMaterials,
+    Magical,
+    Neutralizers,
+    GeneralGoods,
+    Metal,
+    Jewels,
+    Spices,
+    Seeds,
+    Food,
+    Medicine,
+    Bombs,
+    MagicTools,
+    Ingots,
+    Cloth,
+    Weapons,
+    Armor,
+    Accessories,
+    Tools,
+    Furniture,
+    KeyItems,
+    Essence,
+}
+
+pub fn schema(output_path: &Path) -> anyhow::Result<()> {
+    let schema = schema_for!(Items);
+    File::create(output_path)?.write(&serde_json::to_vec_pretty(&schema)?)?;
+    Ok(())
+}
+
+/// Read an items JSON file.
+fn load(input_path: &Path) -> anyhow::Result<Items> {
+    Ok(serde_json::from_reader(BufReader::new(File::open(
+        input_path,
+    )?))?)
+}
+
+/// ID, name, and icon of every material in an items JSON file, in `MATERIALS` order.
+pub(crate) fn material_labels(
+    input_path: &Path,
+) -> anyhow::Result<Vec<(MaterialId, String, Lo5AssetId)>> {
+    Ok(load(input_path)?
+        .materials
+        .into_iter()
+        .map(|(id, material)| (id, material.name, material.icon))
+        .collect())
+}
+
+pub fn code(input_path: &Path, output_path: &Path) -> anyhow::Result<()> {
+    let items = load(input_path)?;
+    let mut rs = BufWriter::new(File::create(output_path)?);
+    write!(rs, "{src}", src = items.to_rust()?)?;
+    Ok(())
+}
+
+// region Rust codegen
+
+/// Rust source for a single value, for use in the WASM-4 edition's `alchemy` module.
+trait ToRust {
+    fn to_rust(&self) -> String;
+}
+
+/// Name of the constant for a material.
+fn material_const(id: &MaterialId) -> String {
+    id.to_uppercase()
+}
+
+/// Path to the `Lo5SplitSprite` constant generated by the WASM-4 build script for an item icon.
+/// The build script names these constants after the upper-cased Aseprite slice name.
+fn icon_const(id: &Lo5AssetId) -> String {
+    format!("asset_data::item::{name}", name = id.to_uppercase())
+}
+
+impl ToRust for Element {
+    fn to_rust(&self) -> String {
+        format!("Element::{self:?}")
+    }
+}
+
+impl ToRust for Effect {
+    fn to_rust(&self) -> String {
+        format!("Effect::{self:?}")
+    }
+}
+
+impl ToRust for EnumSet<Category> {
+    fn to_rust(&self) -> String {
+        format!(
+            "enum_set!({categories})",
+            categories = self
+                .iter()
+                .map(|category| format!("Category::{category:?}"))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        )
+    }
+}
+
+impl ToRust for RecipeNodeEffect {
+    fn to_rust(&self) -> String {
+        format!(
+            "RecipeNodeEffect {{ id: {id}, level: {level}, count: {count} }}",
+            id = self.id.to_rust(),
+            level = self.level,
+            count = self.count,
+        )
+    }
+}
+
+impl ToRust for RecipeNodeElementalRequirement {
+    fn to_rust(&self) -> String {
+        format!(
+            "RecipeNodeElementalRequirement {{ element: {element}, count: {count} }}",
+            element = self.element.to_rust(),
+            count = self.count,
+        )
+    }
+}
+
+impl<T: ToRust> ToRust for Option<T> {
+    fn to_rust(&self) -> String {
+        match self {
+            Some(x) => format!("Some({x})", x = x.to_rust()),
+            None => "None".to_string(),
+        }
+    }
+}
+
+impl ToRust for Quality {
+    fn to_rust(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToRust for EffectSpread {
+    fn to_rust(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToRust for usize {
+    fn to_rust(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Items {
+    /// Generate a Rust module with a `Material` constant for each material.
+    /// Output is not formatted; run `rustfmt` on it if you want to read it.
+    fn to_rust(&self) -> anyhow::Result<String> {
+        let mut acc = vec![
+            "// Generated from items JSON by `aetools items-code`. Do not edit.\n".to_string(),
+            "use crate::alchemy::{Category, Effect, Element, Material, Recipe, RecipeNode, \
+            RecipeNodeEffect, RecipeNodeElementalRequirement, RecipeNodeInput};"
+                .to_string(),
+            "use crate::asset_data;".to_string(),
+            "use enumset::enum_set;\n".to_string(),
+        ];
+
+        for (index, (id, material)) in self.materials.iter().enumerate() {
+            // Save games store material indexes in a byte.
+            let index = u8::try_from(index).map_err(|_| {
+                anyhow::anyhow!("Too many materials: save games only have room for 256")
+            })?;
+            acc.push(self.material_to_rust(index, id, material)?);
+        }
+
+        acc.push(format!(
+            "/// Every material, in items JSON order.\n\
+            /// Save games refer to materials by their index in this list,\n\
+            /// so add new materials at the end.\n\
+            pub const MATERIALS: &[&Material] = &[{materials}];\n",
+            materials = self
+                .materials
+                .keys()
+                .map(|id| format!("{const_name}, ", const_name = material_const(id)))
+                .collect::<String>()
+        ));
+
+        Ok(acc.join("\n"))
+    }
+
+    fn material_to_rust(
+        &self,
+        index: u8,
+        id: &MaterialId,
+        material: &Material,
+    ) -> anyhow::Result<String> {
+        let recipe = match &material.recipe {
+            Some(recipe) => format!("Some({recipe})", recipe = self.recipe_to_rust(id, recipe)?),
+            None => "None".to_string(),
+        };
+        Ok(format!(
+            "pub const {const_name}: &Material = &Material {{ \
+            index: {index}, \
+            name: {name:?}, \
+            icon: {icon}, \
+            categories: {categories}, \
+            recipe: {recipe}, \
+            effect_spread: {effect_spread}, \
+            }};\n",
+            const_name = material_const(id),
+            name = material.name,
+            icon = icon_const(&material.icon),
+            categories = material.categories.to_rust(),
+            effect_spread = material.effect_spread.to_rust(),
+        ))
+    }
+
+    fn recipe_to_rust(&self, material_id: &MaterialId, recipe: &Recipe) -> anyhow::Result<String> {
+        let parents = recipe.parents().map_err(|e| {
+            anyhow::anyhow!("Couldn't resolve recipe links for material {material_id}: {e}")
+        })?;
+        let mut nodes = Vec::<String>::new();
+        for ((node_id, node), parent) in recipe.nodes.iter().zip(parents) {
+            let input = match &node.input {
+                RecipeNodeInput::Material(input_material_id) => {
+                    if !self.materials.contains_key(input_material_id) {
+                        anyhow::bail!(
+                            "Recipe node {node_id} for material {material_id} uses unknown material: {input_material_id}"
+                        );
+                    }
+                    format!(
+                        "RecipeNodeInput::Material({const_name})",
+                        const_name = material_const(input_material_id)
+                    )
+                }
+                RecipeNodeInput::Category(category) => {
+                    format!("RecipeNodeInput::Category(Category::{category:?})")
+                }
+            };
+            let effects: String = node
+                .effects
+                .iter()
+                .map(|effect| format!("{effect}, ", effect = effect.to_rust()))
+                .collect();
+            nodes.push(format!(
+                "RecipeNode {{ \
+                grid_pos: ({x}, {y}), \
+                element: {element}, \
+                input: {input}, \
+                effects: &[{effects}], \
+                elemental_requirement: {elemental_requirement}, \
+                quality_requirement: {quality_requirement}, \
+                parent: {parent}, \
+                halo: {halo}, \
+                }}, ",
+                x = node.grid_pos.0,
+                y = node.grid_pos.1,
+                element = node.element.to_rust(),
+                elemental_requirement = node.elemental_requirement.to_rust(),
+                quality_requirement = node.quality_requirement.to_rust(),
+                parent = parent.to_rust(),
+                halo = node.halo,
+            ));
+        }
+        Ok(format!(
+            "Recipe {{ nodes: &[{nodes}] }}",
+            nodes = nodes.concat()
+        ))
+    }
+}
+
+impl Recipe {
+    /// Index of each node's parent node, in node order, derived from the recipe's links.
+    /// Links go from parent to child, and each node can have at most one parent.
+    fn parents(&self) -> anyhow::Result<Vec<Option<usize>>> {
+        let mut parents = vec![None; self.nodes.len()];
+        for (parent_id, child_id) in &self.links {
+            let Some(parent_index) = self.nodes.get_index_of(parent_id) else {
+                anyhow::bail!("Link from unknown node: {parent_id}");
+            };
+            let Some(child_index) = self.nodes.get_index_of(child_id) else {
+                anyhow::bail!("Link to unknown node: {child_id}");
+            };
+            if parents[child_index].replace(parent_index).is_some() {
+                anyhow::bail!("Node has more than one parent: {child_id}");
+            }
+        }
+        Ok(parents)
+    }
+}
+
+// endregion Rust codegen
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items_to_rust() {
+        let items: Items = serde_json::from_str(
+            r#"{"materials": {
+                "ore_copper": {"name": "Copper Ore", "icon": "ore_copper", "categories": ["ore"],
+                    "effect_spread": 2},
+                "bomb": {"name": "Bomb", "icon": "bomb", "categories": ["bombs", "gunpowder"],
+                    "recipe": {
+                        "nodes": {
+                            "a": {"grid_pos": [0, 0], "element": "fire",
+                                "input": {"material": "ore_copper"},
+                                "effects": [{"id": "fire_dmg", "level": 1, "count": 2}]},
+                            "b": {"grid_pos": [1, -1], "element": "wind",
+                                "input": {"category": "sand"}, "effects": [],
+                                "elemental_requirement": {"element": "fire", "count": 3},
+                                "quality_requirement": 50, "halo": true}
+                        },
+                        "links": [["a", "b"]]
+                    }}
+            }}"#,
+        )
+        .unwrap();
+        let rust = items.to_rust().unwrap();
+        let chunks: Vec<&str> = rust.split('\n').collect();
+
+        assert_eq!(
+            chunks[0],
+            "// Generated from items JSON by `aetools items-code`. Do not edit."
+        );
+        assert!(chunks.contains(
+            &"pub const ORE_COPPER: &Material = &Material { \
+            index: 0, \
+            name: \"Copper Ore\", \
+            icon: asset_data::item::ORE_COPPER, \
+            categories: enum_set!(Category::Ore), \
+            recipe: None, \
+            effect_spread: Some(2), \
+            };"
+        ));
+        assert!(chunks.contains(
+            &"pub const BOMB: &Material = &Material { \
+            index: 1, \
+            name: \"Bomb\", \
+            icon: asset_data::item::BOMB, \
+            categories: enum_set!(Category::Gunpowder | Category::Bombs), \
+            recipe: Some(Recipe { nodes: &[\
+            RecipeNode { \
+            grid_pos: (0, 0), \
+            element: Element::Fire, \
+            input: RecipeNodeInput::Material(ORE_COPPER), \
+            effects: &[RecipeNodeEffect { id: Effect::FireDmg, level: 1, count: 2 }, ], \
+            elemental_requirement: None, \
+            quality_requirement: None, \
+            parent: None, \
+            halo: false, \
+            }, \
+            RecipeNode { \
+            grid_pos: (1, -1), \
+            element: Element::Wind, \
+            input: RecipeNodeInput::Category(Category::Sand), \
+            effects: &[], \
+            elemental_requirement: Some(RecipeNodeElementalRequirement { element: Element::Fire, count: 3 }), \
+            quality_requirement: Some(50), \
+            parent: Some(0), \
+            halo: true, \
+            }, \
+            ] }), \
+            effect_spread: None, \
+            };"
+        ));
+        assert!(chunks.contains(&"pub const MATERIALS: &[&Material] = &[ORE_COPPER, BOMB, ];"));
+    }
+
+    #[test]
+    fn test_items_to_rust_unknown_input() {
+        let items: Items = serde_json::from_str(
+            r#"{"materials": {
+                "bomb": {"name": "Bomb", "icon": "bomb", "categories": ["bombs"], "recipe": {
+                    "nodes": {"a": {"grid_pos": [0, 0], "element": "fire",
+                        "input": {"material": "ore_copper"}, "effects": []}},
+                    "links": []
+                }}
+            }}"#,
+        )
+        .unwrap();
+        assert!(items.to_rust().is_err());
+    }
+
+    /// Recipe with nodes `a` through `d` and the given links.
+    fn recipe(links: &[(&str, &str)]) -> Recipe {
+        let node = r#"{"grid_pos": [0, 0], "element": "fire", "input": {"category": "sand"}, "effects": []}"#;
+        serde_json::from_str(&format!(
+            r#"{{"nodes": {{"a": {node}, "b": {node}, "c": {node}, "d": {node}}}, "links": {links}}}"#,
+            links = serde_json::to_string(links).unwrap()
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_recipe_parents() {
+        // Links don't have to be listed in node order.
+        let chain = recipe(&[("c", "d"), ("a", "b"), ("b", "c")]);
+        assert_eq!(
+            chain.parents().unwrap(),
+            vec![None, Some(0), Some(1), Some(2)]
+        );
+
+        let fork = recipe(&[("a", "b"), ("a", "c")]);
+        assert_eq!(fork.parents().unwrap(), vec![None, Some(0), Some(0), None]);
+    }
+
+    #[test]
+    fn test_recipe_parents_errors() {
+        assert!(recipe(&[("x", "a")]).parents().is_err());
+        assert!(recipe(&[("a", "x")]).parents().is_err());
+        assert!(recipe(&[("a", "c"), ("b", "c")]).parents().is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! Draw recipe graphs as SVG and PNG images, so recipe designs can be reviewed without running the game.
+
+use crate::assets::{asset_group_foreach, export_or_copy_to_png, SPRITE_ASSETS};
+use crate::ext::imagemagick;
+use crate::fsutil::{delete_dir, ensure_dir};
+use crate::items::{load, Element, Items, Recipe, RecipeNode, RecipeNodeInput};
+use crate::palettes::WASM4_COLORS_ALPHA;
+use image::Rgba;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Distance between neighboring hex cells.
+/// Must match `SPACE` in the WASM-4 edition's `alchemy` module.
+const SPACE: f32 = 40.0;
+/// Horizontal spacing multiplier for hex columns.
+/// Must match `H_SPACE_MUL` in the WASM-4 edition's `alchemy` module.
+#[allow(clippy::excessive_precision)]
+const H_SPACE_MUL: f32 = 0.8660254037844386;
+
+/// Radius of the node hexagon, as drawn by `SynthesisNode::draw`.
+const NODE_RADIUS: f32 = 13.0;
+/// Room around the graph for effect slots and labels.
+const MARGIN: f32 = 24.0;
+/// SVG user units per game pixel.
+const SCALE: f32 = 4.0;
+const FONT_SIZE: f32 = 6.0;
+const LINE_HEIGHT: f32 = 8.0;
+
+/// Render every recipe in an items JSON file to an SVG and a PNG in the output directory.
+/// Sprites used by the images are exported next to them.
+pub fn render(input_path: &Path, asset_base_dir: &Path, build_dir: &Path) -> anyhow::Result<()> {
+    let items = load(input_path)?;
+
+    delete_dir(build_dir)?;
+    ensure_dir(build_dir)?;
+
+    // Element and item sprites end up in `element/` and `item/`, where the SVGs can refer to them.
+    asset_group_foreach(
+        SPRITE_ASSETS
+            .iter()
+            .filter(|group| group.name == "element" || group.name == "item"),
+        asset_base_dir,
+        build_dir,
+        export_or_copy_to_png,
+        |_group_name: &str, _group_dir: &Path| -> anyhow::Result<()> { Ok(()) },
+    )?;
+
+    for (material_id, material) in &items.materials {
+        let Some(recipe) = &material.recipe else {
+            continue;
+        };
+
+        let svg_path = build_dir.join(material_id).with_extension("svg");
+        {
+            let mut svg = BufWriter::new(File::create(&svg_path)?);
+            write!(
+                svg,
+                "{src}",
+                src = items.recipe_svg(&material.name, recipe)?
+            )?;
+        }
+
+        let png_path = svg_path.with_extension("png");
+        imagemagick::convert(&svg_path, &png_path)?;
+    }
+
+    Ok(())
+}
+
+/// Center of a node in game pixels, relative to the grid origin.
+/// Same layout as `SynthesisNode::center` in the WASM-4 edition.
+fn center(node: &RecipeNode) -> (f32, f32) {
+    let (col, row) = node.grid_pos;
+    (
+        col as f32 * SPACE * H_SPACE_MUL,
+        (row as f32 + if col % 2 == 1 { 0.5 } else { 0.0 }) * SPACE,
+    )
+}
+
+fn color(rgba: Rgba<u8>) -> String {
+    let Rgba([r, g, b, _]) = rgba;
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn xml_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&apos;".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+/// Name of the 7×7 icon sprite for an element, as used by `Element::icon`.
+fn element_icon(element: &Element) -> String {
+    format!(
+        "element/{name}7.png",
+        name = format!("{element:?}").to_lowercase()
+    )
+}
+
+/// Pixel-art sprite, centered on a point.
+fn sprite(href: &str, (x, y): (f32, f32), size: f32) -> String {
+    format!(
+        r#"<image href="{href}" x="{x}" y="{y}" width="{size}" height="{size}" style="image-rendering: pixelated"/>"#,
+        href = xml_escape(href),
+        x = x - size / 2.0,
+        y = y - size / 2.0,
+    )
+}
+
+fn text(content: &str, (x, y): (f32, f32), anchor: &str, fill: &str) -> String {
+    format!(
+        r#"<text x="{x}" y="{y}" text-anchor="{anchor}" fill="{fill}">{content}</text>"#,
+        content = xml_escape(content),
+    )
+}
+
+impl Items {
+    fn recipe_svg(&self, name: &str, recipe: &Recipe) -> anyhow::Result<String> {
+        let parents = recipe.parents()?;
+        let centers: Vec<(f32, f32)> = recipe.nodes.values().map(center).collect();
+
+        let light = color(WASM4_COLORS_ALPHA[3]);
+        let mid_light = color(WASM4_COLORS_ALPHA[2]);
+        let mid_dark = color(WASM4_COLORS_ALPHA[1]);
+        let dark = color(WASM4_COLORS_ALPHA[0]);
+
+        // Fit the graph, with a title above it and a legend of node details below it.
+        let min_x = centers.iter().map(|c| c.0).fold(f32::INFINITY, f32::min) - MARGIN;
+        let max_x = centers
+            .iter()
+            .map(|c| c.0)
+            .fold(f32::NEG_INFINITY, f32::max)
+            + MARGIN;
+        let min_y =
+            centers.iter().map(|c| c.1).fold(f32::INFINITY, f32::min) - MARGIN - LINE_HEIGHT;
+        let graph_max_y = centers
+            .iter()
+            .map(|c| c.1)
+            .fold(f32::NEG_INFINITY, f32::max)
+            + MARGIN;
+        let legend: Vec<String> = recipe
+            .nodes
+            .iter()
+            .map(|(node_id, node)| self.legend_line(node_id, node))
+            .collect();
+        let max_y = graph_max_y + LINE_HEIGHT * legend.len() as f32;
+        let legend_width = legend
+            .iter()
+            .map(|line| line.chars().count() as f32 * FONT_SIZE * 0.6)
+            .fold(0.0, f32::max);
+        let width = (max_x - min_x).max(legend_width + 2.0);
+        let height = max_y - min_y;
+
+        let mut acc = Vec::<String>::new();
+        acc.push(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{min_x} {min_y} {width} {height}" width="{scaled_width}" height="{scaled_height}" font-family="monospace" font-size="{FONT_SIZE}">"#,
+            scaled_width = width * SCALE,
+            scaled_height = height * SCALE,
+        ));
+        acc.push(format!(
+            r#"<rect x="{min_x}" y="{min_y}" width="{width}" height="{height}" fill="{light}"/>"#
+        ));
+        acc.push(text(
+            &format!("Synthesizing: {name}"),
+            (min_x + 1.0, min_y + FONT_SIZE),
+            "start",
+            &dark,
+        ));
+
+        // Links under nodes.
+        for (child_index, parent_index) in parents.iter().enumerate() {
+            let Some(parent_index) = parent_index else {
+                continue;
+            };
+            let (x1, y1) = centers[*parent_index];
+            let (x2, y2) = centers[child_index];
+            acc.push(format!(
+                r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{mid_dark}" stroke-width="3"/>"#
+            ));
+        }
+
+        for ((node_id, node), (x, y)) in recipe.nodes.iter().zip(&centers) {
+            let (x, y) = (*x, *y);
+
+            // Halo outside the effect slots.
+            if node.halo {
+                acc.push(format!(
+                    r#"<circle cx="{x}" cy="{y}" r="20" fill="none" stroke="{dark}" stroke-width="1"/>"#
+                ));
+            }
+
+            // Hexagon and backing circle.
+            let points: Vec<String> = (0..6)
+                .map(|i| {
+                    let theta = (2.0 * PI) * i as f32 / 6.0;
+                    format!(
+                        "{px},{py}",
+                        px = x + NODE_RADIUS * theta.cos(),
+                        py = y + NODE_RADIUS * theta.sin()
+                    )
+                })
+                .collect();
+            acc.push(format!(
+                r#"<polygon points="{points}" fill="{mid_dark}" stroke="{mid_light}" stroke-width="4" stroke-linejoin="round"/>"#,
+                points = points.join(" ")
+            ));
+            acc.push(format!(
+                r#"<circle cx="{x}" cy="{y}" r="10.5" fill="{mid_dark}"/>"#
+            ));
+
+            // Material icon or category name.
+            match &node.input {
+                RecipeNodeInput::Material(input_material_id) => {
+                    let Some(input_material) = self.materials.get(input_material_id) else {
+                        anyhow::bail!("Unknown material: {input_material_id}");
+                    };
+                    acc.push(sprite(
+                        &format!("item/{icon}.png", icon = input_material.icon),
+                        (x, y),
+                        16.0,
+                    ));
+                }
+                RecipeNodeInput::Category(category) => {
+                    acc.push(text(
+                        &format!("({category:?})"),
+                        (x, y + FONT_SIZE / 3.0),
+                        "middle",
+                        &light,
+                    ));
+                }
+            }
+
+            // Empty element slots for the first effect tier, as the game shows them before any items are added.
+            if let Some(effect) = node.effects.first() {
+                for i in 0..(effect.count.get().min(6)) {
+                    let theta = PI / -3.0 + (2.0 * PI) * i as f32 / 6.0;
+                    let slot = (
+                        x + (NODE_RADIUS + 2.0) * theta.cos(),
+                        y + (NODE_RADIUS + 2.0) * theta.sin(),
+                    );
+                    acc.push(sprite(&element_icon(&node.element), slot, 7.0));
+                }
+            }
+
+            // Locks.
+            if let Some(req) = &node.elemental_requirement {
+                acc.push(sprite("element/lock7.png", (x - 6.5, y + 7.5), 7.0));
+                acc.push(sprite(&element_icon(&req.element), (x + 1.5, y + 8.5), 7.0));
+                acc.push(text(
+                    &req.count.to_string(),
+                    (x + 8.0, y + 8.0 + FONT_SIZE / 3.0),
+                    "middle",
+                    &light,
+                ));
+            }
+            if let Some(quality) = &node.quality_requirement {
+                acc.push(sprite("element/lock7.png", (x - 6.5, y - 7.5), 7.0));
+                acc.push(text(
+                    &format!("Q{quality}"),
+                    (x + 3.0, y - 7.5 + FONT_SIZE / 3.0),
+                    "middle",
+                    &light,
+                ));
+            }
+
+            acc.push(text(node_id, (x, y + NODE_RADIUS + 8.0), "middle", &dark));
+        }
+
+        // Legend with the full effect tiers and requirements for each node.
+        for (i, line) in legend.iter().enumerate() {
+            acc.push(text(
+                line,
+                (min_x + 1.0, graph_max_y + LINE_HEIGHT * (i as f32 + 0.75)),
+                "start",
+                &dark,
+            ));
+        }
+
+        acc.push("</svg>\n".to_string());
+        Ok(acc.join("\n"))
+    }
+
+    /// One-line summary of a node: element, effect tiers with the element count each one needs, and locks.
+    fn legend_line(&self, node_id: &str, node: &RecipeNode) -> String {
+        let tiers: Vec<String> = node
+            .effects
+            .iter()
+            .map(|effect| {
+                format!(
+                    "{id:?} Lv{level} ({count})",
+                    id = effect.id,
+                    level = effect.level,
+                    count = effect.count
+                )
+            })
+            .collect();
+        let mut line = format!(
+            "{node_id}: {element:?}: {tiers}",
+            element = node.element,
+            tiers = if tiers.is_empty() {
+                "no effects".to_string()
+            } else {
+                tiers.join(", ")
+            }
+        );
+        if let Some(req) = &node.elemental_requirement {
+            line.push_str(&format!(
+                "; locked until parent has {count} {element:?}",
+                count = req.count,
+                element = req.element
+            ));
+        }
+        if let Some(quality) = &node.quality_requirement {
+            line.push_str(&format!("; locked below quality {quality}"));
+        }
+        if node.halo {
+            line.push_str("; halo");
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITEMS: &str = r#"{"materials": {
+        "ore_copper": {"name": "Ore", "icon": "ore_copper", "categories": ["ore"]},
+        "bomb": {"name": "Bomb & Fuse", "icon": "bomb", "categories": ["bombs"], "recipe": {
+            "nodes": {
+                "a": {"grid_pos": [0, 0], "element": "fire", "input": {"material": "ore_copper"},
+                    "effects": []},
+                "b": {"grid_pos": [1, 0], "element": "ice", "input": {"category": "sand"},
+                    "effects": [{"id": "quality", "level": 1, "count": 2}],
+                    "elemental_requirement": {"element": "fire", "count": 1}, "halo": true}
+            },
+            "links": [["a", "b"]]
+        }}
+    }}"#;
+
+    #[test]
+    fn test_recipe_svg() {
+        let items: Items = serde_json::from_str(ITEMS).unwrap();
+        let bomb = &items.materials["bomb"];
+        let svg = items
+            .recipe_svg(&bomb.name, bomb.recipe.as_ref().unwrap())
+            .unwrap();
+
+        // Node b is one column over and half a row down from node a, at the origin.
+        // The graph gets a margin all around, a title line above, and a legend line per node below.
+        let height = (0.5 * SPACE + MARGIN) - (-MARGIN - LINE_HEIGHT) + 2.0 * LINE_HEIGHT;
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-24 -32 "#));
+        assert!(svg.contains(&format!(
+            r#"height="{scaled_height}""#,
+            scaled_height = height * SCALE
+        )));
+        assert!(svg.ends_with("</svg>\n"));
+
+        assert_eq!(svg.matches("<polygon ").count(), 2);
+        assert_eq!(svg.matches("<line ").count(), 1);
+        assert!(svg.contains(r#"href="item/ore_copper.png""#));
+        assert!(svg.contains(">(Sand)</text>"));
+        assert_eq!(svg.matches(r#"href="element/ice7.png""#).count(), 2);
+        assert!(svg.contains(">Synthesizing: Bomb &amp; Fuse</text>"));
+    }
+
+    #[test]
+    fn test_legend_line() {
+        let items: Items = serde_json::from_str(ITEMS).unwrap();
+        let nodes = &items.materials["bomb"].recipe.as_ref().unwrap().nodes;
+        assert_eq!(items.legend_line("a", &nodes["a"]), "a: Fire: no effects");
+        assert_eq!(
+            items.legend_line("b", &nodes["b"]),
+            "b: Ice: Quality Lv1 (2); locked until parent has 1 Fire; halo"
+        );
+    }
+}
@@ -0,0 +1,586 @@
+//! Headless version of the WASM-4 edition's synthesis rules,
+//! for finding out which effect tiers are reachable with a given set of ingredients.
+
+use crate::items::{
+    load, Category, Effect, EffectSpread, Element, Items, Material, MaterialId, Recipe,
+    RecipeNodeInput,
+};
+use enumset::EnumSet;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Quality effects add this much quality per level to a synthesized item.
+/// Must match `QUALITY_PER_EFFECT_LEVEL` in the WASM-4 edition's `alchemy` module.
+const QUALITY_PER_EFFECT_LEVEL: u16 = 10;
+
+/// Give up instead of trying more ways than this to place the ingredients.
+const MAX_PLACEMENTS: usize = 1 << 20;
+
+/// A hypothetical instance of a material, like the ones in the player's inventory.
+/// Same fields as `Item` in the WASM-4 edition's `alchemy` module.
+#[derive(Debug, Deserialize)]
+struct Item {
+    material: MaterialId,
+    elements: EnumSet<Element>,
+    element_value: u8,
+    quality: u16,
+    /// Defaults to the material's categories.
+    #[serde(default)]
+    categories: Option<EnumSet<Category>>,
+}
+
+/// Simulate every recipe in an items JSON file with the ingredients from another JSON file,
+/// which contains an array of items, and print the best outcome for each recipe.
+pub fn simulate(input_path: &Path, ingredients_path: &Path) -> anyhow::Result<()> {
+    let items = load(input_path)?;
+    let ingredients: Vec<Item> =
+        serde_json::from_reader(BufReader::new(File::open(ingredients_path)?))?;
+    for item in &ingredients {
+        if !items.materials.contains_key(&item.material) {
+            anyhow::bail!(
+                "Ingredient has unknown material: {material}",
+                material = item.material
+            );
+        }
+    }
+
+    for (material_id, material) in &items.materials {
+        let Some(recipe) = &material.recipe else {
+            continue;
+        };
+        let simulation = Simulation::new(&items, recipe, &ingredients)?;
+        println!(
+            "{report}",
+            report = simulation.report(material_id, material)?
+        );
+    }
+
+    Ok(())
+}
+
+/// Ingredients placed on a recipe's nodes.
+/// `placements[i]` is the index of the node that ingredient `i` is on, if any.
+struct Simulation<'a> {
+    items: &'a Items,
+    recipe: &'a Recipe,
+    parents: Vec<Option<usize>>,
+    ingredients: &'a [Item],
+}
+
+/// What a recipe looks like after placing ingredients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Outcome {
+    placements: Vec<Option<usize>>,
+    /// Mean quality of the ingredients used, or `None` if there aren't any.
+    /// This is what quality locks are checked against.
+    quality: Option<u16>,
+    nodes: Vec<NodeOutcome>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NodeOutcome {
+    locked: bool,
+    /// Element value of ingredients on this node that match the node's element.
+    value: u32,
+    /// How many of the node's effect tiers are completely filled.
+    tiers: usize,
+}
+
+impl Outcome {
+    fn tiers(&self) -> usize {
+        self.nodes.iter().map(|node| node.tiers).sum()
+    }
+
+    fn unlocked(&self) -> usize {
+        self.nodes.iter().filter(|node| !node.locked).count()
+    }
+
+    /// Outcomes with higher scores are better: more effect tiers, then more open nodes, then higher quality.
+    fn score(&self) -> (usize, usize, Option<u16>) {
+        (self.tiers(), self.unlocked(), self.quality)
+    }
+}
+
+impl<'a> Simulation<'a> {
+    fn new(items: &'a Items, recipe: &'a Recipe, ingredients: &'a [Item]) -> anyhow::Result<Self> {
+        Ok(Simulation {
+            items,
+            recipe,
+            parents: recipe.parents()?,
+            ingredients,
+        })
+    }
+
+    fn categories(&self, item: &Item) -> EnumSet<Category> {
+        item.categories.unwrap_or_else(|| {
+            self.items
+                .materials
+                .get(&item.material)
+                .map(|material| material.categories)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Indexes of the nodes that will accept an ingredient.
+    fn compatible_nodes(&self, item: &Item) -> Vec<usize> {
+        self.recipe
+            .nodes
+            .values()
+            .enumerate()
+            .filter(|(_, node)| match &node.input {
+                RecipeNodeInput::Material(material_id) => *material_id == item.material,
+                RecipeNodeInput::Category(category) => self.categories(item).contains(*category),
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn effect_spread(&self, item: &Item) -> Option<EffectSpread> {
+        self.items
+            .materials
+            .get(&item.material)
+            .and_then(|material| material.effect_spread)
+    }
+
+    /// Number of links between a node and every node that Effect Spread can reach it from,
+    /// or `None` for nodes it can't reach it from.
+    /// Same as `SynthesisState::spread_distances`.
+    fn spread_distances(&self, node_index: usize) -> Vec<Option<u8>> {
+        let halo = |i: usize| self.recipe.nodes[i].halo;
+        let mut distances = vec![None; self.recipe.nodes.len()];
+        distances[node_index] = Some(0);
+        if halo(node_index) {
+            return distances;
+        }
+        let mut frontier = vec![node_index];
+        let mut distance = 0u8;
+        while !frontier.is_empty() {
+            distance = distance.saturating_add(1);
+            let mut next = Vec::<usize>::new();
+            for i in frontier {
+                for (j, parent) in self.parents.iter().enumerate() {
+                    let linked = *parent == Some(i) || self.parents[i] == Some(j);
+                    if linked && distances[j].is_none() && !halo(j) {
+                        distances[j] = Some(distance);
+                        next.push(j);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        distances
+    }
+
+    /// Total element value of ingredients that have a given element,
+    /// on a node or on other nodes whose Effect Spread reaches it.
+    /// Same as `SynthesisState::item_value` when `element` is the node's element.
+    fn element_value(
+        &self,
+        placements: &[Option<usize>],
+        node_index: usize,
+        element: Element,
+    ) -> u32 {
+        let distances = self.spread_distances(node_index);
+        self.ingredients
+            .iter()
+            .zip(placements)
+            .filter(|(item, placement)| {
+                let Some(distance) = placement.and_then(|i| distances[i]) else {
+                    return false;
+                };
+                let reaches = distance == 0
+                    || self
+                        .effect_spread(item)
+                        .is_some_and(|spread| spread.get() >= distance);
+                reaches && item.elements.contains(element)
+            })
+            .map(|(item, _)| item.element_value as u32)
+            .sum()
+    }
+
+    /// Apply the synthesis rules to one way of placing the ingredients.
+    /// Returns `None` if any ingredient ended up on a locked node, since the game won't allow that.
+    fn evaluate(&self, placements: &[Option<usize>]) -> Option<Outcome> {
+        let used: Vec<u16> = self
+            .ingredients
+            .iter()
+            .zip(placements)
+            .filter(|(_, placement)| placement.is_some())
+            .map(|(item, _)| item.quality)
+            .collect();
+        let quality = if used.is_empty() {
+            None
+        } else {
+            Some((used.iter().map(|q| *q as u32).sum::<u32>() / used.len() as u32) as u16)
+        };
+
+        let mut locked = vec![None::<bool>; self.recipe.nodes.len()];
+        for node_index in 0..locked.len() {
+            self.resolve_lock(placements, quality, &mut locked, node_index);
+        }
+
+        let mut nodes = Vec::<NodeOutcome>::new();
+        for (node_index, node) in self.recipe.nodes.values().enumerate() {
+            let locked = locked[node_index].expect("Lock not resolved");
+            if locked && placements.contains(&Some(node_index)) {
+                return None;
+            }
+
+            // Count filled tiers the same way as `SynthesisNode::active_effect`.
+            let value = self.element_value(placements, node_index, node.element);
+            let mut remaining = value;
+            let mut tiers = 0;
+            for effect in &node.effects {
+                let count = effect.count.get() as u32;
+                if remaining < count {
+                    break;
+                }
+                remaining -= count;
+                tiers += 1;
+            }
+
+            nodes.push(NodeOutcome {
+                locked,
+                value,
+                tiers,
+            });
+        }
+
+        Some(Outcome {
+            placements: placements.to_vec(),
+            quality,
+            nodes,
+        })
+    }
+
+    /// A node is locked if its parent is locked, if its parent doesn't have enough of the required element,
+    /// or if the quality so far is below the node's quality requirement.
+    /// A node with an elemental requirement but no parent can never be unlocked.
+    /// `items-check` reports link cycles; nodes in one count as locked here.
+    fn resolve_lock(
+        &self,
+        placements: &[Option<usize>],
+        quality: Option<u16>,
+        locked: &mut [Option<bool>],
+        node_index: usize,
+    ) -> bool {
+        if let Some(node_locked) = locked[node_index] {
+            return node_locked;
+        }
+        locked[node_index] = Some(true);
+        let (_, node) = self
+            .recipe
+            .nodes
+            .get_index(node_index)
+            .expect("Node index out of range");
+        let parent_locked = match self.parents[node_index] {
+            Some(parent_index) => self.resolve_lock(placements, quality, locked, parent_index),
+            None => false,
+        };
+        let elemental_locked = match (&node.elemental_requirement, self.parents[node_index]) {
+            (None, _) => false,
+            (Some(req), Some(parent_index)) => {
+                self.element_value(placements, parent_index, req.element) < req.count.get() as u32
+            }
+            (Some(_), None) => true,
+        };
+        let quality_locked = match node.quality_requirement {
+            None => false,
+            Some(req) => quality.unwrap_or(0) < req.get(),
+        };
+        let node_locked = parent_locked || elemental_locked || quality_locked;
+        locked[node_index] = Some(node_locked);
+        node_locked
+    }
+
+    /// Try every way of placing the ingredients, including leaving some out.
+    /// Returns the best outcome and, for each node, the most effect tiers it filled in any outcome.
+    fn run(&self) -> anyhow::Result<(Outcome, Vec<usize>)> {
+        let choices: Vec<Vec<Option<usize>>> = self
+            .ingredients
+            .iter()
+            .map(|item| {
+                std::iter::once(None)
+                    .chain(self.compatible_nodes(item).into_iter().map(Some))
+                    .collect()
+            })
+            .collect();
+        let total = choices
+            .iter()
+            .try_fold(1usize, |acc, c| acc.checked_mul(c.len()))
+            .filter(|total| *total <= MAX_PLACEMENTS);
+        if total.is_none() {
+            anyhow::bail!(
+                "Too many ways to place {count} ingredients, try fewer",
+                count = self.ingredients.len()
+            );
+        }
+
+        let mut best: Option<Outcome> = None;
+        let mut best_tiers = vec![0; self.recipe.nodes.len()];
+        let mut choice_indexes = vec![0; choices.len()];
+        loop {
+            let placements: Vec<Option<usize>> = choices
+                .iter()
+                .zip(&choice_indexes)
+                .map(|(c, i)| c[*i])
+                .collect();
+            if let Some(outcome) = self.evaluate(&placements) {
+                for (node_best, node) in best_tiers.iter_mut().zip(&outcome.nodes) {
+                    *node_best = (*node_best).max(node.tiers);
+                }
+                match &best {
+                    Some(best) if best.score() >= outcome.score() => {}
+                    _ => best = Some(outcome),
+                }
+            }
+
+            // Next combination, like counting with a different base for each digit.
+            let mut digit = 0;
+            loop {
+                if digit == choices.len() {
+                    // Leaving everything out is always valid, so there's always a best outcome.
+                    return Ok((best.expect("No valid outcome"), best_tiers));
+                }
+                choice_indexes[digit] += 1;
+                if choice_indexes[digit] < choices[digit].len() {
+                    break;
+                }
+                choice_indexes[digit] = 0;
+                digit += 1;
+            }
+        }
+    }
+
+    /// Quality of the synthesized item: the mean ingredient quality,
+    /// plus a bonus for the best Quality effect on any unlocked node.
+    /// Same as `SynthesisState::results`.
+    fn final_quality(&self, outcome: &Outcome) -> Option<u16> {
+        let quality_level = self
+            .recipe
+            .nodes
+            .values()
+            .zip(&outcome.nodes)
+            .filter(|(_, node_outcome)| !node_outcome.locked)
+            .filter_map(|(node, node_outcome)| {
+                node_outcome
+                    .tiers
+                    .checked_sub(1)
+                    .and_then(|i| node.effects.get(i))
+            })
+            .filter(|effect| matches!(effect.id, Effect::Quality))
+            .map(|effect| effect.level.get() as u16)
+            .max()
+            .unwrap_or(0);
+        outcome
+            .quality
+            .map(|quality| quality.saturating_add(quality_level * QUALITY_PER_EFFECT_LEVEL))
+    }
+
+    /// Describe the effect tiers filled on a node.
+    fn tier_name(&self, node_index: usize, tiers: usize) -> String {
+        let (_, node) = self
+            .recipe
+            .nodes
+            .get_index(node_index)
+            .expect("Node index out of range");
+        match tiers.checked_sub(1).and_then(|i| node.effects.get(i)) {
+            Some(effect) => format!("{id:?} Lv{level}", id = effect.id, level = effect.level),
+            None => "no effect".to_string(),
+        }
+    }
+
+    fn report(&self, material_id: &str, material: &Material) -> anyhow::Result<String> {
+        let (best, best_tiers) = self.run()?;
+
+        let mut lines = Vec::<String>::new();
+        lines.push(format!("{name} ({material_id})", name = material.name));
+        lines.push(format!(
+            "  Quality: {quality}",
+            quality = self
+                .final_quality(&best)
+                .map_or("no ingredients used".to_string(), |q| q.to_string())
+        ));
+
+        lines.push("  Nodes:".to_string());
+        for (node_index, ((node_id, node), outcome)) in
+            self.recipe.nodes.iter().zip(&best.nodes).enumerate()
+        {
+            let state = if outcome.locked {
+                let mut reasons = Vec::<String>::new();
+                if let Some(req) = &node.elemental_requirement {
+                    reasons.push(format!(
+                        "needs {count} {element:?} on parent",
+                        count = req.count,
+                        element = req.element
+                    ));
+                }
+                if let Some(quality) = &node.quality_requirement {
+                    reasons.push(format!("needs quality {quality}"));
+                }
+                if reasons.is_empty() {
+                    reasons.push("parent locked".to_string());
+                }
+                format!("locked ({reasons})", reasons = reasons.join(", "))
+            } else {
+                format!(
+                    "{value} {element:?}: {tier}",
+                    value = outcome.value,
+                    element = node.element,
+                    tier = self.tier_name(node_index, outcome.tiers)
+                )
+            };
+            lines.push(format!(
+                "    {node_id}: {state}; best possible: {best_tier}",
+                best_tier = self.tier_name(node_index, best_tiers[node_index])
+            ));
+        }
+
+        lines.push("  Ingredients:".to_string());
+        for (item, placement) in self.ingredients.iter().zip(&best.placements) {
+            let node_id = match placement {
+                Some(node_index) => self
+                    .recipe
+                    .nodes
+                    .get_index(*node_index)
+                    .expect("Node index out of range")
+                    .0
+                    .as_str(),
+                None => "(unused)",
+            };
+            lines.push(format!(
+                "    {material} {elements:?} {element_value} Q{quality}: {node_id}",
+                material = item.material,
+                elements = item.elements.iter().collect::<Vec<_>>(),
+                element_value = item.element_value,
+                quality = item.quality
+            ));
+        }
+
+        lines.push(String::new());
+        Ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Items {
+        serde_json::from_str(
+            r#"{"materials": {
+                "water": {"name": "Water", "icon": "water", "categories": ["water"]},
+                "gasoline": {"name": "Gasoline", "icon": "potion_dark", "categories": ["water", "fuel"]},
+                "red_flower": {"name": "Red Flower", "icon": "flower1", "categories": ["flowers"]},
+                "ember_flower": {"name": "Ember Flower", "icon": "flower1", "categories": ["flowers"], "effect_spread": 1},
+                "neutralizer": {"name": "Neutralizer", "icon": "test_tube", "categories": ["neutralizers"],
+                    "recipe": {
+                        "nodes": {
+                            "quality": {"grid_pos": [0, 0], "element": "ice", "input": {"material": "water"},
+                                "effects": [
+                                    {"id": "quality", "level": 1, "count": 1},
+                                    {"id": "quality", "level": 2, "count": 2},
+                                    {"id": "quality", "level": 3, "count": 3}
+                                ]},
+                            "fire_dmg_1": {"grid_pos": [1, 0], "element": "fire", "input": {"category": "flowers"},
+                                "effects": [{"id": "fire_dmg", "level": 1, "count": 2}]},
+                            "fire_dmg_2": {"grid_pos": [2, 0], "element": "fire", "input": {"category": "fuel"},
+                                "effects": [{"id": "fire_dmg", "level": 2, "count": 2}],
+                                "elemental_requirement": {"element": "fire", "count": 2},
+                                "quality_requirement": 50}
+                        },
+                        "links": [["quality", "fire_dmg_1"], ["fire_dmg_1", "fire_dmg_2"]]
+                    }}
+            }}"#,
+        )
+        .unwrap()
+    }
+
+    fn run(ingredients: &str) -> (Outcome, Vec<usize>) {
+        let items = items();
+        let ingredients: Vec<Item> = serde_json::from_str(ingredients).unwrap();
+        let recipe = items.materials["neutralizer"].recipe.as_ref().unwrap();
+        Simulation::new(&items, recipe, &ingredients)
+            .unwrap()
+            .run()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tiers() {
+        let (best, best_tiers) = run(
+            r#"[{"material": "water", "elements": ["ice"], "element_value": 3, "quality": 40}]"#,
+        );
+        assert_eq!(vec![Some(0)], best.placements);
+        assert_eq!(Some(40), best.quality);
+
+        assert_eq!(3, best.nodes[0].value);
+        assert_eq!(2, best.nodes[0].tiers);
+        assert!(best.nodes[2].locked);
+        assert_eq!(vec![2, 0, 0], best_tiers);
+    }
+
+    #[test]
+    fn test_elemental_requirement_unlocks() {
+        let (best, _) = run(r#"[
+                {"material": "red_flower", "elements": ["fire"], "element_value": 2, "quality": 60},
+                {"material": "gasoline", "elements": ["fire"], "element_value": 2, "quality": 80}
+            ]"#);
+        assert_eq!(vec![Some(1), Some(2)], best.placements);
+        assert_eq!(Some(70), best.quality);
+        assert!(!best.nodes[2].locked);
+        assert_eq!(1, best.nodes[2].tiers);
+    }
+
+    #[test]
+    fn test_quality_requirement_locks() {
+        let (best, best_tiers) = run(r#"[
+                {"material": "red_flower", "elements": ["fire"], "element_value": 2, "quality": 10},
+                {"material": "gasoline", "elements": ["fire"], "element_value": 2, "quality": 80},
+                {"material": "water", "elements": ["fire"], "element_value": 1, "quality": 10}
+            ]"#);
+        // Mean quality is 45 with the flower and gasoline, too low for the gasoline's node.
+        assert_eq!(vec![Some(1), None, None], best.placements);
+        assert!(best.nodes[2].locked);
+        assert_eq!(vec![0, 1, 0], best_tiers);
+    }
+
+    #[test]
+    fn test_final_quality() {
+        let items = items();
+        let ingredients: Vec<Item> = serde_json::from_str(
+            r#"[{"material": "water", "elements": ["ice"], "element_value": 3, "quality": 40}]"#,
+        )
+        .unwrap();
+        let recipe = items.materials["neutralizer"].recipe.as_ref().unwrap();
+        let simulation = Simulation::new(&items, recipe, &ingredients).unwrap();
+        let (best, _) = simulation.run().unwrap();
+        // Quality Lv2 adds 20.
+        assert_eq!(Some(60), simulation.final_quality(&best));
+    }
+
+    #[test]
+    fn test_effect_spread_and_halo() {
+        let mut items = items();
+        let ingredients: Vec<Item> = serde_json::from_str(
+            r#"[{"material": "ember_flower", "elements": ["fire", "ice"], "element_value": 2, "quality": 50}]"#,
+        )
+        .unwrap();
+        let placements = [Some(1)];
+
+        let recipe = items.materials["neutralizer"].recipe.as_ref().unwrap();
+        let simulation = Simulation::new(&items, recipe, &ingredients).unwrap();
+        assert_eq!(2, simulation.element_value(&placements, 0, Element::Ice));
+        assert_eq!(2, simulation.element_value(&placements, 1, Element::Fire));
+        assert_eq!(2, simulation.element_value(&placements, 2, Element::Fire));
+
+        let recipe = items.materials["neutralizer"].recipe.as_mut().unwrap();
+        recipe.nodes["fire_dmg_2"].halo = true;
+        let recipe = items.materials["neutralizer"].recipe.as_ref().unwrap();
+        let simulation = Simulation::new(&items, recipe, &ingredients).unwrap();
+        assert_eq!(2, simulation.element_value(&placements, 0, Element::Ice));
+        assert_eq!(0, simulation.element_value(&placements, 2, Element::Fire));
+    }
+}
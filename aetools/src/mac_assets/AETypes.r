@@ -1,5 +1,16 @@
 /** Atelier Esri custom resource types. */
 
+/* Tiled custom properties, as an array with the given label. */
+#define TILED_PROPERTIES(label) \
+    unsigned integer = $$CountOf(label); \
+    array label { \
+        pstring;    /* name */ \
+        align word; \
+        integer;    /* type: 0 bool, 1 int, 2 float, 3 color, 4 string, 5 file, 6 object, 7 enum */ \
+        pstring;    /* value, as text */ \
+        align word; \
+    }
+
 /* ResEdit template resource. (Not actually ours, but not in Apple RIncludes.) */
 type 'TMPL' {
     array fields {
@@ -88,6 +99,24 @@ type 'TSX ' {
     integer;    /* image height (in pixels) */
     integer;    /* image PICT resource ID */
     integer;    /* mask PICT resource ID. 0 indicates no mask. */
+
+    unsigned integer = $$CountOf(tile_properties);
+    array tile_properties {
+        integer;    /* tile ID within tileset */
+
+        TILED_PROPERTIES(properties);
+    };
+
+    unsigned integer = $$CountOf(tile_animations);
+    array tile_animations {
+        integer;    /* tile ID within tileset of the animated tile */
+
+        unsigned integer = $$CountOf(frames);
+        array frames {
+            integer;            /* tile ID within tileset */
+            unsigned integer;   /* duration (in 60 Hz ticks) */
+        };
+    };
 };
 
 /* Template for tilesets. */
@@ -120,6 +149,26 @@ resource 'TMPL' (130, "TSX ") {
 	}
 };
 
+/* Object list: objects from a tilemap object layer, in layer order. Coordinates are in map pixels. */
+type 'OBJ#' {
+    unsigned integer = $$CountOf(objects);
+    array objects {
+        pstring;    /* name */
+        align word;
+        pstring;    /* class */
+        align word;
+        integer rectangle = 0, ellipse = 1, point = 2, polyline = 3, polygon = 4;    /* shape */
+        rect;       /* bounds */
+
+        unsigned integer = $$CountOf(points);
+        array points {
+            point;  /* vertex, for polylines and polygons */
+        };
+
+        TILED_PROPERTIES(properties);
+    };
+};
+
 /* Tilemap. */
 type 'TMX ' {
     unsigned integer = $$CountOf(tileset_resource_ids);
@@ -133,6 +182,10 @@ type 'TMX ' {
         align word;
         integer;    /* width (in tiles) */
         integer;    /* height (in tiles) */
+        integer;    /* x offset (in pixels) */
+        integer;    /* y offset (in pixels) */
+        unsigned byte;  /* opacity (0 is transparent, 255 is opaque) */
+        align word;
 
         unsigned integer = $$CountOf(tiles);
         array tiles {
@@ -143,6 +196,24 @@ type 'TMX ' {
             unsigned byte;          /* tileset ordinal (index + 1; 0 indicates an empty tile position) */
             unsigned integer;       /* tile ID within tileset */
         };
+
+        TILED_PROPERTIES(layer_properties);
+    };
+
+    unsigned integer = $$CountOf(image_layers);
+    array image_layers {
+        pstring;    /* name */
+        align word;
+        integer;    /* image PICT resource ID */
+        integer;    /* mask PICT resource ID. 0 indicates no mask. */
+        integer;    /* x offset (in pixels) */
+        integer;    /* y offset (in pixels) */
+        integer;    /* x parallax (in 256ths: 256 scrolls with the map, 0 doesn't scroll) */
+        integer;    /* y parallax (in 256ths) */
+        unsigned byte;  /* opacity (0 is transparent, 255 is opaque) */
+        align word;
+
+        TILED_PROPERTIES(image_layer_properties);
     };
 
     unsigned integer = $$CountOf(region_groups);
@@ -150,7 +221,12 @@ type 'TMX ' {
         pstring;    /* name */
         align word;
         integer;    /* RGN# region list resource ID */
+        integer;    /* OBJ# object list resource ID */
+
+        TILED_PROPERTIES(region_group_properties);
     };
+
+    TILED_PROPERTIES(map_properties);
 };
 
 /* Template for tilemaps. */
@@ -1,14 +1,17 @@
 use crate::assets::{asset_group_foreach, AssetGroup};
-use crate::mac_assets::{MaskedPictAsset, RGNAsset};
+use crate::cinematic_script::{
+    compile_script, Characters, CinematicCharacter, CinematicCharacterSlot, CinematicChoice,
+    CinematicCommand, CinematicComparison, CinematicCondition, CinematicTables, Diagnostics,
+};
+use crate::mac_assets::{string_literal, MaskedPictAsset, RGNAsset};
 use anyhow;
 use convert_case::{Case, Casing};
 use lazy_static::lazy_static;
-use literally::bmap;
 use regex::Regex;
 use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 
 const CINEMATIC_ASSETS: &[AssetGroup] = &[AssetGroup {
@@ -22,8 +25,11 @@ pub fn compile_cinematics(
     rgn_assets: &Vec<RGNAsset>,
     build_dir: &Path,
 ) -> anyhow::Result<PathBuf> {
-    let (character_mood_sprite_indexes, background_resource_ids) =
-        build_maps(masked_pict_assets, rgn_assets)?;
+    let tables = CinematicTables::load(asset_base_dir)?;
+    let asset_maps = build_maps(&tables.characters, masked_pict_assets, rgn_assets)?;
+
+    // Report mistakes in every script, not just the first one with any.
+    let mut script_errors = Vec::<String>::new();
 
     let glob_match_fn = |_group_name: &str,
                          group_dir: &Path,
@@ -33,13 +39,13 @@ pub fn compile_cinematics(
      -> anyhow::Result<()> {
         let mut dst = group_dir.join(base_name.to_string_lossy().to_case(Case::UpperCamel));
         dst.set_extension("cpp");
-        translate_script(
-            &character_mood_sprite_indexes,
-            &background_resource_ids,
-            base_name,
-            src,
-            &dst,
-        )
+        if let Err(e) = translate_script(&tables, &asset_maps, base_name, src, &dst) {
+            match e.downcast::<Diagnostics>() {
+                Ok(diagnostics) => script_errors.push(diagnostics.to_string()),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     };
 
     let group_fn = |_group_name: &str, _group_dir: &Path| -> anyhow::Result<()> { Ok(()) };
@@ -52,18 +58,35 @@ pub fn compile_cinematics(
         group_fn,
     )?;
 
+    if !script_errors.is_empty() {
+        anyhow::bail!("{}", script_errors.join("\n\n"));
+    }
+
     Ok(PathBuf::new())
 }
 
 lazy_static! {
     static ref CHARACTER_MOOD_SPRITE: Regex = Regex::new(r"^avatar_([A-Za-z]+)_(.+)$")
         .expect("Couldn't compile CHARACTER_MOOD_SPRITE regex");
+    static ref ITEM_SPRITE: Regex =
+        Regex::new(r"^item_(.+)$").expect("Couldn't compile ITEM_SPRITE regex");
+}
+
+/// Where the Mac edition keeps the things that scripts refer to.
+struct AssetMaps {
+    /// Sprite index by character ID and mood.
+    character_mood_sprite_indexes: BTreeMap<(usize, String), usize>,
+    /// Sprite index by item icon slice name.
+    item_sprite_indexes: BTreeMap<String, usize>,
+    /// `PICT` resource ID by background name.
+    background_resource_ids: BTreeMap<String, i16>,
 }
 
 fn build_maps(
+    characters: &Characters,
     masked_pict_assets: &Vec<(String, Vec<MaskedPictAsset>)>,
     rgn_assets: &Vec<RGNAsset>,
-) -> anyhow::Result<(BTreeMap<(usize, String), usize>, BTreeMap<String, i16>)> {
+) -> anyhow::Result<AssetMaps> {
     let sprite_sheet_assets = masked_pict_assets
         .iter()
         .map(|(group_name, group_assets)| (group_name, group_assets))
@@ -75,15 +98,21 @@ fn build_maps(
     }
 
     let mut character_mood_sprite_indexes: BTreeMap<(usize, String), usize> = BTreeMap::new();
+    let mut item_sprite_indexes: BTreeMap<String, usize> = BTreeMap::new();
     for (index, (name, _)) in rgn_assets.first().unwrap().regions.iter().enumerate() {
+        if let Some(captures) = ITEM_SPRITE.captures(name) {
+            let (_, [icon]) = captures.extract();
+            item_sprite_indexes.insert(icon.to_string(), index);
+            continue;
+        }
         let Some(captures) = CHARACTER_MOOD_SPRITE.captures(name) else {
             continue;
         };
         let (_, [name, mood]) = captures.extract();
-        let Some(id) = CHARACTER_IDS.get(name.to_uppercase().as_str()) else {
-            anyhow::bail!("Couldn't find ID for character: {name}");
+        let Some(id) = characters.by_portrait(name) else {
+            anyhow::bail!("Couldn't find character with portrait: {name}");
         };
-        character_mood_sprite_indexes.insert((*id, mood.to_string()), index);
+        character_mood_sprite_indexes.insert((id, mood.to_string()), index);
     }
 
     let background_resource_ids: BTreeMap<String, i16> = masked_pict_assets
@@ -98,75 +127,17 @@ fn build_maps(
     // TODO: we currently assume that the mask_pict_resource_id is unused,
     //  but should enforce this at the type level by having regular PICTs
 
-    Ok((character_mood_sprite_indexes, background_resource_ids))
-}
-
-// TODO: copied from MacOS/Material.cpp but should be in a resource
-const MATERIAL_NAMES: &[&str] = &[
-    "Bacon",
-    "Bud",
-    "Crystal",
-    "Dragon Eye",
-    "Dunkelheit",
-    "Elerium",
-    "Feather",
-    "Flower 1",
-    "Flower 2",
-    "Grapes",
-    "Grass",
-    "Gravistone",
-    "Herb",
-    "Leaf Down",
-    "Leaf Triple",
-    "Leaf Up",
-    "Lump",
-    "Mushroom 1",
-    "Mushroom 2",
-    "Copper Ore",
-    "Iron Ore",
-    "Silver Ore",
-    "Stygium Ore",
-    "Titanium Ore",
-    "Page",
-    "Palm",
-    "Pendeloque",
-    "Pods",
-    "Puniball",
-    "Giant Puniball",
-    "Rock",
-    "Sand",
-    "Seaweed 1",
-    "Seaweed 2",
-    "Spider",
-    "Spirit",
-    "Steak",
-    "Sulfur",
-    "Uni",
-    "Water",
-    "Wood",
-    "Worm",
-    "Copper Ingot",
-];
-
-lazy_static! {
-    // TODO: Character data doesn't exist in C++ yet, should be a resource anyway
-    static ref CHARACTER_IDS: BTreeMap<&'static str, usize> = bmap! {
-        "ESRI" => 0usize,
-        "ALLIE" => 1usize,
-        "SAE" => 2usize,
-    };
+    Ok(AssetMaps {
+        character_mood_sprite_indexes,
+        item_sprite_indexes,
+        background_resource_ids,
+    })
 }
 
 trait ToCPP {
     fn to_cpp(&self) -> String;
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct CinematicCharacter {
-    id: usize,
-    mood: usize,
-}
-
 impl ToCPP for CinematicCharacter {
     fn to_cpp(&self) -> String {
         format!(
@@ -177,24 +148,6 @@ impl ToCPP for CinematicCharacter {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-enum CinematicCharacterSlot {
-    Left = 0,
-    Right = 1,
-}
-
-impl TryFrom<&str> for CinematicCharacterSlot {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(match value {
-            "left" => Self::Left,
-            "right" => Self::Right,
-            _ => anyhow::bail!("Invalid character slot: {value}"),
-        })
-    }
-}
-
 impl ToCPP for CinematicCharacterSlot {
     fn to_cpp(&self) -> String {
         match self {
@@ -204,55 +157,29 @@ impl ToCPP for CinematicCharacterSlot {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum CinematicCommand {
-    CinematicCommandCommit,
-    CinematicCommandSetCharacter {
-        slot: CinematicCharacterSlot,
-        character: CinematicCharacter,
-    },
-    CinematicCommandSetMood {
-        slot: CinematicCharacterSlot,
-        mood: usize,
-    },
-    CinematicCommandClearCharacter {
-        slot: CinematicCharacterSlot,
-    },
-    CinematicCommandSetSpeaker {
-        slot: CinematicCharacterSlot,
-    },
-    CinematicCommandClearSpeaker,
-    CinematicCommandSetText {
-        text: String,
-    },
-    CinematicCommandClearText,
-    CinematicCommandSetBackground {
-        id: usize,
-    },
-    CinematicCommandClearBackground,
-    CinematicCommandSetMaterial {
-        id: usize,
-    },
-    CinematicCommandClearMaterial,
+impl ToCPP for CinematicComparison {
+    fn to_cpp(&self) -> String {
+        format!("CinematicComparison::{self:?}")
+    }
 }
 
-struct EncodeAsMacRoman<'a>(&'a str);
+impl ToCPP for CinematicCondition {
+    fn to_cpp(&self) -> String {
+        format!(
+            "CinematicCondition{{.flag={flag}, .comparison={comparison_cpp}, .value={value}}}",
+            flag = self.flag,
+            comparison_cpp = self.comparison.to_cpp(),
+            value = self.value
+        )
+    }
+}
 
-impl<'a> ToCPP for EncodeAsMacRoman<'a> {
+impl ToCPP for CinematicChoice {
     fn to_cpp(&self) -> String {
-        // TODO: actually encode all the characters
         format!(
-            r#""{escaped}""#,
-            escaped = self
-                .0
-                .chars()
-                .map(|c| match c {
-                    '\\' => r"\\".to_string(),
-                    '"' => r#"\""#.to_string(),
-                    '…' => r"\xc9".to_string(),
-                    _ => c.to_string(),
-                })
-                .collect::<String>()
+            "CinematicChoice{{.text={text_cpp}, .target={target}}}",
+            text_cpp = string_literal(&self.text),
+            target = self.target
         )
     }
 }
@@ -292,7 +219,7 @@ impl ToCPP for CinematicCommand {
             CinematicCommand::CinematicCommandSetText { text } => {
                 format!(
                     "CinematicCommandSetText{{.text={text_cpp}}}",
-                    text_cpp = EncodeAsMacRoman(text).to_cpp()
+                    text_cpp = string_literal(text)
                 )
             }
             CinematicCommand::CinematicCommandClearText => {
@@ -310,173 +237,97 @@ impl ToCPP for CinematicCommand {
             CinematicCommand::CinematicCommandClearMaterial => {
                 "CinematicCommandClearMaterial{}".to_string()
             }
+            CinematicCommand::CinematicCommandSetFlag { flag, value } => {
+                format!("CinematicCommandSetFlag{{.flag={flag}, .value={value}}}")
+            }
+            CinematicCommand::CinematicCommandAddFlag { flag, amount } => {
+                format!("CinematicCommandAddFlag{{.flag={flag}, .amount={amount}}}")
+            }
+            CinematicCommand::CinematicCommandJump { target } => {
+                format!("CinematicCommandJump{{.target={target}}}")
+            }
+            CinematicCommand::CinematicCommandJumpUnless { condition, target } => {
+                format!(
+                    "CinematicCommandJumpUnless{{.condition={condition_cpp}, .target={target}}}",
+                    condition_cpp = condition.to_cpp()
+                )
+            }
+            CinematicCommand::CinematicCommandChoice { options } => {
+                format!(
+                    "CinematicCommandChoice{{.options={{{options_cpp}}}}}",
+                    options_cpp = options
+                        .iter()
+                        .map(|option| option.to_cpp())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            CinematicCommand::CinematicCommandPanCamera { x, y, frames } => {
+                format!("CinematicCommandPanCamera{{.x={x}, .y={y}, .frames={frames}}}")
+            }
+            CinematicCommand::CinematicCommandShakeCamera { magnitude, frames } => {
+                format!("CinematicCommandShakeCamera{{.magnitude={magnitude}, .frames={frames}}}")
+            }
         }
     }
 }
 
-lazy_static! {
-    static ref SCRIPT_COMMENT: Regex =
-        Regex::new(r"^#").expect("Couldn't compile SCRIPT_COMMENT regex");
-    static ref SCRIPT_SET: Regex = Regex::new(r"^!set (background|material|left|right) (.+)$")
-        .expect("Couldn't compile SCRIPT_SET regex");
-    static ref SCRIPT_UNSET: Regex =
-        Regex::new(r"^!unset (background|material|speaker|text|left|right)$")
-            .expect("Couldn't compile SCRIPT_UNSET regex");
-    static ref SCRIPT_SPEAKER: Regex =
-        Regex::new(r"^([A-Z0-9_]+):$").expect("Couldn't compile SCRIPT_SPEAKER regex");
-    static ref SCRIPT_MOOD: Regex =
-        Regex::new(r"^\[([a-z0-9_]+)\]$").expect("Couldn't compile SCRIPT_MOOD regex");
-}
-
 fn translate_script(
-    character_mood_sprite_indexes: &BTreeMap<(usize, String), usize>,
-    background_resource_ids: &BTreeMap<String, i16>,
+    tables: &CinematicTables,
+    asset_maps: &AssetMaps,
     base_name: &OsStr,
     input: &Path,
     output: &Path,
 ) -> anyhow::Result<()> {
-    let mut set_character_slot: Option<CinematicCharacterSlot> = None;
-    let mut set_character_id: Option<usize> = None;
-    let mut characters: BTreeMap<CinematicCharacterSlot, CinematicCharacter> = BTreeMap::new();
-    let mut speaker: Option<CinematicCharacterSlot> = None;
-
-    let mut script: Vec<CinematicCommand> = Vec::new();
-
     let lookup_mood = |id: usize, name: &str| -> anyhow::Result<usize> {
-        let Some(sprite_index) = character_mood_sprite_indexes.get(&(id, name.to_string())) else {
-            let Some(character_name) =
-                CHARACTER_IDS
-                    .iter()
-                    .find_map(|(character_name, character_id)| {
-                        if *character_id == id {
-                            Some(character_name)
-                        } else {
-                            None
-                        }
-                    })
-            else {
-                anyhow::bail!("Couldn't find mood for unknown character with ID {id}: {name}");
-            };
-            anyhow::bail!("Couldn't find mood for character {character_name}: {name}");
+        let Some(sprite_index) = asset_maps
+            .character_mood_sprite_indexes
+            .get(&(id, name.to_string()))
+        else {
+            anyhow::bail!(
+                "Couldn't find mood for character {character}: {name}",
+                character = tables.characters.character(id)?.id
+            );
         };
         Ok(*sprite_index)
     };
 
-    for result in BufReader::new(File::open(input)?).lines() {
-        let line = result?;
-        if line.is_empty() || SCRIPT_COMMENT.is_match(&line) {
-            continue;
-        } else if let Some(captures) = SCRIPT_SET.captures(&line) {
-            let (_, [slot, name]) = captures.extract();
-            match slot {
-                "background" => {
-                    let Some(resource_id) = background_resource_ids.get(name) else {
-                        anyhow::bail!("Couldn't find background: {name}");
-                    };
-                    script.push(CinematicCommand::CinematicCommandSetBackground {
-                        id: *resource_id as usize,
-                    });
-                }
-                "material" => {
-                    let Some(material_index) = MATERIAL_NAMES
-                        .iter()
-                        .position(|material_name| *material_name == name)
-                    else {
-                        anyhow::bail!("Couldn't find material: {name}");
-                    };
-                    script
-                        .push(CinematicCommand::CinematicCommandSetMaterial { id: material_index });
-                }
-                "left" | "right" => {
-                    if name != "…" {
-                        anyhow::bail!("!set {slot} doesn't take a name, only '…', but got {name}");
-                    }
-                    set_character_slot = Some(CinematicCharacterSlot::try_from(slot)?);
-                }
-                _ => anyhow::bail!("Unknown slot for !set: {slot}"),
-            }
-        } else if let Some(captures) = SCRIPT_UNSET.captures(&line) {
-            let (_, [slot]) = captures.extract();
-            match slot {
-                "background" => {
-                    script.push(CinematicCommand::CinematicCommandClearBackground);
-                }
-                "material" => {
-                    script.push(CinematicCommand::CinematicCommandClearMaterial);
-                }
-                "speaker" => {
-                    script.push(CinematicCommand::CinematicCommandClearSpeaker);
-                }
-                "text" => {
-                    script.push(CinematicCommand::CinematicCommandClearText);
-                }
-                "left" | "right" => {
-                    let character_slot = CinematicCharacterSlot::try_from(slot)?;
-                    if let Some(speaker_slot) = speaker {
-                        if character_slot == speaker_slot {
-                            script.push(CinematicCommand::CinematicCommandClearSpeaker);
-                        }
-                        speaker = None;
-                    }
-                    script.push(CinematicCommand::CinematicCommandClearCharacter {
-                        slot: character_slot,
-                    });
-                }
-                _ => anyhow::bail!("Unknown slot for !unset: {slot}"),
-            }
-        } else if let Some(captures) = SCRIPT_SPEAKER.captures(&line) {
-            let (_, [name]) = captures.extract();
-            let Some(id) = CHARACTER_IDS.get(name) else {
-                anyhow::bail!("Couldn't find character: {name}");
-            };
-            let id = *id;
-            if set_character_slot.is_some() {
-                set_character_id = Some(id);
-            } else {
-                let Some((slot, _)) = characters.iter().find(|(_, character)| character.id == id)
-                else {
-                    anyhow::bail!("Character isn't on stage, can't set them as speaker: {name}");
-                };
-                let slot = *slot;
-                script.push(CinematicCommand::CinematicCommandSetSpeaker { slot });
-                speaker = Some(slot);
-            }
-        } else if let Some(captures) = SCRIPT_MOOD.captures(&line) {
-            let (_, [name]) = captures.extract();
-            if let Some(slot) = set_character_slot {
-                let Some(id) = set_character_id else {
-                    anyhow::bail!("Can't set mood for unknown character: {name}");
-                };
-                let mood = lookup_mood(id, name)?;
-                let character = CinematicCharacter { id, mood };
-                script.push(CinematicCommand::CinematicCommandSetCharacter { slot, character });
-                characters.insert(slot, character);
-                set_character_slot = None;
-                set_character_id = None;
-                speaker = Some(slot);
-            } else {
-                let Some(slot) = speaker else {
-                    anyhow::bail!("Can't set mood for speaker if nobody is on stage");
-                };
-                let Some(character) = characters.get_mut(&slot) else {
-                    anyhow::bail!("Speaker is slot {slot:?} but nobody is on stage there");
+    let lookup_background = |name: &str| -> anyhow::Result<usize> {
+        let Some(resource_id) = asset_maps.background_resource_ids.get(name) else {
+            anyhow::bail!("Couldn't find background: {name}");
+        };
+        Ok(*resource_id as usize)
+    };
+
+    let script = compile_script(input, tables, lookup_mood, lookup_background)?;
+    // The Mac edition shows materials by their icon, so point material commands at icon sprites,
+    // the same way moods point at portrait sprites.
+    let script = script
+        .into_iter()
+        .map(|command| match command {
+            CinematicCommand::CinematicCommandSetMaterial { id } => {
+                let material = tables.materials.material(id)?;
+                let Some(sprite_index) = asset_maps.item_sprite_indexes.get(&material.icon) else {
+                    anyhow::bail!(
+                        "{input}: couldn't find icon for material {material_id}: item_{icon}",
+                        input = input.display(),
+                        material_id = material.id,
+                        icon = material.icon
+                    );
                 };
-                let mood = lookup_mood(character.id, name)?;
-                script.push(CinematicCommand::CinematicCommandSetMood { slot, mood });
-                character.mood = mood;
+                Ok(CinematicCommand::CinematicCommandSetMaterial { id: *sprite_index })
             }
-        } else {
-            script.push(CinematicCommand::CinematicCommandSetText {
-                text: line.to_string(),
-            });
-            script.push(CinematicCommand::CinematicCommandCommit);
-        }
-    }
-
-    if script.last() != Some(&CinematicCommand::CinematicCommandCommit) {
-        script.push(CinematicCommand::CinematicCommandCommit);
-    }
+            command => Ok(command),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    write_script_cpp(base_name, &script, output)
+}
 
+fn write_script_cpp(
+    base_name: &OsStr,
+    script: &[CinematicCommand],
+    output: &Path,
+) -> anyhow::Result<()> {
     let mut hpp_path = output.to_path_buf();
     hpp_path.set_extension("hpp");
 
@@ -1,13 +1,14 @@
 mod cinematic;
-mod tiled;
+pub(crate) mod tiled;
+pub(crate) mod tiled_properties;
 
 use crate::assets::{asset_group_foreach, IMAGE_ASSETS, SPRITE_ASSETS};
 use crate::ext::{aseprite, imagemagick};
 use crate::fsutil::{delete_dir, ensure_dir};
 use crate::mac::resource::TypedResource;
-use crate::mac::OSType;
+use crate::mac::{mac_roman, OSType};
 use crate::mac_assets::cinematic::compile_cinematics;
-use crate::mac_assets::tiled::{compile_maps, TMXAsset, TSXAsset};
+use crate::mac_assets::tiled::{compile_maps, OBJAsset, TMXAsset, TSXAsset};
 use aetools_derive::cpp_codegen;
 use anyhow;
 use convert_case::{Case, Casing};
@@ -27,6 +28,28 @@ use std::process::Command;
 
 type ResourceID = i16;
 
+/// Quoted MacRoman string literal that means the same thing in Rez and C++.
+/// Anything outside printable ASCII is an octal escape, which both languages read the same way,
+/// and characters that MacRoman doesn't have become `?`.
+pub(crate) fn string_literal(text: &str) -> String {
+    let mut acc = String::from('"');
+    for c in text.chars() {
+        match c {
+            '\\' => acc.push_str(r"\\"),
+            '"' => acc.push_str(r#"\""#),
+            // So that `??` can't start a trigraph.
+            '?' => acc.push_str(r"\?"),
+            ' '..='~' => acc.push(c),
+            _ => acc.push_str(&format!(
+                r"\{byte:03o}",
+                byte = mac_roman(c).unwrap_or(b'?')
+            )),
+        }
+    }
+    acc.push('"');
+    acc
+}
+
 /// See https://preterhuman.net/macstuff/insidemac/MoreToolbox/MoreToolbox-27.html#MARKER-9-196
 #[derive(Debug, Default)]
 pub struct ResourceIDGenerator {
@@ -120,7 +143,7 @@ pub fn generate(asset_base_dir: &Path, build_dir: &Path) -> anyhow::Result<()> {
     masked_pict_asset_groups.extend(sprite_sheet_masked_pict_asset_groups);
     rgn_asset_groups.push(("sprite_sheet".to_string(), sprite_sheet_rgn_assets.clone()));
 
-    let (map_masked_pict_assets, tsx_assets, map_rgn_assets, tmx_assets) =
+    let (map_masked_pict_assets, tsx_assets, map_rgn_assets, obj_assets, tmx_assets) =
         compile_maps(asset_base_dir, build_dir, &mut resource_id_generator)?;
     masked_pict_asset_groups.push(("tileset".to_string(), map_masked_pict_assets));
     rgn_asset_groups.push(("map".to_string(), map_rgn_assets));
@@ -131,6 +154,7 @@ pub fn generate(asset_base_dir: &Path, build_dir: &Path) -> anyhow::Result<()> {
         &rgn_asset_groups,
         &ninepatch_assets,
         &tsx_assets,
+        &obj_assets,
         &tmx_assets,
     )?;
 
@@ -314,6 +338,19 @@ pub struct QDRect {
     pub right: i16,
 }
 
+/// QuickDraw `Point`.
+#[derive(Debug, Clone)]
+pub struct QDPoint {
+    pub v: i16,
+    pub h: i16,
+}
+
+impl QDPoint {
+    fn rez(&self) -> String {
+        format!("{{{v}, {h}}}", v = self.v, h = self.h)
+    }
+}
+
 pub fn hpp() -> anyhow::Result<()> {
     println!("{hpp}", hpp = QDRect::hpp());
     Ok(())
@@ -745,6 +782,7 @@ fn generate_rez_and_header_files(
     rgn_asset_groups: &Vec<(String, Vec<RGNAsset>)>,
     ninepatch_assets: &Vec<NinePatchAsset>,
     tsx_assets: &Vec<TSXAsset>,
+    obj_assets: &Vec<OBJAsset>,
     tmx_assets: &Vec<TMXAsset>,
 ) -> anyhow::Result<(PathBuf, PathBuf)> {
     // Copy custom resource types file as is.
@@ -860,6 +898,17 @@ fn generate_rez_and_header_files(
         write!(header, "\n")?;
     }
 
+    for obj_asset in obj_assets {
+        write!(rez, "/* tilemap object lists */\n\n")?;
+        write!(header, "/* tilemap object lists */\n\n")?;
+
+        write!(rez, "{src}", src = obj_asset.rez())?;
+        write!(header, "{src}", src = obj_asset.header())?;
+
+        write!(rez, "\n")?;
+        write!(header, "\n")?;
+    }
+
     for tmx_asset in tmx_assets {
         write!(rez, "/* tilemaps */\n\n")?;
         write!(header, "/* tilemaps */\n\n")?;
@@ -4,8 +4,12 @@ use crate::assets::{asset_group_foreach, AssetGroup};
 use crate::fsutil::ensure_dir;
 use crate::mac::resource::TypedResource;
 use crate::mac::OSType;
+use crate::mac_assets::tiled_properties::{
+    self, new_loader, MapProperties, Properties, PropertyTypes, TiledLoader, PROJECT_FILE,
+};
 use crate::mac_assets::{
-    png_to_pict, MaskedPictAsset, QDRect, RGNAsset, ResourceID, ResourceIDGenerator, Resourceful,
+    png_to_pict, MaskedPictAsset, QDPoint, QDRect, RGNAsset, ResourceID, ResourceIDGenerator,
+    Resourceful,
 };
 use convert_case::{Case, Casing};
 use std::collections::btree_map::Entry;
@@ -17,8 +21,8 @@ use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tiled::{
-    FiniteTileLayer, LayerTileData, LayerType, Loader, ObjectLayer, ObjectShape, Orientation,
-    TileLayer, Tileset,
+    ChunkData, ImageLayer, Layer, LayerTileData, LayerType, Map, ObjectData, ObjectLayer,
+    ObjectShape, Orientation, TileLayer, Tileset,
 };
 
 const TILEMAP_ASSETS: &[AssetGroup] = &[AssetGroup {
@@ -34,10 +38,11 @@ pub fn compile_maps(
     Vec<MaskedPictAsset>,
     Vec<TSXAsset>,
     Vec<RGNAsset>,
+    Vec<OBJAsset>,
     Vec<TMXAsset>,
 )> {
     // Map from canonicalized absolute path to image asset.
-    // Used for de-duping image data used by multiple tilesets.
+    // Used for de-duping image data used by multiple tilesets and image layers.
     let mut tileset_image_assets_by_path = BTreeMap::<PathBuf, MaskedPictAsset>::new();
 
     // Map from tileset name to tileset asset.
@@ -47,15 +52,18 @@ pub fn compile_maps(
 
     let mut tilemap_rgn_assets = Vec::<RGNAsset>::new();
 
+    let mut tilemap_obj_assets = Vec::<OBJAsset>::new();
+
     let mut tilemap_assets = Vec::<TMXAsset>::new();
 
     // While we could have more than one group of maps,
-    // tilesets and tileset images may be shared arbitrarily,
+    // tilesets, tileset images, and image layer images may be shared arbitrarily,
     // so they'll all go in one group directory.
     let tileset_group_dir = build_dir.join("tileset");
     ensure_dir(&tileset_group_dir)?;
 
-    let mut loader = Loader::new();
+    let property_types = PropertyTypes::load(&asset_base_dir.join(PROJECT_FILE))?;
+    let mut loader = new_loader();
 
     // Discover all tilemaps.
     let glob_match_fn = |_group_name: &str,
@@ -70,8 +78,10 @@ pub fn compile_maps(
             &mut tileset_image_assets_by_path,
             &mut tileset_assets_by_name,
             &mut tilemap_rgn_assets,
+            &mut tilemap_obj_assets,
             &tileset_group_dir,
             &mut loader,
+            &property_types,
             src,
             base_name,
         )?;
@@ -106,6 +116,7 @@ pub fn compile_maps(
         tileset_image_assets,
         tileset_assets,
         tilemap_rgn_assets,
+        tilemap_obj_assets,
         tilemap_assets,
     ))
 }
@@ -132,13 +143,18 @@ fn codegen(
             write!(hpp, "#pragma once\n")?;
             write!(hpp, "\n")?;
             write!(hpp, "#include <cstdint>\n")?;
+            write!(hpp, "#include <limits>\n")?;
             write!(hpp, "#include <optional>\n")?;
+            write!(hpp, "#include <string>\n")?;
+            write!(hpp, "#include <variant>\n")?;
+            write!(hpp, "#include <vector>\n")?;
             write!(hpp, "\n")?;
             write!(hpp, "#include \"Resource.hpp\"\n")?;
             write!(hpp, "\n")?;
             write!(hpp, "namespace AtelierEsri {{\n")?;
             write!(hpp, "\n")?;
 
+            write!(hpp, "{src}\n", src = tiled_properties::HPP)?;
             write!(hpp, "{src}\n", src = TSXAsset::HPP)?;
 
             for tileset_asset in tileset_assets {
@@ -187,9 +203,11 @@ fn codegen(
             write!(hpp, "#pragma once\n")?;
             write!(hpp, "\n")?;
             write!(hpp, "#include <cstdint>\n")?;
+            write!(hpp, "#include <string>\n")?;
             write!(hpp, "#include <vector>\n")?;
             write!(hpp, "\n")?;
             write!(hpp, "#include \"Resource.hpp\"\n")?;
+            write!(hpp, "#include \"TSXData.hpp\"\n")?;
             write!(hpp, "\n")?;
             write!(hpp, "namespace AtelierEsri {{\n")?;
             write!(hpp, "\n")?;
@@ -239,23 +257,39 @@ fn get_tileset_image_asset(
     tileset_group_dir: &Path,
     tileset: &Arc<Tileset>,
 ) -> anyhow::Result<MaskedPictAsset> {
-    let image_path = tileset
+    let image_path = &tileset
         .image
         .as_ref()
         .ok_or(anyhow::anyhow!(
             "No image for tileset {tileset}",
             tileset = tileset.name
         ))?
-        .source
-        .clone();
+        .source;
+
+    get_image_asset(
+        build_dir,
+        resource_id_generator,
+        tileset_image_assets_by_path,
+        tileset_group_dir,
+        image_path,
+    )
+}
 
+/// Convert tileset or image layer image to masked PICT asset, or retrieve it if already converted.
+fn get_image_asset(
+    build_dir: &Path,
+    resource_id_generator: &mut ResourceIDGenerator,
+    tileset_image_assets_by_path: &mut BTreeMap<PathBuf, MaskedPictAsset>,
+    tileset_group_dir: &Path,
+    image_path: &Path,
+) -> anyhow::Result<MaskedPictAsset> {
     let canonical_image_path = image_path.canonicalize()?;
     let tileset_image_asset = match tileset_image_assets_by_path.entry(canonical_image_path) {
         Entry::Occupied(entry) => entry.get().clone(),
 
         Entry::Vacant(entry) => {
             if image_path.extension() != Some(&OsString::from("png")) {
-                anyhow::bail!("Only PNG tileset images are supported right now");
+                anyhow::bail!("Only PNG tileset and image layer images are supported right now");
             }
 
             // Copy image to tilesets group directory.
@@ -264,7 +298,7 @@ fn get_tileset_image_asset(
                     .file_name()
                     .ok_or(anyhow::anyhow!("Couldn't get file name for tileset image"))?,
             );
-            fs::copy(&image_path, &tileset_group_dir_image_path)?;
+            fs::copy(image_path, &tileset_group_dir_image_path)?;
 
             let image_base_name = image_path
                 .file_stem()
@@ -294,6 +328,7 @@ fn get_tileset_asset(
     tileset_assets_by_name: &mut BTreeMap<String, TSXAsset>,
     tileset_group_dir: &Path,
     tileset: &Arc<Tileset>,
+    tile_properties: Option<&BTreeMap<u32, Properties>>,
 ) -> anyhow::Result<TSXAsset> {
     let tileset_asset = match tileset_assets_by_name.entry(tileset.name.clone()) {
         Entry::Occupied(entry) => entry.get().clone(),
@@ -311,6 +346,7 @@ fn get_tileset_asset(
                     resource_id_generator,
                     tileset,
                     &tileset_image_asset,
+                    tile_properties,
                 )?)
                 .clone()
         }
@@ -340,6 +376,10 @@ pub struct TSXAsset {
     /// ID of a `PICT` resource.
     /// Optional: can be serialized as ID `0`, which is not usable by app resources.
     mask_pict_resource_id: Option<ResourceID>,
+    /// Custom properties of tiles that have them, by tile ID.
+    tile_properties: Vec<(u16, Properties)>,
+    /// Animations of tiles that have them, by tile ID.
+    tile_animations: Vec<(u16, TileAnimation)>,
 }
 
 impl TSXAsset {
@@ -347,6 +387,7 @@ impl TSXAsset {
         resource_id_generator: &mut ResourceIDGenerator,
         tileset: &Arc<Tileset>,
         tileset_image_asset: &MaskedPictAsset,
+        tile_properties: Option<&BTreeMap<u32, Properties>>,
     ) -> anyhow::Result<Self> {
         let MaskedPictAsset {
             image_width,
@@ -364,10 +405,51 @@ impl TSXAsset {
             image_height: *image_height as u16,
             image_pict_resource_id: *image_pict_resource_id,
             mask_pict_resource_id: *mask_pict_resource_id,
+            tile_properties: tile_properties
+                .into_iter()
+                .flatten()
+                .map(|(id, properties)| Ok((u16::try_from(*id)?, properties.clone())))
+                .collect::<anyhow::Result<_>>()?,
+            tile_animations: tile_animations(tileset)?,
         })
     }
 }
 
+/// Frames of a tile animation: tile ID within the tileset, and how long to show it in 60 Hz ticks.
+pub(crate) type TileAnimation = Vec<(u16, u16)>;
+
+/// Animations of tiles that have them, sorted by tile ID.
+pub(crate) fn tile_animations(tileset: &Tileset) -> anyhow::Result<Vec<(u16, TileAnimation)>> {
+    let mut acc = Vec::<(u16, TileAnimation)>::new();
+    for (tile_id, tile) in tileset.tiles() {
+        let Some(frames) = &tile.animation else {
+            continue;
+        };
+        if frames.is_empty() {
+            continue;
+        }
+        let frames = frames
+            .iter()
+            .map(|frame| {
+                Ok((
+                    u16::try_from(frame.tile_id)?,
+                    duration_ticks(frame.duration)?,
+                ))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        acc.push((u16::try_from(tile_id)?, frames));
+    }
+    acc.sort_by_key(|(tile_id, _)| *tile_id);
+    Ok(acc)
+}
+
+/// Convert a Tiled frame duration in milliseconds to the nearest number of 60 Hz ticks.
+/// Every frame gets at least one tick, or it'd never be seen.
+fn duration_ticks(ms: u32) -> anyhow::Result<u16> {
+    let ticks = (u64::from(ms) * 60 + 500) / 1000;
+    Ok(u16::try_from(ticks.max(1))?)
+}
+
 impl TypedResource for TSXAsset {
     const OS_TYPE: OSType = *b"TSX ";
 }
@@ -405,6 +487,26 @@ impl Resourceful for TSXAsset {
             mask_pict_resource_id = self.mask_pict_resource_id.unwrap_or(0)
         ));
 
+        // Tile properties.
+        acc.push("    {".to_string());
+        for (tile_id, properties) in &self.tile_properties {
+            acc.push(format!("        {tile_id},"));
+            acc.extend(properties.rez("        "));
+        }
+        acc.push("    },".to_string());
+
+        // Tile animations.
+        acc.push("    {".to_string());
+        for (tile_id, frames) in &self.tile_animations {
+            acc.push(format!("        {tile_id},"));
+            acc.push("        {".to_string());
+            for (frame_tile_id, ticks) in frames {
+                acc.push(format!("            {frame_tile_id}, {ticks},"));
+            }
+            acc.push("        },".to_string());
+        }
+        acc.push("    },".to_string());
+
         acc.push("};\n".to_string());
 
         acc.join("\n")
@@ -421,13 +523,37 @@ impl Resourceful for TSXAsset {
 }
 
 impl Codegen for TSXAsset {
-    const HPP: &'static str = r#"struct TSXAsset {
+    const HPP: &'static str = r#"struct TSXTileProperties {
+  /// ID within tileset.
+  uint16_t tile_id;
+  std::vector<TiledProperty> properties;
+};
+
+struct TSXTileAnimationFrame {
+  /// ID within tileset.
+  uint16_t tile_id;
+  /// How long to show this frame, in 60 Hz ticks.
+  uint16_t ticks;
+};
+
+struct TSXTileAnimation {
+  /// ID within tileset of the tile that's replaced by the animation.
+  uint16_t tile_id;
+  /// Played in order, then repeated.
+  std::vector<TSXTileAnimationFrame> frames;
+};
+
+struct TSXAsset {
   uint16_t tile_width;
   uint16_t tile_height;
   uint16_t image_width;
   uint16_t image_height;
   ResourceID image_pict_resource_id;
   std::optional<ResourceID> mask_pict_resource_id;
+  /// Only tiles that have properties, sorted by tile ID.
+  std::vector<TSXTileProperties> tile_properties;
+  /// Only tiles that are animated, sorted by tile ID.
+  std::vector<TSXTileAnimation> tile_animations;
 };
 "#;
 
@@ -451,6 +577,12 @@ impl Codegen for TSXAsset {
   .image_height = {image_height},
   .image_pict_resource_id = {image_pict_resource_id},
   .mask_pict_resource_id = {mask_pict_resource_id},
+  .tile_properties = {{
+{tile_properties}
+  }},
+  .tile_animations = {{
+{tile_animations}
+  }},
 }};
 "#,
             id = self.data_id(),
@@ -463,6 +595,27 @@ impl Codegen for TSXAsset {
                 .mask_pict_resource_id
                 .map(|id| id.to_string())
                 .unwrap_or("{}".to_string()),
+            tile_properties = self
+                .tile_properties
+                .iter()
+                .map(|(tile_id, properties)| format!(
+                    "    TSXTileProperties{{\n      .tile_id = {tile_id},\n      .properties = {properties},\n    }},\n",
+                    properties = properties.cpp("      "),
+                ))
+                .collect::<String>(),
+            tile_animations = self
+                .tile_animations
+                .iter()
+                .map(|(tile_id, frames)| format!(
+                    "    TSXTileAnimation{{\n      .tile_id = {tile_id},\n      .frames = {{\n{frames}      }},\n    }},\n",
+                    frames = frames
+                        .iter()
+                        .map(|(frame_tile_id, ticks)| format!(
+                            "        TSXTileAnimationFrame{{{frame_tile_id}, {ticks}}},\n"
+                        ))
+                        .collect::<String>(),
+                ))
+                .collect::<String>(),
         )
     }
 }
@@ -485,7 +638,9 @@ pub struct TMXAsset {
     /// TSX resources.
     tileset_resource_ids: Vec<ResourceID>,
     tile_layers: Vec<TMXTileLayer>,
+    image_layers: Vec<TMXImageLayer>,
     region_groups: Vec<TMXRegionGroup>,
+    properties: Properties,
 }
 
 impl TypedResource for TMXAsset {
@@ -518,10 +673,13 @@ impl Resourceful for TMXAsset {
         acc.push("    {".to_string());
         for tile_layer in &self.tile_layers {
             acc.push(format!(
-                "        \"{name}\", {width}, {height},",
+                "        \"{name}\", {width}, {height}, {offset_x}, {offset_y}, {opacity},",
                 name = tile_layer.name,
                 width = tile_layer.width,
-                height = tile_layer.height
+                height = tile_layer.height,
+                offset_x = tile_layer.offset_x,
+                offset_y = tile_layer.offset_y,
+                opacity = tile_layer.opacity,
             ));
 
             acc.push("        {".to_string());
@@ -536,6 +694,26 @@ impl Resourceful for TMXAsset {
                 ));
             }
             acc.push("        },".to_string());
+            acc.extend(tile_layer.properties.rez("        "));
+        }
+        acc.push("    },".to_string());
+
+        // Image layers.
+        acc.push("    {".to_string());
+        for image_layer in &self.image_layers {
+            acc.push(format!(
+                "        \"{name}\", {image_pict_resource_id}, {mask_pict_resource_id}, \
+                {offset_x}, {offset_y}, {parallax_x}, {parallax_y}, {opacity},",
+                name = image_layer.name,
+                image_pict_resource_id = image_layer.image_pict_resource_id,
+                mask_pict_resource_id = image_layer.mask_pict_resource_id.unwrap_or(0),
+                offset_x = image_layer.offset_x,
+                offset_y = image_layer.offset_y,
+                parallax_x = image_layer.parallax_x,
+                parallax_y = image_layer.parallax_y,
+                opacity = image_layer.opacity,
+            ));
+            acc.extend(image_layer.properties.rez("        "));
         }
         acc.push("    },".to_string());
 
@@ -543,13 +721,18 @@ impl Resourceful for TMXAsset {
         acc.push("    {".to_string());
         for region_group in &self.region_groups {
             acc.push(format!(
-                "        \"{name}\", {rgn_resource_id},",
+                "        \"{name}\", {rgn_resource_id}, {obj_resource_id},",
                 name = region_group.name,
                 rgn_resource_id = region_group.rgn_resource_id,
+                obj_resource_id = region_group.obj_resource_id,
             ));
+            acc.extend(region_group.properties.rez("        "));
         }
         acc.push("    },".to_string());
 
+        // Map properties.
+        acc.extend(self.properties.rez("    "));
+
         acc.push("};\n".to_string());
 
         acc.join("\n")
@@ -579,6 +762,20 @@ impl Resourceful for TMXAsset {
             ));
         }
 
+        // Image layer indexes.
+        acc.push("\n".to_string());
+        for (index, image_layer) in self.image_layers.iter().enumerate() {
+            acc.push(format!(
+                "#define {index_constant} {index}",
+                index_constant = format!(
+                    "asset_{name}_{image_layer_name}_image_layer_index",
+                    name = self.name(),
+                    image_layer_name = image_layer.name,
+                )
+                .to_case(Case::Camel),
+            ));
+        }
+
         // Region group indexes.
         acc.push("\n".to_string());
         for (index, region_group) in self.region_groups.iter().enumerate() {
@@ -615,12 +812,43 @@ struct TMXTileLayer {
   uint16_t width;
   /// In tiles.
   uint16_t height;
+  /// In pixels.
+  int16_t offset_x;
+  /// In pixels.
+  int16_t offset_y;
+  /// 0 is transparent, 255 is opaque.
+  uint8_t opacity;
   std::vector<TMXTile> tiles;
+  std::vector<TiledProperty> properties;
+};
+
+/// Background image, drawn behind the tile layers.
+struct TMXImageLayer {
+  std::string name;
+  ResourceID image_pict_resource_id;
+  std::optional<ResourceID> mask_pict_resource_id;
+  /// Position of the image's top left corner, in pixels.
+  int16_t offset_x;
+  /// Position of the image's top left corner, in pixels.
+  int16_t offset_y;
+  /// How fast the image scrolls compared to the map, in 256ths:
+  /// 256 scrolls with the map, and 0 doesn't scroll.
+  int16_t parallax_x;
+  /// See `parallax_x`.
+  int16_t parallax_y;
+  /// 0 is transparent, 255 is opaque.
+  uint8_t opacity;
+  std::vector<TiledProperty> properties;
 };
 
 struct TMXRegionGroup {
   std::string name;
+  /// 'RGN#' resource ID for rectangular objects.
   ResourceID rgn_resource_id;
+  /// 'OBJ#' resource ID for all objects.
+  ResourceID obj_resource_id;
+  /// Object layer's properties.
+  std::vector<TiledProperty> properties;
 };
 
 struct TMXAsset {
@@ -635,7 +863,9 @@ struct TMXAsset {
   /// 'TSX ' resource IDs.
   std::vector<ResourceID> tileset_resource_ids;
   std::vector<TMXTileLayer> tile_layers;
+  std::vector<TMXImageLayer> image_layers;
   std::vector<TMXRegionGroup> region_groups;
+  std::vector<TiledProperty> properties;
 };
 "#;
 
@@ -694,14 +924,56 @@ struct TMXAsset {
       .name = "{name}",
       .width = {width},
       .height = {height},
+      .offset_x = {offset_x},
+      .offset_y = {offset_y},
+      .opacity = {opacity},
       .tiles = {{
 {tiles}
       }},
+      .properties = {properties},
     }},
 "#,
                         name = tile_layer.name,
                         width = tile_layer.width,
                         height = tile_layer.height,
+                        offset_x = tile_layer.offset_x,
+                        offset_y = tile_layer.offset_y,
+                        opacity = tile_layer.opacity,
+                        properties = tile_layer.properties.cpp("      "),
+                    )
+                })
+                .collect()
+        };
+
+        let image_layers: String = {
+            self.image_layers
+                .iter()
+                .map(|image_layer| {
+                    format!(
+                        r#"    TMXImageLayer{{
+      .name = "{name}",
+      .image_pict_resource_id = {image_pict_resource_id},
+      .mask_pict_resource_id = {mask_pict_resource_id},
+      .offset_x = {offset_x},
+      .offset_y = {offset_y},
+      .parallax_x = {parallax_x},
+      .parallax_y = {parallax_y},
+      .opacity = {opacity},
+      .properties = {properties},
+    }},
+"#,
+                        name = image_layer.name,
+                        image_pict_resource_id = image_layer.image_pict_resource_id,
+                        mask_pict_resource_id = image_layer
+                            .mask_pict_resource_id
+                            .map(|id| id.to_string())
+                            .unwrap_or("{}".to_string()),
+                        offset_x = image_layer.offset_x,
+                        offset_y = image_layer.offset_y,
+                        parallax_x = image_layer.parallax_x,
+                        parallax_y = image_layer.parallax_y,
+                        opacity = image_layer.opacity,
+                        properties = image_layer.properties.cpp("      "),
                     )
                 })
                 .collect()
@@ -715,10 +987,14 @@ struct TMXAsset {
                         r#"    TMXRegionGroup{{
       .name = "{name}",
       .rgn_resource_id = {rgn_resource_id},
+      .obj_resource_id = {obj_resource_id},
+      .properties = {properties},
     }},
 "#,
                         name = region_group.name,
                         rgn_resource_id = region_group.rgn_resource_id,
+                        obj_resource_id = region_group.obj_resource_id,
+                        properties = region_group.properties.cpp("      "),
                     )
                 })
                 .collect()
@@ -735,10 +1011,14 @@ struct TMXAsset {
   }},
   .tile_layers = {{
 {tile_layers}
+  }},
+  .image_layers = {{
+{image_layers}
   }},
   .region_groups = {{
 {region_groups}
   }},
+  .properties = {properties},
 }};
 "#,
             id = self.data_id(),
@@ -746,31 +1026,57 @@ struct TMXAsset {
             height = self.height,
             tile_width = self.tile_width,
             tile_height = self.tile_height,
+            properties = self.properties.cpp("  "),
         )
     }
 }
 
 /// Tile layer within a map.
 #[derive(Debug, Clone)]
-struct TMXTileLayer {
+pub(crate) struct TMXTileLayer {
+    /// Includes the names of any group layers it was in.
+    pub(crate) name: String,
+    // Pad to word.
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    /// In pixels, including offsets of any group layers it was in.
+    pub(crate) offset_x: i16,
+    pub(crate) offset_y: i16,
+    /// 0 is transparent, 255 is opaque.
+    pub(crate) opacity: u8,
+    // Pad to word.
+    pub(crate) tiles: Vec<TMXTile>,
+    pub(crate) properties: Properties,
+}
+
+/// Image layer within a map, as a masked `PICT`.
+#[derive(Debug, Clone)]
+struct TMXImageLayer {
     name: String,
     // Pad to word.
-    width: u16,
-    height: u16,
-    tiles: Vec<TMXTile>,
+    image_pict_resource_id: ResourceID,
+    /// Can be serialized as ID `0`, which is not usable by app resources.
+    mask_pict_resource_id: Option<ResourceID>,
+    offset_x: i16,
+    offset_y: i16,
+    parallax_x: i16,
+    parallax_y: i16,
+    opacity: u8,
+    // Pad to word.
+    properties: Properties,
 }
 
 /// A single tile position. May be empty.
 #[derive(Debug, Clone)]
-struct TMXTile {
-    flip_h: bool,
-    flip_v: bool,
-    flip_d: bool,
+pub(crate) struct TMXTile {
+    pub(crate) flip_h: bool,
+    pub(crate) flip_v: bool,
+    pub(crate) flip_d: bool,
     /// 1 + index into parent map's list of tilesets.
     /// 0 indicates an empty tile position; all other fields should be 0/false.
-    tileset_ordinal: u8,
+    pub(crate) tileset_ordinal: u8,
     /// ID within tileset.
-    tile_id: u16,
+    pub(crate) tile_id: u16,
 }
 
 impl TryFrom<&LayerTileData> for TMXTile {
@@ -782,7 +1088,7 @@ impl TryFrom<&LayerTileData> for TMXTile {
         Ok(TMXTile {
             flip_h: data.flip_h,
             flip_v: data.flip_v,
-            flip_d: data.flip_v,
+            flip_d: data.flip_d,
             tileset_ordinal: 1 + tileset_index,
             tile_id,
         })
@@ -801,64 +1107,267 @@ impl TMXTile {
     }
 }
 
-/// A named list of map regions, stored in an `RGN#` resource.
+/// A named list of map regions, stored in an `RGN#` resource,
+/// and the objects they came from, stored in an `OBJ#` resource.
 #[derive(Debug, Clone)]
 struct TMXRegionGroup {
     name: String,
     // Pad to word.
     /// ID of `RGN#` resource.
     rgn_resource_id: ResourceID,
+    /// ID of `OBJ#` resource.
+    obj_resource_id: ResourceID,
+    /// Object layer's properties.
+    properties: Properties,
 }
 
 fn object_layer_to_tmx_region_group(
     resource_id_generator: &mut ResourceIDGenerator,
     tilemap_rgn_assets: &mut Vec<RGNAsset>,
-    map_name: String,
-    layer_name: String,
-    layer: ObjectLayer,
-) -> anyhow::Result<TMXRegionGroup> {
-    let mut regions = BTreeMap::<String, QDRect>::new();
-    for object in layer.objects() {
-        match object.shape {
-            ObjectShape::Rect { width, height } => {
-                if width < 0.0 || height < 0.0 {
-                    anyhow::bail!("Rectangle object's width or height is negative");
-                }
-                let top = i16_try_from_f32(object.y)?;
-                let left = i16_try_from_f32(object.x)?;
-                let bottom = top + i16_try_from_f32(height)?;
-                let right = top + i16_try_from_f32(width)?;
-                regions.insert(
-                    object.name.clone(),
-                    QDRect {
-                        top,
-                        left,
-                        bottom,
-                        right,
-                    },
-                );
-            }
-            ObjectShape::Ellipse { .. } => anyhow::bail!("Ellipse objects aren't supported"),
-            ObjectShape::Polyline { .. } => anyhow::bail!("Polyline objects aren't supported"),
-            ObjectShape::Polygon { .. } => anyhow::bail!("Polygon objects aren't supported"),
-            ObjectShape::Point(_, _) => anyhow::bail!("Point objects aren't supported"),
-            ObjectShape::Text { .. } => anyhow::bail!("Text objects aren't supported"),
-        }
-    }
+    tilemap_obj_assets: &mut Vec<OBJAsset>,
+    map_name: &str,
+    layer: MapObjectLayer,
+) -> TMXRegionGroup {
+    let MapObjectLayer {
+        name: layer_name,
+        properties,
+        objects,
+    } = layer;
 
     let rgn_asset = RGNAsset::new(
         resource_id_generator,
         format!("map {map_name} layer {layer_name} rectangular objects"),
-        regions,
+        // `RGN#` resources look regions up by name, so the last one with a given name wins.
+        objects
+            .iter()
+            .filter(|object| object.shape == MapObjectShape::Rectangle)
+            .map(|object| (object.name.clone(), object.bounds.clone()))
+            .collect(),
     );
 
+    let obj_asset = OBJAsset {
+        resource_id: resource_id_generator.get(OBJAsset::OS_TYPE),
+        name: format!("map {map_name} layer {layer_name} objects"),
+        objects,
+    };
+
     let region_group = TMXRegionGroup {
         name: layer_name,
         rgn_resource_id: rgn_asset.resource_id,
+        obj_resource_id: obj_asset.resource_id,
+        properties,
     };
 
     tilemap_rgn_assets.push(rgn_asset);
-    Ok(region_group)
+    tilemap_obj_assets.push(obj_asset);
+    region_group
+}
+
+/// Kinds of Tiled object we can use.
+/// Values match the `OBJ#` resource type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MapObjectShape {
+    Rectangle = 0,
+    Ellipse = 1,
+    Point = 2,
+    Polyline = 3,
+    Polygon = 4,
+}
+
+/// Object from an object layer. Coordinates are in map pixels.
+#[derive(Debug, Clone)]
+pub(crate) struct MapObject {
+    /// Not necessarily unique, or even present.
+    pub(crate) name: String,
+    /// Tiled class (formerly type). May be empty.
+    pub(crate) class: String,
+    pub(crate) shape: MapObjectShape,
+    /// Bounding box. Empty for points.
+    pub(crate) bounds: QDRect,
+    /// Vertices of polylines and polygons. Empty for other shapes.
+    pub(crate) points: Vec<QDPoint>,
+    pub(crate) properties: Properties,
+}
+
+impl MapObject {
+    fn new(object: &ObjectData, map_properties: &MapProperties) -> anyhow::Result<Self> {
+        if object.rotation != 0.0 {
+            anyhow::bail!("Rotated objects aren't supported");
+        }
+        let top = i16_try_from_f32(object.y)?;
+        let left = i16_try_from_f32(object.x)?;
+
+        let (shape, bounds, points) = match &object.shape {
+            ObjectShape::Rect { width, height } => (
+                MapObjectShape::Rectangle,
+                sized_rect(top, left, *width, *height)?,
+                vec![],
+            ),
+            ObjectShape::Ellipse { width, height } => (
+                MapObjectShape::Ellipse,
+                sized_rect(top, left, *width, *height)?,
+                vec![],
+            ),
+            ObjectShape::Point(_, _) => (
+                MapObjectShape::Point,
+                QDRect {
+                    top,
+                    left,
+                    bottom: top,
+                    right: left,
+                },
+                vec![],
+            ),
+            ObjectShape::Polyline { points } => {
+                let points = object_points(left, top, points)?;
+                (MapObjectShape::Polyline, bounding_rect(&points)?, points)
+            }
+            ObjectShape::Polygon { points } => {
+                let points = object_points(left, top, points)?;
+                (MapObjectShape::Polygon, bounding_rect(&points)?, points)
+            }
+            ObjectShape::Text { .. } => anyhow::bail!("Text objects aren't supported"),
+        };
+
+        Ok(MapObject {
+            name: object.name.clone(),
+            class: object.user_type.clone(),
+            shape,
+            bounds,
+            points,
+            properties: map_properties
+                .objects
+                .get(&object.id())
+                .cloned()
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl MapObject {
+    /// Move by a number of map pixels.
+    fn translate(&mut self, h: i16, v: i16) {
+        self.bounds.top += v;
+        self.bounds.bottom += v;
+        self.bounds.left += h;
+        self.bounds.right += h;
+        for point in &mut self.points {
+            point.v += v;
+            point.h += h;
+        }
+    }
+}
+
+fn sized_rect(top: i16, left: i16, width: f32, height: f32) -> anyhow::Result<QDRect> {
+    if width < 0.0 || height < 0.0 {
+        anyhow::bail!("Object's width or height is negative");
+    }
+    Ok(QDRect {
+        top,
+        left,
+        bottom: top + i16_try_from_f32(height)?,
+        right: left + i16_try_from_f32(width)?,
+    })
+}
+
+/// Tiled polyline and polygon points are relative to the object's position.
+fn object_points(left: i16, top: i16, points: &[(f32, f32)]) -> anyhow::Result<Vec<QDPoint>> {
+    points
+        .iter()
+        .map(|(x, y)| {
+            Ok(QDPoint {
+                v: top + i16_try_from_f32(*y)?,
+                h: left + i16_try_from_f32(*x)?,
+            })
+        })
+        .collect()
+}
+
+fn bounding_rect(points: &[QDPoint]) -> anyhow::Result<QDRect> {
+    let (Some(top), Some(left), Some(bottom), Some(right)) = (
+        points.iter().map(|p| p.v).min(),
+        points.iter().map(|p| p.h).min(),
+        points.iter().map(|p| p.v).max(),
+        points.iter().map(|p| p.h).max(),
+    ) else {
+        anyhow::bail!("Polyline or polygon object has no points");
+    };
+    Ok(QDRect {
+        top,
+        left,
+        bottom,
+        right,
+    })
+}
+
+/// Objects in an object layer, in layer order.
+fn object_layer_objects(
+    layer: ObjectLayer,
+    map_properties: &MapProperties,
+) -> anyhow::Result<Vec<MapObject>> {
+    layer
+        .objects()
+        .map(|object| MapObject::new(&object, map_properties))
+        .collect()
+}
+
+/// List of objects from a map object layer, with their shapes and custom properties,
+/// stored as an `OBJ#` resource.
+#[derive(Debug, Clone)]
+pub struct OBJAsset {
+    resource_id: ResourceID,
+    name: String,
+    objects: Vec<MapObject>,
+}
+
+impl TypedResource for OBJAsset {
+    const OS_TYPE: OSType = *b"OBJ#";
+}
+
+impl Resourceful for OBJAsset {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn rez(&self) -> String {
+        let mut acc = Vec::<String>::new();
+        acc.push(format!(
+            "resource '{os_type}' ({id_constant}, \"{name}\") {{",
+            os_type = Self::os_type_rez(),
+            name = self.name,
+            id_constant = self.id_constant(),
+        ));
+        acc.push("    {".to_string());
+        for object in &self.objects {
+            acc.push(format!(
+                "        \"{name}\", \"{class}\", {shape}, {bounds},",
+                name = object.name,
+                class = object.class,
+                shape = object.shape as i16,
+                bounds = object.bounds.rez(),
+            ));
+
+            acc.push("        {".to_string());
+            for point in &object.points {
+                acc.push(format!("            {point},", point = point.rez()));
+            }
+            acc.push("        },".to_string());
+
+            acc.extend(object.properties.rez("        "));
+        }
+        acc.push("    }".to_string());
+        acc.push("};\n".to_string());
+        acc.join("\n")
+    }
+
+    fn header(&self) -> String {
+        // Objects are looked up by name, since they don't have to have unique names.
+        format!(
+            "#define {id_constant} {id}\n",
+            id_constant = self.id_constant(),
+            id = self.resource_id,
+        )
+    }
 }
 
 fn i16_try_from_f32(x: f32) -> anyhow::Result<i16> {
@@ -873,26 +1382,377 @@ fn i16_try_from_f32(x: f32) -> anyhow::Result<i16> {
     Ok(x.trunc() as i16)
 }
 
+/// Object layer within a map.
+pub(crate) struct MapObjectLayer {
+    /// Includes the names of any group layers it was in.
+    pub(crate) name: String,
+    pub(crate) properties: Properties,
+    pub(crate) objects: Vec<MapObject>,
+}
+
+/// Image layer within a map. Usually a parallax background.
+pub(crate) struct MapImageLayer {
+    /// Includes the names of any group layers it was in.
+    pub(crate) name: String,
+    pub(crate) image: PathBuf,
+    /// Position of the image's top left corner, in map pixels.
+    pub(crate) offset_x: i16,
+    pub(crate) offset_y: i16,
+    /// How fast the image scrolls compared to the map, in 256ths:
+    /// 256 scrolls with the map, and 0 doesn't scroll.
+    pub(crate) parallax_x: i16,
+    pub(crate) parallax_y: i16,
+    /// 0 is transparent, 255 is opaque.
+    pub(crate) opacity: u8,
+    pub(crate) properties: Properties,
+}
+
+/// Tile or image layer, by index into [`MapContents`]'s list of that kind of layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DrawnLayer {
+    Tiles(usize),
+    Image(usize),
+}
+
+/// Map contents that don't depend on which platform they're for.
+/// Group layers are flattened, hidden layers are left out,
+/// and infinite maps are cropped to the chunks they use.
+pub(crate) struct MapContents {
+    pub(crate) map: Map,
+    /// In tiles. Use these instead of the map's, which don't mean anything for infinite maps.
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) properties: Properties,
+    pub(crate) tile_layers: Vec<TMXTileLayer>,
+    pub(crate) image_layers: Vec<MapImageLayer>,
+    pub(crate) object_layers: Vec<MapObjectLayer>,
+    /// Tile and image layers, bottom to top.
+    pub(crate) drawn_layers: Vec<DrawnLayer>,
+    /// Custom properties of tiles that have them, by tileset name, then tile ID.
+    pub(crate) tile_properties: BTreeMap<String, BTreeMap<u32, Properties>>,
+}
+
+/// Load a TMX map and check that it's something we can use.
+pub(crate) fn read_map(
+    loader: &mut TiledLoader,
+    property_types: &PropertyTypes,
+    src: &Path,
+) -> anyhow::Result<MapContents> {
+    let map_properties = MapProperties::read(property_types, src)?;
+    let map = loader.load_tmx_map(src)?;
+
+    if map.orientation != Orientation::Orthogonal {
+        anyhow::bail!("Only orthogonal rectangular maps are supported");
+    }
+
+    let bounds = if map.infinite() {
+        infinite_map_bounds(&map)?
+    } else {
+        TileBounds {
+            x: 0,
+            y: 0,
+            width: map.width,
+            height: map.height,
+        }
+    };
+
+    let mut reader = LayerReader {
+        map_properties: &map_properties,
+        bounds,
+        origin_x: (bounds.x * map.tile_width as i32) as f32,
+        origin_y: (bounds.y * map.tile_height as i32) as f32,
+        tile_layers: vec![],
+        image_layers: vec![],
+        object_layers: vec![],
+        drawn_layers: vec![],
+    };
+    reader.read_layers(map.layers(), &LayerPlacement::default())?;
+    let LayerReader {
+        tile_layers,
+        image_layers,
+        object_layers,
+        drawn_layers,
+        ..
+    } = reader;
+
+    Ok(MapContents {
+        width: bounds.width,
+        height: bounds.height,
+        properties: map_properties.map,
+        tile_layers,
+        image_layers,
+        object_layers,
+        drawn_layers,
+        tile_properties: map_properties.tiles,
+        map,
+    })
+}
+
+/// Part of the map's tile grid that we keep, in tiles.
+#[derive(Debug, Clone, Copy)]
+struct TileBounds {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// Infinite maps are cropped to the chunks that any of their tile layers use.
+fn infinite_map_bounds(map: &Map) -> anyhow::Result<TileBounds> {
+    let mut chunks = Vec::<(i32, i32)>::new();
+    collect_chunks(map.layers(), &mut chunks);
+    let (Some(left), Some(top), Some(right), Some(bottom)) = (
+        chunks.iter().map(|(x, _)| *x).min(),
+        chunks.iter().map(|(_, y)| *y).min(),
+        chunks.iter().map(|(x, _)| *x).max(),
+        chunks.iter().map(|(_, y)| *y).max(),
+    ) else {
+        anyhow::bail!("Infinite map has no tiles");
+    };
+    Ok(TileBounds {
+        x: left * ChunkData::WIDTH as i32,
+        y: top * ChunkData::HEIGHT as i32,
+        width: (right - left + 1) as u32 * ChunkData::WIDTH,
+        height: (bottom - top + 1) as u32 * ChunkData::HEIGHT,
+    })
+}
+
+fn collect_chunks<'map>(layers: impl Iterator<Item = Layer<'map>>, chunks: &mut Vec<(i32, i32)>) {
+    for layer in layers.filter(|layer| layer.visible) {
+        match layer.layer_type() {
+            LayerType::Tiles(TileLayer::Infinite(tile_layer)) => {
+                chunks.extend(tile_layer.chunk_data().map(|(pos, _)| pos));
+            }
+            LayerType::Group(group_layer) => collect_chunks(group_layer.layers(), chunks),
+            _ => {}
+        }
+    }
+}
+
+/// What a layer inherits from the group layers it's in, combined with its own settings.
+#[derive(Debug, Clone)]
+struct LayerPlacement {
+    /// Names of the group layers it's in, each followed by a space.
+    name_prefix: String,
+    /// In map pixels.
+    offset_x: f32,
+    offset_y: f32,
+    opacity: f32,
+    parallax_x: f32,
+    parallax_y: f32,
+}
+
+impl Default for LayerPlacement {
+    fn default() -> Self {
+        Self {
+            name_prefix: String::new(),
+            offset_x: 0.0,
+            offset_y: 0.0,
+            opacity: 1.0,
+            parallax_x: 1.0,
+            parallax_y: 1.0,
+        }
+    }
+}
+
+impl LayerPlacement {
+    /// Combine with a layer's own settings, the way Tiled does.
+    fn layer(&self, layer: &Layer) -> Self {
+        Self {
+            name_prefix: self.name_prefix.clone(),
+            offset_x: self.offset_x + layer.offset_x,
+            offset_y: self.offset_y + layer.offset_y,
+            opacity: self.opacity * layer.opacity,
+            parallax_x: self.parallax_x * layer.parallax_x,
+            parallax_y: self.parallax_y * layer.parallax_y,
+        }
+    }
+
+    fn name(&self, layer: &Layer) -> String {
+        format!(
+            "{prefix}{name}",
+            prefix = self.name_prefix,
+            name = layer.name
+        )
+    }
+
+    /// 0 is transparent, 255 is opaque.
+    fn opacity_u8(&self) -> u8 {
+        (self.opacity.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+}
+
+/// Flattens a map's layers.
+struct LayerReader<'a> {
+    map_properties: &'a MapProperties,
+    bounds: TileBounds,
+    /// Top left corner of `bounds` in map pixels, which becomes the new origin.
+    origin_x: f32,
+    origin_y: f32,
+    tile_layers: Vec<TMXTileLayer>,
+    image_layers: Vec<MapImageLayer>,
+    object_layers: Vec<MapObjectLayer>,
+    drawn_layers: Vec<DrawnLayer>,
+}
+
+impl LayerReader<'_> {
+    fn read_layers<'map>(
+        &mut self,
+        layers: impl Iterator<Item = Layer<'map>>,
+        parent: &LayerPlacement,
+    ) -> anyhow::Result<()> {
+        // Artists hide layers they aren't using,
+        // so leave out hidden layers and everything in hidden groups.
+        for layer in layers.filter(|layer| layer.visible) {
+            let placement = parent.layer(&layer);
+            let name = placement.name(&layer);
+            let properties = self
+                .map_properties
+                .layers
+                .get(&layer.id())
+                .cloned()
+                .unwrap_or_default();
+            match layer.layer_type() {
+                LayerType::Tiles(TileLayer::Finite(tile_layer)) => {
+                    let tiles = self.tiles(|x, y| tile_layer.get_tile_data(x, y))?;
+                    self.push_tile_layer(name, properties, &placement, tiles)?;
+                }
+                LayerType::Tiles(TileLayer::Infinite(tile_layer)) => {
+                    let tiles = self.tiles(|x, y| tile_layer.get_tile_data(x, y))?;
+                    self.push_tile_layer(name, properties, &placement, tiles)?;
+                }
+                LayerType::Objects(object_layer) => {
+                    let mut objects = object_layer_objects(object_layer, self.map_properties)?;
+                    let h = i16_try_from_f32(placement.offset_x - self.origin_x)?;
+                    let v = i16_try_from_f32(placement.offset_y - self.origin_y)?;
+                    for object in &mut objects {
+                        object.translate(h, v);
+                    }
+                    self.object_layers.push(MapObjectLayer {
+                        name,
+                        properties,
+                        objects,
+                    });
+                }
+                LayerType::Image(image_layer) => {
+                    self.push_image_layer(name, properties, &placement, image_layer)?;
+                }
+                LayerType::Group(group_layer) => {
+                    let group = LayerPlacement {
+                        name_prefix: format!("{name} "),
+                        ..placement
+                    };
+                    self.read_layers(group_layer.layers(), &group)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tiles within the bounds, in row-major order.
+    fn tiles<'t>(
+        &self,
+        get_tile_data: impl Fn(i32, i32) -> Option<&'t LayerTileData>,
+    ) -> anyhow::Result<Vec<TMXTile>> {
+        let TileBounds {
+            x,
+            y,
+            width,
+            height,
+        } = self.bounds;
+        let mut tiles = vec![];
+        for y in y..y + height as i32 {
+            for x in x..x + width as i32 {
+                let tile = if let Some(data) = get_tile_data(x, y) {
+                    TMXTile::try_from(data)?
+                } else {
+                    TMXTile::empty()
+                };
+                tiles.push(tile);
+            }
+        }
+        Ok(tiles)
+    }
+
+    fn push_tile_layer(
+        &mut self,
+        name: String,
+        properties: Properties,
+        placement: &LayerPlacement,
+        tiles: Vec<TMXTile>,
+    ) -> anyhow::Result<()> {
+        // Tile layers are all cropped the same way, so the origin doesn't move them.
+        self.drawn_layers
+            .push(DrawnLayer::Tiles(self.tile_layers.len()));
+        self.tile_layers.push(TMXTileLayer {
+            name,
+            width: u16::try_from(self.bounds.width)?,
+            height: u16::try_from(self.bounds.height)?,
+            offset_x: i16_try_from_f32(placement.offset_x)?,
+            offset_y: i16_try_from_f32(placement.offset_y)?,
+            opacity: placement.opacity_u8(),
+            tiles,
+            properties,
+        });
+        Ok(())
+    }
+
+    fn push_image_layer(
+        &mut self,
+        name: String,
+        properties: Properties,
+        placement: &LayerPlacement,
+        image_layer: ImageLayer,
+    ) -> anyhow::Result<()> {
+        // Tiled creates image layers without images, which don't draw anything.
+        let Some(image) = &image_layer.image else {
+            return Ok(());
+        };
+        self.drawn_layers
+            .push(DrawnLayer::Image(self.image_layers.len()));
+        self.image_layers.push(MapImageLayer {
+            name,
+            image: image.source.clone(),
+            offset_x: i16_try_from_f32(placement.offset_x - self.origin_x)?,
+            offset_y: i16_try_from_f32(placement.offset_y - self.origin_y)?,
+            parallax_x: (placement.parallax_x * 256.0).round() as i16,
+            parallax_y: (placement.parallax_y * 256.0).round() as i16,
+            opacity: placement.opacity_u8(),
+            properties,
+        });
+        Ok(())
+    }
+}
+
 fn load_map(
     build_dir: &Path,
     resource_id_generator: &mut ResourceIDGenerator,
     tileset_image_assets_by_path: &mut BTreeMap<PathBuf, MaskedPictAsset>,
     tileset_assets_by_name: &mut BTreeMap<String, TSXAsset>,
     tilemap_rgn_assets: &mut Vec<RGNAsset>,
+    tilemap_obj_assets: &mut Vec<OBJAsset>,
     tileset_group_dir: &Path,
-    loader: &mut Loader,
+    loader: &mut TiledLoader,
+    property_types: &PropertyTypes,
     src: &Path,
     base_name: &OsStr,
 ) -> anyhow::Result<TMXAsset> {
-    let map = loader.load_tmx_map(src)?;
+    let MapContents {
+        map,
+        width,
+        height,
+        properties,
+        tile_layers,
+        image_layers,
+        object_layers,
+        // Mac game modes draw layers one at a time, in whatever order they like.
+        drawn_layers: _,
+        tile_properties,
+    } = read_map(loader, property_types, src)?;
     let name = base_name.to_string_lossy().to_string();
 
-    if map.orientation != Orientation::Orthogonal {
-        anyhow::bail!("Only orthogonal rectangular maps are supported");
-    }
-
-    let width = u16::try_from(map.width)?;
-    let height = u16::try_from(map.height)?;
+    let width = u16::try_from(width)?;
+    let height = u16::try_from(height)?;
     let tile_width = u16::try_from(map.tile_width)?;
     let tile_height = u16::try_from(map.tile_height)?;
 
@@ -905,42 +1765,51 @@ fn load_map(
             tileset_assets_by_name,
             tileset_group_dir,
             tileset,
+            tile_properties.get(&tileset.name),
         )?;
         tileset_resource_ids.push(tileset_asset.resource_id);
     }
 
-    let mut tile_layers = Vec::<TMXTileLayer>::new();
-    let mut region_groups = Vec::<TMXRegionGroup>::new();
-    for layer in map.layers() {
-        match layer.layer_type() {
-            LayerType::Tiles(tile_layer) => match tile_layer {
-                TileLayer::Infinite(_) => {
-                    anyhow::bail!("Infinite tile layers aren't supported");
-                }
-                TileLayer::Finite(finite_tile_layer) => {
-                    let tmx_layer =
-                        finite_tile_layer_to_tmx_tile_layer(finite_tile_layer, layer.name.clone())?;
-                    tile_layers.push(tmx_layer);
-                }
-            },
-            LayerType::Objects(object_layer) => {
-                let tmx_region_group = object_layer_to_tmx_region_group(
-                    resource_id_generator,
-                    tilemap_rgn_assets,
-                    name.clone(),
-                    layer.name.clone(),
-                    object_layer,
-                )?;
-                region_groups.push(tmx_region_group);
-            }
-            LayerType::Image(_) => {
-                anyhow::bail!("Image layers aren't supported");
-            }
-            LayerType::Group(_) => {
-                anyhow::bail!("Group layers aren't supported");
-            }
-        }
-    }
+    let image_layers = image_layers
+        .into_iter()
+        .map(|image_layer| {
+            let MaskedPictAsset {
+                image_pict_resource_id,
+                mask_pict_resource_id,
+                ..
+            } = get_image_asset(
+                build_dir,
+                resource_id_generator,
+                tileset_image_assets_by_path,
+                tileset_group_dir,
+                &image_layer.image,
+            )?;
+            Ok(TMXImageLayer {
+                name: image_layer.name,
+                image_pict_resource_id,
+                mask_pict_resource_id,
+                offset_x: image_layer.offset_x,
+                offset_y: image_layer.offset_y,
+                parallax_x: image_layer.parallax_x,
+                parallax_y: image_layer.parallax_y,
+                opacity: image_layer.opacity,
+                properties: image_layer.properties,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let region_groups = object_layers
+        .into_iter()
+        .map(|layer| {
+            object_layer_to_tmx_region_group(
+                resource_id_generator,
+                tilemap_rgn_assets,
+                tilemap_obj_assets,
+                &name,
+                layer,
+            )
+        })
+        .collect();
 
     let resource_id = resource_id_generator.get(TMXAsset::OS_TYPE);
 
@@ -953,33 +1822,178 @@ fn load_map(
         tile_height,
         tileset_resource_ids,
         tile_layers,
+        image_layers,
         region_groups,
+        properties,
     })
 }
 
-fn finite_tile_layer_to_tmx_tile_layer(
-    layer: FiniteTileLayer,
-    name: String,
-) -> anyhow::Result<TMXTileLayer> {
-    let width = u16::try_from(layer.width())?;
-    let height = u16::try_from(layer.height())?;
-    let mut tiles = vec![];
-
-    for y in 0..(layer.height() as i32) {
-        for x in 0..(layer.width() as i32) {
-            let tile = if let Some(data) = layer.get_tile_data(x, y) {
-                TMXTile::try_from(data)?
-            } else {
-                TMXTile::empty()
-            };
-            tiles.push(tile);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILESET: &str = r#"<tileset firstgid="1" name="Town" tilewidth="8" tileheight="8" tilecount="4" columns="2">
+  <image source="town.png" width="16" height="16"/>
+ </tileset>"#;
+
+    /// Write a TMX map to the temp directory and read it.
+    fn read_tmx(file_name: &str, layers: &str, infinite: bool) -> MapContents {
+        let src = std::env::temp_dir().join(file_name);
+        fs::write(
+            &src,
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="2" height="2" tilewidth="8" tileheight="8" infinite="{infinite}" nextlayerid="20" nextobjectid="20">
+ {TILESET}
+ {layers}
+</map>
+"#,
+                infinite = u8::from(infinite),
+            ),
+        )
+        .unwrap();
+        read_map(&mut new_loader(), &PropertyTypes::default(), &src).unwrap()
     }
 
-    Ok(TMXTileLayer {
-        name,
-        width,
-        height,
-        tiles,
-    })
+    /// CSV data for a chunk with one tile at the given position within it.
+    fn chunk(x: i32, y: i32, tile_x: usize, tile_y: usize) -> String {
+        let mut gids = vec!["0"; (ChunkData::WIDTH * ChunkData::HEIGHT) as usize];
+        gids[tile_y * ChunkData::WIDTH as usize + tile_x] = "2";
+        format!(
+            r#"<chunk x="{x}" y="{y}" width="{width}" height="{height}">{gids}</chunk>"#,
+            x = x * ChunkData::WIDTH as i32,
+            y = y * ChunkData::HEIGHT as i32,
+            width = ChunkData::WIDTH,
+            height = ChunkData::HEIGHT,
+            gids = gids.join(","),
+        )
+    }
+
+    #[test]
+    fn test_read_map_flattens_groups() {
+        let contents = read_tmx(
+            "aetools_test_read_map_flattens_groups.tmx",
+            r#"<layer id="1" name="Ground" width="2" height="2">
+  <data encoding="csv">1,0,0,2</data>
+ </layer>
+ <group id="2" name="Town" offsetx="8" opacity="0.5">
+  <group id="3" name="Houses" offsety="16" opacity="0.5">
+   <layer id="4" name="Roofs" width="2" height="2" offsetx="1">
+    <data encoding="csv">0,3,0,0</data>
+   </layer>
+  </group>
+  <imagelayer id="5" name="Sky" parallaxx="0.5">
+   <image source="sky.png" width="32" height="16"/>
+  </imagelayer>
+  <objectgroup id="6" name="Doors">
+   <object id="1" name="Inn" x="4" y="4" width="8" height="8"/>
+  </objectgroup>
+ </group>
+ <layer id="7" name="Sketch" width="2" height="2" visible="0">
+  <data encoding="csv">4,4,4,4</data>
+ </layer>
+ <group id="8" name="Old" visible="0">
+  <layer id="9" name="Inside" width="2" height="2">
+   <data encoding="csv">4,4,4,4</data>
+  </layer>
+ </group>"#,
+            false,
+        );
+        assert_eq!((contents.width, contents.height), (2, 2));
+
+        let names: Vec<&str> = contents
+            .tile_layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Ground", "Town Houses Roofs"]);
+        let roofs = &contents.tile_layers[1];
+        assert_eq!((roofs.offset_x, roofs.offset_y), (9, 16));
+        assert_eq!(roofs.opacity, 64);
+        assert_eq!(roofs.tiles[1].tile_id, 2);
+
+        let sky = &contents.image_layers[0];
+        assert_eq!(sky.name, "Town Sky");
+        assert_eq!((sky.offset_x, sky.offset_y), (8, 0));
+        assert_eq!((sky.parallax_x, sky.parallax_y), (128, 256));
+        assert_eq!(sky.opacity, 128);
+
+        let doors = &contents.object_layers[0];
+        assert_eq!(doors.name, "Town Doors");
+        let bounds = &doors.objects[0].bounds;
+        assert_eq!((bounds.left, bounds.top), (12, 4));
+
+        assert_eq!(
+            contents.drawn_layers,
+            vec![
+                DrawnLayer::Tiles(0),
+                DrawnLayer::Tiles(1),
+                DrawnLayer::Image(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_map_crops_infinite_maps() {
+        let contents = read_tmx(
+            "aetools_test_read_map_crops_infinite_maps.tmx",
+            &format!(
+                r#"<layer id="1" name="Ground" width="2" height="2">
+  <data encoding="csv">{west}{south_east}</data>
+ </layer>
+ <group id="2" name="Town">
+  <layer id="3" name="Roofs" width="2" height="2">
+   <data encoding="csv">{north}</data>
+  </layer>
+ </group>
+ <layer id="4" name="Sketch" width="2" height="2" visible="0">
+  <data encoding="csv">{far}</data>
+ </layer>
+ <imagelayer id="5" name="Sky">
+  <image source="sky.png" width="32" height="16"/>
+ </imagelayer>
+ <objectgroup id="6" name="Doors">
+  <object id="1" name="Inn" x="-16" y="-8" width="8" height="8"/>
+ </objectgroup>"#,
+                west = chunk(-1, 0, 0, 0),
+                south_east = chunk(1, 1, 15, 15),
+                north = chunk(0, -1, 3, 0),
+                far = chunk(5, 5, 0, 0),
+            ),
+            true,
+        );
+        let chunk_width = ChunkData::WIDTH;
+        let chunk_height = ChunkData::HEIGHT;
+        assert_eq!(
+            (contents.width, contents.height),
+            (3 * chunk_width, 3 * chunk_height)
+        );
+
+        let ground = &contents.tile_layers[0];
+        let occupied: Vec<usize> = ground
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| tile.tileset_ordinal != 0)
+            .map(|(i, _)| i)
+            .collect();
+        let width = contents.width as usize;
+        let height = contents.height as usize;
+        assert_eq!(
+            occupied,
+            vec![chunk_height as usize * width, width * height - 1]
+        );
+        let roofs = &contents.tile_layers[1];
+        assert_eq!(roofs.name, "Town Roofs");
+        assert_eq!(roofs.tiles[chunk_width as usize + 3].tile_id, 1);
+        assert_eq!(contents.tile_layers.len(), 2);
+
+        // Everything else moves with the tiles.
+        let origin_x = chunk_width as i16 * 8;
+        let origin_y = chunk_height as i16 * 8;
+        let sky = &contents.image_layers[0];
+        assert_eq!((sky.offset_x, sky.offset_y), (origin_x, origin_y));
+        let bounds = &contents.object_layers[0].objects[0].bounds;
+        assert_eq!((bounds.left, bounds.top), (origin_x - 16, origin_y - 8));
+    }
 }
@@ -0,0 +1,911 @@
+//! Typed Tiled custom properties, checked against the property types in the Tiled project.
+//!
+//! The `tiled` crate drops the `propertytype` attribute that says which enum or class
+//! a property belongs to, and can't load class properties at all,
+//! so we read properties straight from the XML and give `tiled` copies without class properties.
+//!
+//! Class properties are flattened into one property per member, named `property.member`,
+//! with the class's default values filled in for members that aren't set.
+//! Enum values are stored by name, whatever the enum's storage type.
+
+use crate::mac_assets::string_literal;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+use tiled::ResourceReader;
+use xml::reader::{EventReader, XmlEvent};
+use xml::EmitterConfig;
+
+/// Name of the Tiled project file in the assets directory.
+pub(crate) const PROJECT_FILE: &str = "AtelierEsri.tiled-project";
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PropertyValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    /// ARGB.
+    Color(u32),
+    String(String),
+    /// Relative to the file the property is in.
+    File(String),
+    /// Object ID, or 0 if unset.
+    Object(u32),
+    /// Value name, or comma-separated names for flag enums.
+    Enum(String),
+}
+
+impl PropertyValue {
+    /// Must match the `OBJ#`, `TMX `, and `TSX ` resource types and `TiledProperty::Type` in C++.
+    pub(crate) fn type_index(&self) -> i16 {
+        match self {
+            PropertyValue::Bool(_) => 0,
+            PropertyValue::Int(_) => 1,
+            PropertyValue::Float(_) => 2,
+            PropertyValue::Color(_) => 3,
+            PropertyValue::String(_) => 4,
+            PropertyValue::File(_) => 5,
+            PropertyValue::Object(_) => 6,
+            PropertyValue::Enum(_) => 7,
+        }
+    }
+
+    /// Tiled's name for the type, for error messages.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            PropertyValue::Bool(_) => "bool",
+            PropertyValue::Int(_) => "int",
+            PropertyValue::Float(_) => "float",
+            PropertyValue::Color(_) => "color",
+            PropertyValue::String(_) => "string",
+            PropertyValue::File(_) => "file",
+            PropertyValue::Object(_) => "object",
+            PropertyValue::Enum(_) => "enum",
+        }
+    }
+
+    /// Value as text, for resources.
+    pub(crate) fn text(&self) -> String {
+        match self {
+            PropertyValue::Bool(x) => x.to_string(),
+            PropertyValue::Int(x) => x.to_string(),
+            PropertyValue::Float(x) => x.to_string(),
+            PropertyValue::Color(x) => x.to_string(),
+            PropertyValue::String(x) => x.clone(),
+            PropertyValue::File(x) => x.clone(),
+            PropertyValue::Object(x) => x.to_string(),
+            PropertyValue::Enum(x) => x.clone(),
+        }
+    }
+
+    /// C++ initializer for the `value` field of `TiledProperty`.
+    fn cpp(&self) -> String {
+        match self {
+            PropertyValue::Bool(x) => x.to_string(),
+            PropertyValue::Int(x) => format!("int32_t{{{x}}}"),
+            PropertyValue::Float(x) => float_cpp(*x),
+            PropertyValue::Color(x) => format!("int32_t{{{x}}}", x = *x as i32),
+            PropertyValue::Object(x) => format!("int32_t{{{x}}}"),
+            PropertyValue::String(x) | PropertyValue::File(x) | PropertyValue::Enum(x) => {
+                format!("std::string{{{x}}}", x = string_literal(x))
+            }
+        }
+    }
+}
+
+/// C++ expression for a `float`. Rust's shortest round-trip formatting is also valid C++,
+/// once it has an `f` suffix to keep it from being a `double`.
+fn float_cpp(x: f32) -> String {
+    if x.is_nan() {
+        "std::numeric_limits<float>::quiet_NaN()".to_string()
+    } else if x.is_infinite() {
+        format!(
+            "{sign}std::numeric_limits<float>::infinity()",
+            sign = if x < 0.0 { "-" } else { "" }
+        )
+    } else {
+        format!("float{{{x:?}f}}")
+    }
+}
+
+/// Custom properties of one map, layer, tile, or object, sorted by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Properties(pub(crate) Vec<(String, PropertyValue)>);
+
+impl Properties {
+    pub(crate) fn get(&self, name: &str) -> Option<&PropertyValue> {
+        self.0
+            .binary_search_by(|(k, _)| k.as_str().cmp(name))
+            .ok()
+            .map(|i| &self.0[i].1)
+    }
+
+    pub(crate) fn bool(&self, name: &str) -> bool {
+        self.get(name) == Some(&PropertyValue::Bool(true))
+    }
+
+    /// Rez array of properties, as lines starting with `indent`.
+    pub(crate) fn rez(&self, indent: &str) -> Vec<String> {
+        let mut acc = Vec::<String>::new();
+        acc.push(format!("{indent}{{"));
+        for (name, value) in &self.0 {
+            acc.push(format!(
+                "{indent}    {name}, {type_index}, {text},",
+                name = string_literal(name),
+                type_index = value.type_index(),
+                text = string_literal(&value.text()),
+            ));
+        }
+        acc.push(format!("{indent}}},"));
+        acc
+    }
+
+    /// C++ initializer for a `std::vector<TiledProperty>`.
+    pub(crate) fn cpp(&self, indent: &str) -> String {
+        let properties: String = self
+            .0
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{indent}  TiledProperty{{\n\
+                    {indent}    .name = {name},\n\
+                    {indent}    .type = TiledProperty::Type({type_index}),\n\
+                    {indent}    .value = {value},\n\
+                    {indent}  }},\n",
+                    name = string_literal(name),
+                    type_index = value.type_index(),
+                    value = value.cpp(),
+                )
+            })
+            .collect();
+        format!("{{\n{properties}{indent}}}")
+    }
+}
+
+impl From<BTreeMap<String, PropertyValue>> for Properties {
+    fn from(properties: BTreeMap<String, PropertyValue>) -> Self {
+        Self(properties.into_iter().collect())
+    }
+}
+
+/// C++ declaration of `TiledProperty`.
+pub(crate) const HPP: &str = r#"/// Tiled custom property.
+/// Class properties are flattened into one property per member, named `property.member`.
+struct TiledProperty {
+  enum class Type : int16_t {
+    Bool = 0,
+    Int = 1,
+    Float = 2,
+    /// ARGB, stored as `int32_t`.
+    Color = 3,
+    String = 4,
+    /// Relative to the file the property is in, stored as `std::string`.
+    File = 5,
+    /// Object ID, stored as `int32_t`.
+    Object = 6,
+    /// Value name, or comma-separated names for flag enums, stored as `std::string`.
+    Enum = 7,
+  };
+
+  std::string name;
+  Type type;
+  std::variant<bool, int32_t, float, std::string> value;
+};
+"#;
+
+// region schema
+
+/// Custom property types from the Tiled project.
+#[derive(Debug, Default)]
+pub(crate) struct PropertyTypes {
+    types: BTreeMap<String, PropertyType>,
+}
+
+#[derive(Debug)]
+enum PropertyType {
+    Enum {
+        /// Stored as value indexes (or bit flags) instead of value names.
+        int_storage: bool,
+        values: Vec<String>,
+        flags: bool,
+    },
+    Class {
+        members: Vec<ClassMember>,
+    },
+}
+
+#[derive(Debug)]
+struct ClassMember {
+    name: String,
+    /// Tiled type name: `bool`, `int`, `class`, and so on.
+    type_name: String,
+    /// Enum or class name.
+    property_type: Option<String>,
+    /// Default value.
+    value: Value,
+}
+
+impl PropertyTypes {
+    /// Load property types from a Tiled project file.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let project: Value = serde_json::from_slice(&fs::read(path)?)?;
+        Self::from_project(&project).map_err(|e| {
+            anyhow::anyhow!(
+                "Couldn't read property types from {path}: {e}",
+                path = path.display()
+            )
+        })
+    }
+
+    fn from_project(project: &Value) -> anyhow::Result<Self> {
+        let mut types = BTreeMap::<String, PropertyType>::new();
+        let property_types = project["propertyTypes"]
+            .as_array()
+            .ok_or(anyhow::anyhow!("No propertyTypes list"))?;
+        for property_type in property_types {
+            let name = json_str(&property_type["name"])?;
+            let parsed = match json_str(&property_type["type"])?.as_str() {
+                "enum" => PropertyType::Enum {
+                    int_storage: property_type["storageType"] == "int",
+                    values: property_type["values"]
+                        .as_array()
+                        .ok_or(anyhow::anyhow!("Enum {name} has no values"))?
+                        .iter()
+                        .map(json_str)
+                        .collect::<anyhow::Result<_>>()?,
+                    flags: property_type["valuesAsFlags"] == true,
+                },
+                "class" => PropertyType::Class {
+                    members: property_type["members"]
+                        .as_array()
+                        .ok_or(anyhow::anyhow!("Class {name} has no members"))?
+                        .iter()
+                        .map(|member| {
+                            Ok(ClassMember {
+                                name: json_str(&member["name"])?,
+                                type_name: json_str(&member["type"])?,
+                                property_type: member["propertyType"].as_str().map(String::from),
+                                value: member["value"].clone(),
+                            })
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                },
+                other => anyhow::bail!("Property type {name} has unknown kind {other}"),
+            };
+            types.insert(name, parsed);
+        }
+        Ok(Self { types })
+    }
+
+    /// Add default values of a class's members. Does nothing if `class` isn't a class.
+    fn add_class_defaults(
+        &self,
+        class: &str,
+        prefix: &str,
+        out: &mut BTreeMap<String, PropertyValue>,
+    ) -> anyhow::Result<()> {
+        let Some(PropertyType::Class { members }) = self.types.get(class) else {
+            return Ok(());
+        };
+        for member in members {
+            let name = format!("{prefix}{member}", member = member.name);
+            if member.type_name == "class" {
+                let member_class = member.property_type.as_deref().ok_or(anyhow::anyhow!(
+                    "Class member {class}.{member} has no class",
+                    member = member.name
+                ))?;
+                self.add_class_defaults(member_class, &format!("{name}."), out)?;
+                continue;
+            }
+            let text = match &member.value {
+                Value::String(s) => s.clone(),
+                Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            let value = self.value(
+                &name,
+                &member.type_name,
+                member.property_type.as_deref(),
+                &text,
+            )?;
+            out.insert(name, value);
+        }
+        Ok(())
+    }
+
+    /// Convert a non-class property value from its XML or JSON text.
+    fn value(
+        &self,
+        name: &str,
+        type_name: &str,
+        property_type: Option<&str>,
+        text: &str,
+    ) -> anyhow::Result<PropertyValue> {
+        if let Some(property_type) = property_type {
+            return self.enum_value(name, type_name, property_type, text);
+        }
+        let invalid = |e: &dyn std::fmt::Display| {
+            anyhow::anyhow!("Property {name} has invalid {type_name} value {text:?}: {e}")
+        };
+        Ok(match type_name {
+            "bool" => PropertyValue::Bool(text.parse().map_err(|e| invalid(&e))?),
+            "int" => PropertyValue::Int(text.parse().map_err(|e| invalid(&e))?),
+            "float" => PropertyValue::Float(text.parse().map_err(|e| invalid(&e))?),
+            "color" => PropertyValue::Color(parse_color(text).map_err(|e| invalid(&e))?),
+            "string" => PropertyValue::String(text.to_string()),
+            "file" => PropertyValue::File(text.to_string()),
+            "object" => PropertyValue::Object(text.parse().map_err(|e| invalid(&e))?),
+            other => anyhow::bail!("Property {name} has unknown type {other}"),
+        })
+    }
+
+    fn enum_value(
+        &self,
+        name: &str,
+        type_name: &str,
+        enum_name: &str,
+        text: &str,
+    ) -> anyhow::Result<PropertyValue> {
+        let Some(PropertyType::Enum {
+            int_storage,
+            values,
+            flags,
+        }) = self.types.get(enum_name)
+        else {
+            anyhow::bail!(
+                "Property {name} has type {enum_name}, which isn't an enum in {PROJECT_FILE}"
+            );
+        };
+        let expected_type = if *int_storage { "int" } else { "string" };
+        if type_name != expected_type {
+            anyhow::bail!("Property {name} of enum {enum_name} should be stored as {expected_type}, not {type_name}");
+        }
+        let out_of_range = || {
+            anyhow::anyhow!("Property {name} has value {text:?}, which isn't in enum {enum_name}")
+        };
+
+        let names = if *int_storage {
+            let index: usize = text.parse().map_err(|_| out_of_range())?;
+            if *flags {
+                if index >> values.len() != 0 {
+                    return Err(out_of_range());
+                }
+                values
+                    .iter()
+                    .enumerate()
+                    .filter(|(bit, _)| index & (1 << bit) != 0)
+                    .map(|(_, value)| value.as_str())
+                    .collect::<Vec<_>>()
+            } else {
+                vec![values.get(index).ok_or_else(out_of_range)?.as_str()]
+            }
+        } else {
+            let names = if *flags {
+                text.split(',').filter(|s| !s.is_empty()).collect()
+            } else {
+                vec![text]
+            };
+            if names.iter().any(|n| !values.iter().any(|v| v == n)) {
+                return Err(out_of_range());
+            }
+            names
+        };
+        Ok(PropertyValue::Enum(names.join(",")))
+    }
+
+    /// Convert the `property` children of a `properties` element,
+    /// checking them against the class they belong to if there is one.
+    fn add_properties(
+        &self,
+        properties: &Element,
+        class: Option<&str>,
+        prefix: &str,
+        out: &mut BTreeMap<String, PropertyValue>,
+    ) -> anyhow::Result<()> {
+        for property in properties.children("property") {
+            let name = property.attr("name").unwrap_or_default();
+            let type_name = property.attr("type").unwrap_or("string");
+            let property_type = property.attr("propertytype");
+            let full_name = format!("{prefix}{name}");
+
+            if let Some(class) = class {
+                let Some(PropertyType::Class { members }) = self.types.get(class) else {
+                    anyhow::bail!("Property {full_name} is in {class}, which isn't a class");
+                };
+                let member = members
+                    .iter()
+                    .find(|m| m.name == name)
+                    .ok_or(anyhow::anyhow!("Class {class} has no member {name}"))?;
+                if member.type_name != type_name || member.property_type.as_deref() != property_type
+                {
+                    anyhow::bail!(
+                        "Property {full_name} doesn't have the type of class member {class}.{name}"
+                    );
+                }
+            }
+
+            if type_name == "class" {
+                let member_class = property_type.ok_or(anyhow::anyhow!(
+                    "Class property {full_name} doesn't say which class it is"
+                ))?;
+                if !matches!(
+                    self.types.get(member_class),
+                    Some(PropertyType::Class { .. })
+                ) {
+                    anyhow::bail!("Property {full_name} has type {member_class}, which isn't a class in {PROJECT_FILE}");
+                }
+                let member_prefix = format!("{full_name}.");
+                self.add_class_defaults(member_class, &member_prefix, out)?;
+                if let Some(members) = property.child("properties") {
+                    self.add_properties(members, Some(member_class), &member_prefix, out)?;
+                }
+                continue;
+            }
+
+            // Multiline strings are stored as element text instead of an attribute.
+            let text = property.attr("value").unwrap_or(&property.text);
+            let value = self.value(&full_name, type_name, property_type, text)?;
+            out.insert(full_name, value);
+        }
+        Ok(())
+    }
+
+    /// Properties of a map, layer, tile, or object element:
+    /// its class's defaults, overridden by its own `properties` child if it has one.
+    fn element_properties(&self, element: &Element) -> anyhow::Result<Properties> {
+        let mut out = BTreeMap::<String, PropertyValue>::new();
+        // Older Tiled versions call it `type`.
+        if let Some(class) = element.attr("class").or(element.attr("type")) {
+            self.add_class_defaults(class, "", &mut out)?;
+        }
+        if let Some(properties) = element.child("properties") {
+            self.add_properties(properties, None, "", &mut out)?;
+        }
+        Ok(out.into())
+    }
+}
+
+fn json_str(value: &Value) -> anyhow::Result<String> {
+    value
+        .as_str()
+        .map(String::from)
+        .ok_or(anyhow::anyhow!("Expected a string, got {value}"))
+}
+
+/// Tiled writes `#AARRGGBB` or `#RRGGBB`, or nothing for an unset color (which we treat as transparent).
+fn parse_color(text: &str) -> anyhow::Result<u32> {
+    let hex = text.trim_start_matches('#');
+    Ok(match hex.len() {
+        0 => 0,
+        6 => 0xff000000 | u32::from_str_radix(hex, 16)?,
+        8 => u32::from_str_radix(hex, 16)?,
+        _ => anyhow::bail!("Not a color"),
+    })
+}
+
+// endregion schema
+
+// region XML
+
+/// Just enough of an XML DOM to find properties.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: BTreeMap<String, String>,
+    elements: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn parse(xml: &[u8]) -> anyhow::Result<Self> {
+        let mut stack = vec![Element::default()];
+        for event in EventReader::new(xml) {
+            match event? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|a| (a.name.local_name, a.value))
+                        .collect(),
+                    ..Default::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().expect("Unbalanced XML");
+                    stack
+                        .last_mut()
+                        .expect("Unbalanced XML")
+                        .elements
+                        .push(element);
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        stack
+            .pop()
+            .and_then(|document| document.elements.into_iter().next())
+            .ok_or(anyhow::anyhow!("Empty XML document"))
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements.iter().filter(move |e| e.name == name)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements.iter().find(|e| e.name == name)
+    }
+
+    fn id(&self) -> anyhow::Result<u32> {
+        Ok(self
+            .attr("id")
+            .ok_or(anyhow::anyhow!(
+                "{name} element has no ID",
+                name = self.name
+            ))?
+            .parse()?)
+    }
+}
+
+/// Copy a TMX or TSX file without any class properties.
+fn strip_class_properties(xml: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut writer = EmitterConfig::new().create_writer(Vec::<u8>::new());
+    // Depth within a class property we're skipping.
+    let mut skip_depth = 0;
+    for event in EventReader::new(xml) {
+        let event = event?;
+        match &event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let is_class_property = name.local_name == "property"
+                    && attributes
+                        .iter()
+                        .any(|a| a.name.local_name == "type" && a.value == "class");
+                if skip_depth > 0 || is_class_property {
+                    skip_depth += 1;
+                    continue;
+                }
+            }
+            XmlEvent::EndElement { .. } if skip_depth > 0 => {
+                skip_depth -= 1;
+                continue;
+            }
+            _ if skip_depth > 0 => continue,
+            _ => {}
+        }
+        if let Some(event) = event.as_writer_event() {
+            writer.write(event)?;
+        }
+    }
+    Ok(writer.into_inner())
+}
+
+/// Gives `tiled` copies of TMX and TSX files without class properties, which it can't load.
+pub(crate) struct ClasslessReader;
+
+impl ResourceReader for ClasslessReader {
+    type Resource = Cursor<Vec<u8>>;
+    type Error = io::Error;
+
+    fn read_from(&mut self, path: &Path) -> Result<Self::Resource, Self::Error> {
+        let xml = fs::read(path)?;
+        strip_class_properties(&xml)
+            .map(Cursor::new)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+pub(crate) type TiledLoader = tiled::Loader<tiled::DefaultResourceCache, ClasslessReader>;
+
+pub(crate) fn new_loader() -> TiledLoader {
+    tiled::Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), ClasslessReader)
+}
+
+// endregion XML
+
+/// Typed properties of everything in a map that can have them.
+#[derive(Debug, Default)]
+pub(crate) struct MapProperties {
+    pub(crate) map: Properties,
+    /// By layer ID.
+    pub(crate) layers: BTreeMap<u32, Properties>,
+    /// By object ID.
+    pub(crate) objects: BTreeMap<u32, Properties>,
+    /// By tileset name, then tile ID within the tileset. Only tiles with properties are present.
+    pub(crate) tiles: BTreeMap<String, BTreeMap<u32, Properties>>,
+}
+
+impl MapProperties {
+    /// Read properties from a TMX file and the TSX files it uses.
+    pub(crate) fn read(types: &PropertyTypes, src: &Path) -> anyhow::Result<Self> {
+        let map = Element::parse(&fs::read(src)?)?;
+        let mut properties = MapProperties {
+            map: types.element_properties(&map)?,
+            ..Default::default()
+        };
+        properties.add_layers(types, &map)?;
+
+        for tileset in map.children("tileset") {
+            match tileset.attr("source") {
+                Some(source) => {
+                    let tsx_path = src
+                        .parent()
+                        .ok_or(anyhow::anyhow!("Map has no parent directory"))?
+                        .join(source);
+                    let tsx = Element::parse(&fs::read(&tsx_path)?)?;
+                    properties.add_tileset(types, &tsx)?;
+                }
+                None => properties.add_tileset(types, tileset)?,
+            }
+        }
+        Ok(properties)
+    }
+
+    /// Layers can be nested in group layers.
+    fn add_layers(&mut self, types: &PropertyTypes, parent: &Element) -> anyhow::Result<()> {
+        for layer in &parent.elements {
+            if !matches!(
+                layer.name.as_str(),
+                "layer" | "objectgroup" | "imagelayer" | "group"
+            ) {
+                continue;
+            }
+            self.layers
+                .insert(layer.id()?, types.element_properties(layer)?);
+            for object in layer.children("object") {
+                if object.attr("template").is_some() {
+                    anyhow::bail!("Object templates aren't supported");
+                }
+                self.objects
+                    .insert(object.id()?, types.element_properties(object)?);
+            }
+            self.add_layers(types, layer)?;
+        }
+        Ok(())
+    }
+
+    fn add_tileset(&mut self, types: &PropertyTypes, tileset: &Element) -> anyhow::Result<()> {
+        let name = tileset
+            .attr("name")
+            .ok_or(anyhow::anyhow!("Tileset has no name"))?;
+        let tiles = self.tiles.entry(name.to_string()).or_default();
+        for tile in tileset.children("tile") {
+            let properties = types.element_properties(tile)?;
+            if !properties.0.is_empty() {
+                tiles.insert(tile.id()?, properties);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn types() -> PropertyTypes {
+        PropertyTypes::from_project(&json!({
+            "propertyTypes": [
+                {
+                    "name": "Facing",
+                    "type": "enum",
+                    "storageType": "string",
+                    "values": ["north", "south"],
+                    "valuesAsFlags": false,
+                },
+                {
+                    "name": "Senses",
+                    "type": "enum",
+                    "storageType": "int",
+                    "values": ["sight", "hearing", "smell"],
+                    "valuesAsFlags": true,
+                },
+                {
+                    "name": "Door",
+                    "type": "class",
+                    "members": [
+                        {"name": "locked", "type": "bool", "value": false},
+                        {"name": "target_map", "type": "string", "value": "Village"},
+                        {"name": "facing", "type": "string", "propertyType": "Facing", "value": "south"},
+                    ],
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn properties(xml: &str) -> anyhow::Result<Properties> {
+        types().element_properties(&Element::parse(xml.as_bytes())?)
+    }
+
+    #[test]
+    fn test_plain_properties() {
+        let properties = properties(
+            r##"<object id="1">
+              <properties>
+                <property name="b" type="bool" value="true"/>
+                <property name="a" type="int" value="3"/>
+                <property name="c" type="color" value="#ff102030"/>
+                <property name="d" value="hi"/>
+              </properties>
+            </object>"##,
+        )
+        .unwrap();
+        assert_eq!(
+            properties.0,
+            vec![
+                ("a".to_string(), PropertyValue::Int(3)),
+                ("b".to_string(), PropertyValue::Bool(true)),
+                ("c".to_string(), PropertyValue::Color(0xff102030)),
+                ("d".to_string(), PropertyValue::String("hi".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rez_and_cpp_escaping() {
+        let properties = Properties(vec![
+            (
+                "say".to_string(),
+                PropertyValue::String("\"Oh…\\ what??\"\n".to_string()),
+            ),
+            ("nan".to_string(), PropertyValue::Float(f32::NAN)),
+            ("tiny".to_string(), PropertyValue::Float(1e-7)),
+            ("cold".to_string(), PropertyValue::Float(f32::NEG_INFINITY)),
+        ]);
+        assert_eq!(
+            properties.rez(""),
+            vec![
+                "{",
+                r#"    "say", 4, "\"Oh\311\\ what\?\?\"\012","#,
+                r#"    "nan", 2, "NaN","#,
+                r#"    "tiny", 2, "0.0000001","#,
+                r#"    "cold", 2, "-inf","#,
+                "},",
+            ]
+        );
+        let values: Vec<String> = properties.0.iter().map(|(_, value)| value.cpp()).collect();
+        assert_eq!(
+            values,
+            vec![
+                r#"std::string{"\"Oh\311\\ what\?\?\"\012"}"#,
+                "std::numeric_limits<float>::quiet_NaN()",
+                "float{1e-7f}",
+                "-std::numeric_limits<float>::infinity()",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enum_properties() {
+        let properties = properties(
+            r#"<object id="1">
+              <properties>
+                <property name="facing" propertytype="Facing" value="north"/>
+                <property name="senses" type="int" propertytype="Senses" value="5"/>
+              </properties>
+            </object>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            properties.get("facing"),
+            Some(&PropertyValue::Enum("north".to_string()))
+        );
+        assert_eq!(
+            properties.get("senses"),
+            Some(&PropertyValue::Enum("sight,smell".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_bad_enum_value() {
+        assert!(properties(
+            r#"<object id="1">
+              <properties>
+                <property name="facing" propertytype="Facing" value="up"/>
+              </properties>
+            </object>"#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_unknown_property_type() {
+        assert!(properties(
+            r#"<object id="1">
+              <properties>
+                <property name="x" propertytype="Nope" value="up"/>
+              </properties>
+            </object>"#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_class_property_defaults_and_overrides() {
+        let properties = properties(
+            r#"<object id="1">
+              <properties>
+                <property name="door" type="class" propertytype="Door">
+                  <properties>
+                    <property name="locked" type="bool" value="true"/>
+                  </properties>
+                </property>
+              </properties>
+            </object>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            properties.0,
+            vec![
+                (
+                    "door.facing".to_string(),
+                    PropertyValue::Enum("south".to_string())
+                ),
+                ("door.locked".to_string(), PropertyValue::Bool(true)),
+                (
+                    "door.target_map".to_string(),
+                    PropertyValue::String("Village".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_member_type_mismatch() {
+        assert!(properties(
+            r#"<object id="1">
+              <properties>
+                <property name="door" type="class" propertytype="Door">
+                  <properties>
+                    <property name="locked" type="int" value="1"/>
+                  </properties>
+                </property>
+              </properties>
+            </object>"#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_object_class_defaults() {
+        let properties = properties(
+            r#"<object id="1" class="Door">
+              <properties>
+                <property name="target_map" value="Castle"/>
+              </properties>
+            </object>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            properties.get("target_map"),
+            Some(&PropertyValue::String("Castle".to_string()))
+        );
+        assert_eq!(properties.get("locked"), Some(&PropertyValue::Bool(false)));
+    }
+
+    #[test]
+    fn test_strip_class_properties() {
+        let stripped = strip_class_properties(
+            br#"<object id="1"><properties><property name="door" type="class" propertytype="Door"><properties><property name="locked" type="bool" value="true"/></properties></property><property name="a" type="int" value="1"/></properties></object>"#,
+        )
+        .unwrap();
+        let stripped = String::from_utf8(stripped).unwrap();
+        assert!(!stripped.contains("door"));
+        assert!(!stripped.contains("locked"));
+        assert!(stripped.contains(r#"name="a""#));
+    }
+}
@@ -1,4 +1,6 @@
 mod assets;
+mod cinematic_script;
+mod cinematics;
 mod ext;
 mod fsutil;
 mod grey_quantizer;
@@ -10,9 +12,11 @@ mod lo5;
 mod mac;
 mod mac_assets;
 mod mac_icon;
+mod maps;
 mod palettes;
 mod pico8;
 mod pokepak;
+mod screenplay;
 mod tileshred;
 mod unisprite;
 
@@ -106,6 +110,75 @@ enum Commands {
         #[clap(value_parser)]
         output: PathBuf,
     },
+    /// Check an items JSON file for problems that its schema can't catch.
+    ItemsCheck {
+        /// Input JSON file.
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Input assets directory, used to look up item icons.
+        #[clap(value_parser)]
+        assets: PathBuf,
+    },
+    /// Render every recipe in an items JSON file to SVG and PNG images.
+    ItemsRender {
+        /// Input JSON file.
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Input assets directory, used for element and item icons.
+        #[clap(value_parser)]
+        assets: PathBuf,
+        /// Output directory.
+        #[clap(value_parser)]
+        output: PathBuf,
+    },
+    /// Try every way of placing some ingredients on every recipe in an items JSON file,
+    /// and report the best effects, locked nodes, and final quality for each recipe.
+    ItemsSimulate {
+        /// Input JSON file.
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// JSON file with an array of ingredient items.
+        #[clap(value_parser)]
+        ingredients: PathBuf,
+    },
+    /// Generate Rust code for the WASM-4 edition from every Tiled map in an assets directory.
+    MapsCode {
+        /// Input assets directory. Maps are read from its `maps` subdirectory.
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Output Rust file.
+        #[clap(value_parser)]
+        output: PathBuf,
+    },
+    /// Generate Rust code for the WASM-4 edition from every cinematic script in an assets directory.
+    CinematicsCode {
+        /// Input assets directory. Scripts are read from its `cinematics` subdirectory,
+        /// and character portraits from its `avatars` subdirectory.
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Output Rust file.
+        #[clap(value_parser)]
+        output: PathBuf,
+    },
+    /// Write cinematic scripts as JSON, for tools and editions without generated code of their own.
+    CinematicsJson {
+        /// Input assets directory. Scripts are read from its `cinematics` subdirectory.
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Output JSON file.
+        #[clap(value_parser)]
+        output: PathBuf,
+    },
+    /// Convert a screenplay-style Markdown story draft to a cinematic script,
+    /// and list the lines that still need a writer's attention, like entrances without a mood.
+    CinematicsImport {
+        /// Input Markdown file.
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Output `.aecinematic` file.
+        #[clap(value_parser)]
+        output: PathBuf,
+    },
     /// Generate Mac header and resource file for assets.
     MacAssets {
         /// Input assets directory.
@@ -156,6 +229,25 @@ fn main() -> anyhow::Result<()> {
         } => tileshred::convert(tile_width, tile_height, input.as_path(), output.as_path())?,
         Commands::ItemsSchema { output } => items::schema(output.as_path())?,
         Commands::ItemsCode { input, output } => items::code(input.as_path(), output.as_path())?,
+        Commands::ItemsCheck { input, assets } => items::check(input.as_path(), assets.as_path())?,
+        Commands::ItemsRender {
+            input,
+            assets,
+            output,
+        } => items::render(input.as_path(), assets.as_path(), output.as_path())?,
+        Commands::ItemsSimulate { input, ingredients } => {
+            items::simulate(input.as_path(), ingredients.as_path())?
+        }
+        Commands::MapsCode { input, output } => maps::code(input.as_path(), output.as_path())?,
+        Commands::CinematicsCode { input, output } => {
+            cinematics::code(input.as_path(), output.as_path())?
+        }
+        Commands::CinematicsJson { input, output } => {
+            cinematics::json(input.as_path(), output.as_path())?
+        }
+        Commands::CinematicsImport { input, output } => {
+            screenplay::import(input.as_path(), output.as_path())?
+        }
         Commands::MacAssets { input, output } => {
             mac_assets::generate(input.as_path(), output.as_path())?
         }
@@ -0,0 +1,533 @@
+//! Generate WASM-4 map data from Tiled TMX maps and their TSX tilesets.
+
+use crate::mac_assets::tiled::{
+    read_map, tile_animations, DrawnLayer, MapContents, MapImageLayer, MapObject, MapObjectLayer,
+    MapObjectShape, TMXTileLayer,
+};
+use crate::mac_assets::tiled_properties::{
+    new_loader, Properties, PropertyTypes, PropertyValue, PROJECT_FILE,
+};
+use crate::unisprite;
+use convert_case::{Case, Casing};
+use glob::glob;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tiled::Tileset;
+
+/// Must match `gfx::TILE_SOLID` in the WASM-4 edition.
+const TILE_SOLID: u8 = 1 << 0;
+
+/// Must match `gfx::TILE_FLIP_*` in the WASM-4 edition.
+const TILE_FLIP_H: u8 = 1 << 0;
+const TILE_FLIP_V: u8 = 1 << 1;
+const TILE_FLIP_D: u8 = 1 << 2;
+
+/// Generate a Rust module with `Tileset`, `Layer`, `ImageLayer`, `Region`, and map `Properties` constants
+/// for every map in `maps/*.tmx` under the assets directory,
+/// plus a `MapLayer` slice of each map's tile and image layers in drawing order.
+/// Output is not formatted; run `rustfmt` on it if you want to read it.
+pub fn code(asset_base_dir: &Path, output_path: &Path) -> anyhow::Result<()> {
+    let property_types = PropertyTypes::load(&asset_base_dir.join(PROJECT_FILE))?;
+    let mut loader = new_loader();
+
+    // Tilesets may be shared by more than one map. Keyed by constant name.
+    let mut tilesets = BTreeMap::<String, String>::new();
+    let mut maps = Vec::<String>::new();
+
+    let pattern = asset_base_dir.join("maps").join("*.tmx");
+    for glob_result in glob(&pattern.to_string_lossy())? {
+        let src = glob_result?;
+        let name = src
+            .file_stem()
+            .ok_or(anyhow::anyhow!("Couldn't get file stem for map"))?
+            .to_string_lossy()
+            .to_string();
+        let contents = read_map(&mut loader, &property_types, &src)
+            .map_err(|e| anyhow::anyhow!("Couldn't load map {src}: {e}", src = src.display()))?;
+
+        let tileset = match contents.map.tilesets() {
+            [tileset] => tileset,
+            _ => anyhow::bail!("Map {name} must use exactly one tileset"),
+        };
+        let tileset_const = tileset_const(tileset);
+        if !tilesets.contains_key(&tileset_const) {
+            tilesets.insert(
+                tileset_const.clone(),
+                tileset_to_rust(tileset, contents.tile_properties.get(&tileset.name))?,
+            );
+        }
+
+        maps.push(map_to_rust(&name, &tileset_const, &contents)?);
+    }
+
+    let mut acc = vec![
+        "// Generated from Tiled maps by `aetools maps-code`. Do not edit.\n".to_string(),
+        "use crate::asset_data;".to_string(),
+        "use crate::gfx::{\
+        ImageLayer, Layer, Lo5SplitSprite, MapLayer, Properties, PropertyValue, Region, RleRows, \
+        Shape, Tileset\
+        };\n"
+            .to_string(),
+    ];
+    acc.extend(tilesets.into_values());
+    acc.extend(maps);
+
+    let mut rs = BufWriter::new(File::create(output_path)?);
+    write!(rs, "{src}", src = acc.join("\n"))?;
+    Ok(())
+}
+
+/// Upper snake case, for constant names.
+fn const_name(name: &str) -> String {
+    name.to_case(Case::UpperSnake)
+}
+
+fn tileset_const(tileset: &Arc<Tileset>) -> String {
+    const_name(&tileset.name)
+}
+
+fn tileset_to_rust(
+    tileset: &Arc<Tileset>,
+    tile_properties: Option<&BTreeMap<u32, Properties>>,
+) -> anyhow::Result<String> {
+    let image = tileset.image.as_ref().ok_or(anyhow::anyhow!(
+        "No image for tileset {tileset}",
+        tileset = tileset.name
+    ))?;
+    // The WASM-4 build script runs `w4 png2src` on the lo5 split of each image in `assets`,
+    // which names constants after the upper-cased file name.
+    let image_const = image
+        .source
+        .file_stem()
+        .ok_or(anyhow::anyhow!("Couldn't get file stem for tileset image"))?
+        .to_string_lossy()
+        .to_uppercase();
+
+    let tile_properties = tile_properties.cloned().unwrap_or_default();
+    let mut flags = Vec::<u8>::new();
+    for id in 0..tileset.tilecount {
+        let mut tile_flags = 0;
+        if let Some(properties) = tile_properties.get(&id) {
+            if properties.bool("solid") {
+                tile_flags |= TILE_SOLID;
+            }
+        }
+        flags.push(tile_flags);
+    }
+    // The rest have no flags.
+    while flags.last() == Some(&0) {
+        flags.pop();
+    }
+
+    // Tile IDs are shifted up by one, as in layers.
+    let mut animations = String::new();
+    for (id, frames) in tile_animations(tileset)? {
+        // Tiles past 254 can't be placed in a layer, so there's no point animating them.
+        let Ok(tile_id) = u8::try_from(id + 1) else {
+            continue;
+        };
+        let mut frames_rust = String::new();
+        for (frame_id, ticks) in frames {
+            let frame_id = u8::try_from(frame_id + 1).map_err(|_| {
+                anyhow::anyhow!(
+                    "Animation for tile {id} of tileset {tileset} uses tile {frame_id}, \
+                    but WASM-4 layers only have room for 255 tiles",
+                    tileset = tileset.name,
+                )
+            })?;
+            frames_rust.push_str(&format!("({frame_id}, {ticks}), "));
+        }
+        animations.push_str(&format!("({tile_id}, &[{frames_rust}]), "));
+    }
+
+    Ok(format!(
+        "pub const {const_name}: Tileset = Tileset {{ \
+        tile_width: {tile_width}, \
+        tile_height: {tile_height}, \
+        image: &Lo5SplitSprite {{ \
+        w: asset_data::{image_const}_LO4_WIDTH, \
+        h: asset_data::{image_const}_LO4_HEIGHT, \
+        lo4: &asset_data::{image_const}_LO4, \
+        hi2: &asset_data::{image_const}_HI2, \
+        }}, \
+        flags: &[{flags}], \
+        tile_properties: &[{tile_properties}], \
+        animations: &[{animations}], \
+        }};\n",
+        const_name = tileset_const(tileset),
+        tile_width = tileset.tile_width,
+        tile_height = tileset.tile_height,
+        flags = flags.iter().map(|f| format!("{f}, ")).collect::<String>(),
+        // Tile IDs are shifted up by one, as in layers.
+        tile_properties = tile_properties
+            .iter()
+            .filter(|(_, properties)| !properties.0.is_empty())
+            .map(|(id, properties)| format!(
+                "({tile_id}, {properties}), ",
+                tile_id = id + 1,
+                properties = properties_to_rust(properties),
+            ))
+            .collect::<String>(),
+    ))
+}
+
+fn map_to_rust(name: &str, tileset_const: &str, contents: &MapContents) -> anyhow::Result<String> {
+    let mut acc = vec![format!(
+        "pub const {const_name}: Properties = {properties};\n",
+        const_name = const_name(&format!("{name}_properties")),
+        properties = properties_to_rust(&contents.properties),
+    )];
+    for tile_layer in &contents.tile_layers {
+        acc.push(tile_layer_to_rust(name, tileset_const, tile_layer)?);
+    }
+    for image_layer in &contents.image_layers {
+        acc.push(image_layer_to_rust(name, image_layer)?);
+    }
+    for object_layer in &contents.object_layers {
+        acc.push(object_layer_to_rust(name, object_layer));
+    }
+    acc.push(format!(
+        "pub const {const_name}: &[MapLayer] = &[{layers}];\n",
+        const_name = const_name(&format!("{name}_layers")),
+        layers = contents
+            .drawn_layers
+            .iter()
+            .map(|layer| match *layer {
+                DrawnLayer::Tiles(i) => format!(
+                    "MapLayer::Tiles(&{const_name}), ",
+                    const_name = layer_const(name, &contents.tile_layers[i].name)
+                ),
+                DrawnLayer::Image(i) => format!(
+                    "MapLayer::Image(&{const_name}), ",
+                    const_name = layer_const(name, &contents.image_layers[i].name)
+                ),
+            })
+            .collect::<String>(),
+    ));
+    Ok(acc.join("\n"))
+}
+
+/// Name of the constant for a layer of a map.
+fn layer_const(map_name: &str, layer_name: &str) -> String {
+    const_name(&format!("{map_name}_{layer_name}"))
+}
+
+fn tile_layer_to_rust(
+    map_name: &str,
+    tileset_const: &str,
+    tile_layer: &TMXTileLayer,
+) -> anyhow::Result<String> {
+    let mut tiles = Vec::<u8>::new();
+    let mut flips = Vec::<u8>::new();
+    for tile in &tile_layer.tiles {
+        if tile.tileset_ordinal == 0 {
+            tiles.push(0);
+            flips.push(0);
+            continue;
+        }
+        // 0 means no tile, so tile IDs are shifted up by one.
+        let tile_id = u8::try_from(tile.tile_id + 1).map_err(|_| {
+            anyhow::anyhow!(
+                "Layer {layer_name} of map {map_name} uses tile {tile_id}, \
+                but WASM-4 layers only have room for 255 tiles",
+                layer_name = tile_layer.name,
+                tile_id = tile.tile_id,
+            )
+        })?;
+        tiles.push(tile_id);
+        let mut flip = 0;
+        if tile.flip_h {
+            flip |= TILE_FLIP_H;
+        }
+        if tile.flip_v {
+            flip |= TILE_FLIP_V;
+        }
+        if tile.flip_d {
+            flip |= TILE_FLIP_D;
+        }
+        flips.push(flip);
+    }
+    let width = tile_layer.width as usize;
+    let tiles = rle_rows_to_rust(&tiles, width)?;
+    let flips = rle_rows_to_rust(&flips, width)?;
+
+    Ok(format!(
+        "pub const {const_name}: Layer = Layer {{ \
+        width_tiles: {width}, \
+        height_tiles: {height}, \
+        tileset: &{tileset_const}, \
+        offset_x: {offset_x}, \
+        offset_y: {offset_y}, \
+        opacity: {opacity}, \
+        tiles: {tiles}, \
+        flips: {flips}, \
+        properties: {properties}, \
+        }};\n",
+        const_name = layer_const(map_name, &tile_layer.name),
+        width = tile_layer.width,
+        height = tile_layer.height,
+        offset_x = tile_layer.offset_x,
+        offset_y = tile_layer.offset_y,
+        opacity = tile_layer.opacity,
+        properties = properties_to_rust(&tile_layer.properties),
+    ))
+}
+
+/// Image layers become unisprites, since they're usually too big for the lo5 split.
+fn image_layer_to_rust(map_name: &str, image_layer: &MapImageLayer) -> anyhow::Result<String> {
+    let image = image::open(&image_layer.image)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Couldn't load image {path} for image layer {layer_name} of map {map_name}: {e}",
+                path = image_layer.image.display(),
+                layer_name = image_layer.name,
+            )
+        })?
+        .to_luma_alpha8();
+    let sprite = unisprite::encode_image(&image);
+
+    Ok(format!(
+        "pub const {const_name}: ImageLayer = ImageLayer {{ \
+        image: &aesprite::Unisprite {{ w: {w}, h: {h}, luma: &{luma:?}, alpha: &{alpha:?}, }}, \
+        offset_x: {offset_x}, \
+        offset_y: {offset_y}, \
+        parallax_x: {parallax_x}, \
+        parallax_y: {parallax_y}, \
+        opacity: {opacity}, \
+        properties: {properties}, \
+        }};\n",
+        const_name = layer_const(map_name, &image_layer.name),
+        w = sprite.w,
+        h = sprite.h,
+        luma = sprite.luma,
+        alpha = sprite.alpha,
+        offset_x = image_layer.offset_x,
+        offset_y = image_layer.offset_y,
+        parallax_x = image_layer.parallax_x,
+        parallax_y = image_layer.parallax_y,
+        opacity = image_layer.opacity,
+        properties = properties_to_rust(&image_layer.properties),
+    ))
+}
+
+/// Run-length encode a grid of bytes with PackBits, a row at a time.
+/// Returns the offset of each row's first packet, then the packets.
+/// If every value is 0, both are empty, since the decoder treats missing rows as all 0.
+/// See `gfx::RleRows` in the WASM-4 edition for the format.
+fn rle_rows(values: &[u8], width: usize) -> anyhow::Result<(Vec<u16>, Vec<u8>)> {
+    if values.iter().all(|v| *v == 0) {
+        return Ok((vec![], vec![]));
+    }
+
+    let mut rows = Vec::<u16>::new();
+    let mut packets = Vec::<u8>::new();
+    for row in values.chunks(width) {
+        rows.push(u16::try_from(packets.len()).map_err(|_| {
+            anyhow::anyhow!("Run-length encoded layer is too big for 16-bit row offsets")
+        })?);
+        packbits(row, &mut packets);
+    }
+    Ok((rows, packets))
+}
+
+fn packbits(values: &[u8], packets: &mut Vec<u8>) {
+    const MAX_LITERAL: usize = 128;
+    const MAX_REPEAT: usize = 129;
+
+    let mut literal = Vec::<u8>::new();
+    let flush = |literal: &mut Vec<u8>, packets: &mut Vec<u8>| {
+        if !literal.is_empty() {
+            packets.push((literal.len() - 1) as u8);
+            packets.append(literal);
+        }
+    };
+
+    let mut i = 0;
+    while i < values.len() {
+        let value = values[i];
+        let len = values[i..]
+            .iter()
+            .take(MAX_REPEAT)
+            .take_while(|v| **v == value)
+            .count();
+        if len >= 2 {
+            flush(&mut literal, packets);
+            packets.push((len + 126) as u8);
+            packets.push(value);
+        } else {
+            literal.push(value);
+            if literal.len() == MAX_LITERAL {
+                flush(&mut literal, packets);
+            }
+        }
+        i += len;
+    }
+    flush(&mut literal, packets);
+}
+
+fn rle_rows_to_rust(values: &[u8], width: usize) -> anyhow::Result<String> {
+    let (rows, packets) = rle_rows(values, width)?;
+    Ok(format!(
+        "RleRows {{ rows: &[{rows}], packets: &[{packets}] }}",
+        rows = rows.iter().map(|r| format!("{r}, ")).collect::<String>(),
+        packets = packets.iter().map(|p| format!("{p}, ")).collect::<String>(),
+    ))
+}
+
+fn object_layer_to_rust(map_name: &str, object_layer: &MapObjectLayer) -> String {
+    let layer_const = layer_const(map_name, &object_layer.name);
+    format!(
+        "pub const {layer_const}: &[Region] = &[{regions}];\n\
+        pub const {layer_const}_PROPERTIES: Properties = {properties};\n",
+        regions = object_layer
+            .objects
+            .iter()
+            .map(object_to_rust)
+            .collect::<String>(),
+        properties = properties_to_rust(&object_layer.properties),
+    )
+}
+
+fn object_to_rust(object: &MapObject) -> String {
+    let points = object
+        .points
+        .iter()
+        .map(|p| format!("({x}, {y}), ", x = p.h, y = p.v))
+        .collect::<String>();
+    let shape = match object.shape {
+        MapObjectShape::Rectangle => "Shape::Rectangle".to_string(),
+        MapObjectShape::Ellipse => "Shape::Ellipse".to_string(),
+        MapObjectShape::Point => "Shape::Point".to_string(),
+        MapObjectShape::Polyline => format!("Shape::Polyline(&[{points}])"),
+        MapObjectShape::Polygon => format!("Shape::Polygon(&[{points}])"),
+    };
+    format!(
+        "Region {{ \
+        name: {name:?}, \
+        class: {class:?}, \
+        shape: {shape}, \
+        x: {x}, \
+        y: {y}, \
+        w: {w}, \
+        h: {h}, \
+        properties: {properties}, \
+        }}, ",
+        name = object.name,
+        class = object.class,
+        x = object.bounds.left,
+        y = object.bounds.top,
+        w = object.bounds.right - object.bounds.left,
+        h = object.bounds.bottom - object.bounds.top,
+        properties = properties_to_rust(&object.properties),
+    )
+}
+
+/// Rust `Properties` slice.
+fn properties_to_rust(properties: &Properties) -> String {
+    format!(
+        "&[{properties}]",
+        properties = properties
+            .0
+            .iter()
+            .map(|(name, value)| format!(
+                "({name:?}, {value}), ",
+                value = property_value_to_rust(value)
+            ))
+            .collect::<String>(),
+    )
+}
+
+/// Must match `gfx::PropertyValue` in the WASM-4 edition.
+fn property_value_to_rust(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::Bool(x) => format!("PropertyValue::Bool({x})"),
+        PropertyValue::Int(x) => format!("PropertyValue::Int({x})"),
+        PropertyValue::Float(x) => format!("PropertyValue::Float({x})", x = float_rust(*x)),
+        PropertyValue::Color(x) => format!("PropertyValue::Color({x:#010x})"),
+        PropertyValue::String(x) => format!("PropertyValue::Str({x:?})"),
+        PropertyValue::File(x) => format!("PropertyValue::File({x:?})"),
+        PropertyValue::Object(x) => format!("PropertyValue::Object({x})"),
+        PropertyValue::Enum(x) => format!("PropertyValue::Enum({x:?})"),
+    }
+}
+
+/// Rust expression for an `f32`. Debug formatting round-trips, except for the special values.
+fn float_rust(x: f32) -> String {
+    if x.is_nan() {
+        "f32::NAN".to_string()
+    } else if x.is_infinite() {
+        format!("{sign}f32::INFINITY", sign = if x < 0.0 { "-" } else { "" })
+    } else {
+        format!("{x:?}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_properties_to_rust() {
+        let properties = Properties(vec![
+            (
+                "cinematic".to_string(),
+                PropertyValue::String("hi".to_string()),
+            ),
+            ("door".to_string(), PropertyValue::Object(12)),
+            (
+                "facing".to_string(),
+                PropertyValue::Enum("north".to_string()),
+            ),
+            ("locked".to_string(), PropertyValue::Bool(true)),
+            ("pause".to_string(), PropertyValue::Int(60)),
+            ("speed".to_string(), PropertyValue::Float(f32::NEG_INFINITY)),
+            ("tint".to_string(), PropertyValue::Color(0xff102030)),
+        ]);
+        assert_eq!(
+            properties_to_rust(&properties),
+            [
+                r#"&[("cinematic", PropertyValue::Str("hi")), "#,
+                r#"("door", PropertyValue::Object(12)), "#,
+                r#"("facing", PropertyValue::Enum("north")), "#,
+                r#"("locked", PropertyValue::Bool(true)), "#,
+                r#"("pause", PropertyValue::Int(60)), "#,
+                r#"("speed", PropertyValue::Float(-f32::INFINITY)), "#,
+                r#"("tint", PropertyValue::Color(0xff102030)), ]"#,
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_rle_rows() {
+        let values = [1, 1, 1, 2, 0, 0, 0, 0, 3, 4, 5, 5];
+        let (rows, packets) = rle_rows(&values, 4).unwrap();
+        assert_eq!(rows, vec![0, 4, 6]);
+        assert_eq!(packets, vec![129, 1, 0, 2, 130, 0, 1, 3, 4, 128, 5]);
+    }
+
+    #[test]
+    fn test_rle_rows_all_zero() {
+        let (rows, packets) = rle_rows(&[0; 6], 3).unwrap();
+        assert!(rows.is_empty());
+        assert!(packets.is_empty());
+    }
+
+    #[test]
+    fn test_rle_rows_long_repeat() {
+        let (_, packets) = rle_rows(&[7; 300], 300).unwrap();
+        assert_eq!(packets, vec![255, 7, 255, 7, 168, 7]);
+    }
+
+    #[test]
+    fn test_rle_rows_long_literal() {
+        let values = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        let (_, packets) = rle_rows(&values, 200).unwrap();
+        assert_eq!(packets.len(), 202);
+        assert_eq!(packets[0], 127);
+        assert_eq!(packets[129], 71);
+    }
+}
@@ -0,0 +1,319 @@
+//! Import screenplay-style story drafts, like the ones in `notes/story/*.md`, as `.aecinematic` scripts.
+//!
+//! Drafts are Markdown, read a paragraph at a time:
+//!
+//! - `SPEAKER: line` is a line of dialogue. A mood can go after the speaker's name,
+//!   as in `ALLIE, cheerful: Found you!`, or at the start of the line, as in `ESRI: [pleased] Hi.`
+//! - `---` is a scene break, which clears the stage.
+//! - A paragraph that's all `[stage directions]` becomes a comment, as do Markdown headings.
+//! - Any other paragraph is more text for whoever spoke last.
+//!
+//! Speakers who aren't on stage are brought on in an empty slot, left first,
+//! or else in place of whoever spoke least recently.
+//! Bringing someone on stage takes a mood. If the draft doesn't give one,
+//! they come back with the last mood they had, and if they haven't had one yet,
+//! the script gets a `[todo]` mood that won't compile until a writer picks a real one.
+
+use convert_case::{Case, Casing};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+lazy_static! {
+    static ref SCREENPLAY_LINE: Regex =
+        Regex::new(r"^([A-Z][A-Z0-9_ ]*)(?:,\s*([^:]*?))?:\s*(.*)$")
+            .expect("Couldn't compile SCREENPLAY_LINE regex");
+    static ref SCREENPLAY_MOOD: Regex =
+        Regex::new(r"^\[([^\]]+)\]\s*(.*)$").expect("Couldn't compile SCREENPLAY_MOOD regex");
+    static ref SCRIPT_MOOD_NAME: Regex =
+        Regex::new(r"^[a-z0-9_]+$").expect("Couldn't compile SCRIPT_MOOD_NAME regex");
+}
+
+/// Mood for characters whose entrance the draft doesn't give a mood for.
+const TODO_MOOD: &str = "todo";
+
+const SLOTS: [&str; 2] = ["left", "right"];
+
+/// Something a writer should look at in an imported script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// 1-based line number in the draft.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {line}: {message}",
+            line = self.line,
+            message = self.message
+        )
+    }
+}
+
+/// Convert a screenplay draft to a `.aecinematic` script,
+/// and print every line that needs a writer's attention.
+pub fn import(input_path: &Path, output_path: &Path) -> anyhow::Result<()> {
+    let (script, warnings) = convert(&fs::read_to_string(input_path)?);
+    for warning in &warnings {
+        eprintln!("{input}: {warning}", input = input_path.display());
+    }
+    fs::write(
+        output_path,
+        format!(
+            "# Imported from {input}. Check the staging, and replace every [{TODO_MOOD}] mood.\n\n{script}",
+            input = input_path
+                .file_name()
+                .unwrap_or(input_path.as_os_str())
+                .to_string_lossy()
+        ),
+    )?;
+    Ok(())
+}
+
+/// Script source for a draft, and the lines that need a writer's attention.
+fn convert(source: &str) -> (String, Vec<Warning>) {
+    let mut converter = Converter::default();
+    for (line, paragraph) in paragraphs(source) {
+        converter.paragraph(line, &paragraph);
+    }
+    let Converter {
+        statements,
+        warnings,
+        ..
+    } = converter;
+    let mut script = statements.join("\n\n");
+    script.push('\n');
+    (script, warnings)
+}
+
+/// Paragraphs of a draft, with the line number each starts on.
+/// Dialogue lines always start a new paragraph, even without a blank line before them.
+fn paragraphs(source: &str) -> Vec<(usize, String)> {
+    let mut paragraphs = Vec::<(usize, String)>::new();
+    let mut in_paragraph = false;
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            in_paragraph = false;
+            continue;
+        }
+        match paragraphs.last_mut() {
+            Some((_, paragraph)) if in_paragraph && !SCREENPLAY_LINE.is_match(line) => {
+                paragraph.push(' ');
+                paragraph.push_str(line);
+            }
+            _ => paragraphs.push((i + 1, line.to_string())),
+        }
+        in_paragraph = true;
+    }
+    paragraphs
+}
+
+#[derive(Default)]
+struct Converter {
+    statements: Vec<String>,
+    warnings: Vec<Warning>,
+    /// Who's in each slot, and the paragraph they last spoke in.
+    stage: [Option<(String, usize)>; 2],
+    /// Slot of whoever spoke last.
+    speaker: Option<usize>,
+    /// Last mood each character had.
+    moods: BTreeMap<String, String>,
+    /// Paragraphs of dialogue so far.
+    clock: usize,
+}
+
+impl Converter {
+    fn paragraph(&mut self, line: usize, paragraph: &str) {
+        if paragraph == "---" {
+            for (slot, name) in SLOTS.iter().zip(&self.stage) {
+                if name.is_some() {
+                    self.statements.push(format!("!unset {slot}"));
+                }
+            }
+            self.stage = Default::default();
+            self.speaker = None;
+            return;
+        }
+        if paragraph.starts_with('#') || (paragraph.starts_with('[') && paragraph.ends_with(']')) {
+            self.statements
+                .push(format!("# {}", paragraph.trim_start_matches('#').trim()));
+            return;
+        }
+        let Some(captures) = SCREENPLAY_LINE.captures(paragraph) else {
+            self.text(line, paragraph);
+            return;
+        };
+        let name = captures[1].trim().replace(' ', "_");
+        let mut mood = captures.get(2).map(|m| m.as_str());
+        let mut text = captures.get(3).map_or("", |m| m.as_str());
+        if let Some(mood_captures) = SCREENPLAY_MOOD.captures(text) {
+            if mood.is_some() {
+                self.warn(line, format!("{name} has two moods, using the first one"));
+            } else {
+                mood = mood_captures.get(1).map(|m| m.as_str());
+            }
+            text = mood_captures.get(2).map_or("", |m| m.as_str());
+        }
+        let mood = mood.and_then(|mood| self.mood_name(line, mood));
+        self.dialogue(line, &name, mood, text);
+    }
+
+    fn dialogue(&mut self, line: usize, name: &str, mood: Option<String>, text: &str) {
+        self.clock += 1;
+        let on_stage = self
+            .stage
+            .iter()
+            .position(|character| character.as_ref().is_some_and(|(n, _)| n == name));
+        let slot = match on_stage {
+            Some(slot) => {
+                if self.speaker != Some(slot) {
+                    self.statements.push(format!("{name}:"));
+                }
+                if let Some(mood) = mood {
+                    self.statements.push(format!("[{mood}]"));
+                    self.moods.insert(name.to_string(), mood);
+                }
+                slot
+            }
+            None => {
+                let slot = self.entrance_slot();
+                self.statements.push(format!("!set {} …", SLOTS[slot]));
+                self.statements.push(format!("{name}:"));
+                match mood.or_else(|| self.moods.get(name).cloned()) {
+                    Some(mood) => {
+                        self.statements.push(format!("[{mood}]"));
+                        self.moods.insert(name.to_string(), mood);
+                    }
+                    None => {
+                        self.statements.push(format!("# TODO: mood for {name}"));
+                        self.statements.push(format!("[{TODO_MOOD}]"));
+                        self.warn(line, format!("{name} comes on stage and needs a mood"));
+                    }
+                }
+                slot
+            }
+        };
+        self.stage[slot] = Some((name.to_string(), self.clock));
+        self.speaker = Some(slot);
+        self.text(line, text);
+    }
+
+    /// An empty slot if there is one, or else the slot of whoever spoke least recently.
+    fn entrance_slot(&self) -> usize {
+        if let Some(slot) = self.stage.iter().position(Option::is_none) {
+            return slot;
+        }
+        (0..self.stage.len())
+            .min_by_key(|slot| self.stage[*slot].as_ref().map_or(0, |(_, clock)| *clock))
+            .unwrap_or(0)
+    }
+
+    fn text(&mut self, line: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if text.starts_with('!')
+            || text.starts_with('#')
+            || SCREENPLAY_LINE.is_match(text)
+            || SCREENPLAY_MOOD.is_match(text)
+        {
+            self.warn(
+                line,
+                format!("Text would be read as something else in a script: {text}"),
+            );
+        }
+        self.statements.push(text.to_string());
+    }
+
+    /// Script mood name for a draft's mood annotation, like `mouth_open` for `mouth open`.
+    fn mood_name(&mut self, line: usize, mood: &str) -> Option<String> {
+        let name = mood.trim().to_case(Case::Snake);
+        if SCRIPT_MOOD_NAME.is_match(&name) {
+            return Some(name);
+        }
+        self.warn(line, format!("Can't turn {mood:?} into a mood name"));
+        None
+    }
+
+    fn warn(&mut self, line: usize, message: String) {
+        self.warnings.push(Warning { line, message });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let (script, warnings) = convert(
+            "[ESRI wakes up.]\n\
+             \n\
+             ESRI: Gods, what a night.\n\
+             \n\
+             SAE, mouth open: You're in my bed.\n\
+             \n\
+             ESRI: [pleased] Moonshine\n\
+             and pixie dust.\n\
+             \n\
+             ALLIE: Found you!\n\
+             \n\
+             ESRI: Hi.\n\
+             \n\
+             SAE: Who?\n\
+             \n\
+             ---\n\
+             \n\
+             ALLIE: Cheers!\n",
+        );
+        assert_eq!(
+            script,
+            [
+                "# [ESRI wakes up.]",
+                "!set left …",
+                "ESRI:",
+                "# TODO: mood for ESRI",
+                "[todo]",
+                "Gods, what a night.",
+                "!set right …",
+                "SAE:",
+                "[mouth_open]",
+                "You're in my bed.",
+                "ESRI:",
+                "[pleased]",
+                "Moonshine and pixie dust.",
+                "!set right …",
+                "ALLIE:",
+                "# TODO: mood for ALLIE",
+                "[todo]",
+                "Found you!",
+                "ESRI:",
+                "Hi.",
+                "!set right …",
+                "SAE:",
+                "[mouth_open]",
+                "Who?",
+                "!unset left",
+                "!unset right",
+                "!set left …",
+                "ALLIE:",
+                "# TODO: mood for ALLIE",
+                "[todo]",
+                "Cheers!",
+            ]
+            .join("\n\n")
+                + "\n"
+        );
+        assert_eq!(
+            warnings.iter().map(|w| w.line).collect::<Vec<_>>(),
+            vec![3, 10, 18]
+        );
+    }
+}
@@ -8,7 +8,7 @@ use image::{GrayAlphaImage, LumaA};
 use std::fs;
 use std::path::Path;
 
-fn encode_image(image: &GrayAlphaImage) -> Unisprite<Vec<u8>> {
+pub(crate) fn encode_image(image: &GrayAlphaImage) -> Unisprite<Vec<u8>> {
     let mut quantizer = GreyQuantizer::new();
     for LumaA([l, _]) in image.pixels().cloned() {
         quantizer.count_pixel(l);
@@ -29,7 +29,7 @@ fn encode_image(image: &GrayAlphaImage) -> Unisprite<Vec<u8>> {
 
     Unisprite {
         w: image.width() as i32,
-        h: image.width() as i32,
+        h: image.height() as i32,
         luma: luma.into_vec(),
         alpha: alpha.into_vec(),
     }